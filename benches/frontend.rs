@@ -0,0 +1,53 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use pascal_compiler::driver::compile_file;
+use pascal_compiler::generator::synthetic_program;
+use pascal_compiler::io::CharReader;
+use pascal_compiler::lexer::Lexer;
+use pascal_compiler::options::Options;
+use std::path::PathBuf;
+
+const SIZES: [usize; 3] = [100, 1000, 10000];
+
+// The front end reads sources from files, so inputs are written once up front
+fn write_input(n: usize) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("pascal-compiler-bench-{}.pas", n));
+    std::fs::write(&path, synthetic_program(n)).expect("failed to write bench input");
+    path
+}
+
+fn lexer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lexer");
+
+    for n in SIZES {
+        let path = write_input(n);
+        let filename = path.to_string_lossy().to_string();
+
+        group.bench_with_input(BenchmarkId::from_parameter(n), &filename, |b, f| {
+            b.iter(|| {
+                let reader = CharReader::new(f.clone()).unwrap();
+                Lexer::new(reader).count()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn parser(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parser");
+    let options = Options::default();
+
+    for n in SIZES {
+        let path = write_input(n);
+        let filename = path.to_string_lossy().to_string();
+
+        group.bench_with_input(BenchmarkId::from_parameter(n), &filename, |b, f| {
+            b.iter(|| compile_file(f, &options).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, lexer, parser);
+criterion_main!(benches);
@@ -1,153 +1,2014 @@
+use crate::const_eval::{ordinal_range, ConstEvaluator, ConstValue};
 use crate::error::CompilerError;
-use crate::scope::{Scope, Usage};
+use crate::options::{Conformance, Options};
+use crate::scope::{
+    Param, Scope, ScopeId, ScopeTree, Signature, Symbol, SymbolId, TypeDef, Usage, VariantFields,
+    VariantLayout,
+};
 use crate::syntax::*;
+use crate::unit_files;
+use crate::units;
+use std::collections::BTreeSet;
+
+/// Type of `[]`, which fits every set type
+pub const EMPTY_SET: &str = "[]";
+
+/// Type of `nil`, which fits every pointer type
+pub const NIL: &str = "nil";
+
+// Parameter type of `new` and `dispose`, any pointer variable is accepted
+const ANY_POINTER: &str = "^";
+// Parameter type of builtins which take a value or variable of any ordinal type
+const ANY_ORDINAL: &str = "ordinal";
+// Parameter type of abs and sqr, an integer or a real value
+const ANY_NUMBER: &str = "number";
+// Parameters of write and writeln, and of read and readln
+const ANY_WRITABLE: &str = "writable";
+const ANY_READABLE: &str = "readable";
+// Type of `input` and `output`, a first argument of this type names the file to use
+const TEXT_FILE: &str = "text";
+// Longest Turbo Pascal string, it keeps the length in one byte
+pub const MAX_STRING_LENGTH: i32 = 255;
+
+const VAR_ARGUMENT_NOTE: &str =
+    "a VAR parameter refers to the argument itself, so their types must be identical";
+
+const DISTINCT_TYPES_NOTE: &str =
+    "records and arrays declared apart are different types, even with the same structure";
+
+const CASE_ORDINAL_NOTE: &str =
+    "case needs integer, char, boolean or enumeration values, test strings and reals with if";
 
 pub struct Analyzer {
-    scopes: Vec<Scope>,
+    tree: ScopeTree,
+    // Currently open scopes, innermost last
+    scopes: Vec<ScopeId>,
+    pub(crate) options: Options,
+    // Procedures and functions whose bodies are being parsed, innermost last
+    routines: Vec<Option<SymbolId>>,
+    // Control variables of the `for` loops whose bodies are being parsed, nothing may change them
+    loop_variables: Vec<Option<SymbolId>>,
+    // Routines declared `forward` whose bodies haven't been seen yet
+    forwards: Vec<Forward>,
+    // Units whose compilation led here, the current one last. Using any of
+    // them again would never finish.
+    pub(crate) loading: Vec<String>,
+}
+
+// Heading of a forward declaration, a body written without one takes it from here
+struct Forward {
+    symbol: SymbolId,
+    params: Vec<FormalParameter>,
+    result_type: Option<Identifier>,
+    // Heading from the interface of a unit, its body is due in the implementation
+    interface: bool,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Analyzer {
     pub fn new() -> Self {
-        Self {
-            scopes: Vec::from([Scope::default()]),
+        Self::with_options(&Options::default())
+    }
+
+    /// Analyzer with the builtins `options` allow, e.g. no `string` in ISO 7185 mode
+    pub fn with_options(options: &Options) -> Self {
+        let mut analyzer = Self {
+            tree: ScopeTree::new(),
+            scopes: Vec::from([ScopeId(0)]),
+            options: options.clone(),
+            routines: Vec::new(),
+            loop_variables: Vec::new(),
+            forwards: Vec::new(),
+            loading: Vec::new(),
+        };
+
+        let builtins = [
+            ("integer", Usage::Type(None)),
+            ("real", Usage::Type(None)),
+            ("char", Usage::Type(None)),
+            ("boolean", Usage::Type(None)),
+            ("true", Usage::Constant("boolean".into())),
+            ("false", Usage::Constant("boolean".into())),
+        ];
+
+        for (name, usage) in builtins {
+            analyzer.declare(name.into(), usage, (0, 0));
+        }
+        if options.conformance != Conformance::Iso7185 {
+            analyzer.declare("string".into(), Usage::Type(None), (0, 0));
+        }
+
+        // Standard functions of one value. A result of the parameter's kind (abs, sqr,
+        // succ, pred) is of the argument's type.
+        for (name, param, result) in [
+            ("abs", ANY_NUMBER, ANY_NUMBER),
+            ("sqr", ANY_NUMBER, ANY_NUMBER),
+            ("sqrt", "real", "real"),
+            ("sin", "real", "real"),
+            ("cos", "real", "real"),
+            // Towards zero and to the nearest integer
+            ("trunc", "real", "integer"),
+            ("round", "real", "integer"),
+            ("ord", ANY_ORDINAL, "integer"),
+            ("chr", "integer", "char"),
+            ("succ", ANY_ORDINAL, ANY_ORDINAL),
+            ("pred", ANY_ORDINAL, ANY_ORDINAL),
+            ("odd", "integer", "boolean"),
+        ] {
+            let signature = Signature {
+                params: vec![Param {
+                    type_name: param.into(),
+                    by_reference: false,
+                }],
+                result: Some(result.into()),
+                optional: 0,
+                rest: false,
+            };
+            analyzer.declare(name.into(), Usage::Function(signature), (0, 0));
+        }
+
+        // Allocate and free the target of a pointer
+        for name in ["new", "dispose"] {
+            let signature = Signature {
+                params: vec![Param {
+                    type_name: ANY_POINTER.into(),
+                    by_reference: true,
+                }],
+                result: None,
+                optional: 0,
+                rest: false,
+            };
+            analyzer.declare(name.into(), Usage::Procedure(signature), (0, 0));
+        }
+
+        // Step an ordinal variable, by one unless told otherwise
+        if options.conformance != Conformance::Iso7185 {
+            for name in ["inc", "dec"] {
+                let signature = Signature {
+                    params: vec![
+                        Param {
+                            type_name: ANY_ORDINAL.into(),
+                            by_reference: true,
+                        },
+                        Param {
+                            type_name: "integer".into(),
+                            by_reference: false,
+                        },
+                    ],
+                    result: None,
+                    optional: 1,
+                    rest: false,
+                };
+                analyzer.declare(name.into(), Usage::Procedure(signature), (0, 0));
+            }
+        }
+
+        // Text input and output. After the optional file any number of values follow.
+        for name in ["input", "output"] {
+            analyzer.declare(name.into(), Usage::Variable(TEXT_FILE.into()), (0, 0));
+        }
+        for (name, type_name, by_reference) in [
+            ("write", ANY_WRITABLE, false),
+            ("writeln", ANY_WRITABLE, false),
+            ("read", ANY_READABLE, true),
+            ("readln", ANY_READABLE, true),
+        ] {
+            let signature = Signature {
+                params: vec![Param {
+                    type_name: type_name.into(),
+                    by_reference,
+                }],
+                result: None,
+                optional: 1,
+                rest: true,
+            };
+            analyzer.declare(name.into(), Usage::Procedure(signature), (0, 0));
+        }
+
+        for (name, value) in [("true", true), ("false", false)] {
+            if let Some(symbol) = analyzer.current_scope().get(name.into()) {
+                analyzer.tree.symbols[symbol.0].value = Some(ConstValue::Boolean(value));
+            }
+        }
+
+        analyzer
+    }
+
+    /// Continues analysis inside `scope` of a tree analyzed before, e.g. to check
+    /// an expression typed while the program is stopped in a procedure
+    pub fn resume_scope(&mut self, tree: ScopeTree, scope: ScopeId) {
+        let mut scopes = Vec::new();
+        let mut current = Some(scope);
+
+        while let Some(s) = current {
+            scopes.push(s);
+            current = tree.scope(s).parent;
+        }
+        scopes.reverse();
+
+        self.tree = tree;
+        self.scopes = scopes;
+    }
+
+    pub fn enter_scope(&mut self, pos: (usize, usize)) -> Result<(), CompilerError> {
+        // Scope of builtins doesn't count
+        if self.scopes.len() > self.options.max_scope_depth {
+            return Err(CompilerError::semantic(format!(
+                "Scopes are nested too deeply (limit is {})",
+                self.options.max_scope_depth
+            ))
+            .with_span(pos));
+        }
+
+        let parent = self.scopes.last().copied();
+        let scope = ScopeId(self.tree.scopes.len());
+
+        self.tree.scopes.push(Scope::new(parent, pos));
+        self.scopes.push(scope);
+        Ok(())
+    }
+
+    pub fn leave_scope(&mut self, pos: (usize, usize)) {
+        if let Some(scope) = self.scopes.pop() {
+            self.tree.scopes[scope.0].end = Some(pos);
+        }
+    }
+
+    /// Hand over all scopes and symbols collected so far (e.g. to attach them to the AST)
+    pub fn take_scope_tree(&mut self) -> ScopeTree {
+        std::mem::take(&mut self.tree)
+    }
+
+    fn current_scope(&mut self) -> &mut Scope {
+        let len = self.scopes.len();
+        &mut self.tree.scopes[self.scopes[len - 1].0]
+    }
+
+    /// Add symbol to the symbol table and bind its name in the current scope
+    fn declare(&mut self, name: String, usage: Usage, pos: (usize, usize)) -> SymbolId {
+        let symbol = SymbolId(self.tree.symbols.len());
+        self.tree.symbols.push(Symbol {
+            name: name.clone(),
+            usage,
+            value: None,
+            pos,
+        });
+
+        self.current_scope().insert(name, symbol);
+        symbol
+    }
+
+    /// Declare identifier in the current scope unless it is already there
+    fn declare_identifier(
+        &mut self,
+        id: &mut Identifier,
+        usage: Usage,
+    ) -> Result<(), CompilerError> {
+        let str = id.get_id();
+
+        match self.current_scope().get(str.clone()) {
+            Some(prev) => Err(
+                CompilerError::semantic(format!("Redeclaration of {:?}", str))
+                    .with_span(id.id.pos)
+                    .with_label(self.symbol(prev).pos, "first declared here"),
+            ),
+            None => {
+                id.symbol = Some(self.declare(str, usage, id.id.pos));
+                Ok(())
+            }
         }
     }
 
-    pub fn enter_scope(&mut self) {
-        self.scopes.push(Scope::new());
+    pub fn symbol(&self, symbol: SymbolId) -> &Symbol {
+        self.tree.symbol(symbol)
     }
 
-    pub fn leave_scope(&mut self) {
-        self.scopes.pop();
+    pub fn check_program_name(&mut self, mut id: Identifier) -> Result<Identifier, CompilerError> {
+        self.declare_identifier(&mut id, Usage::Program)?;
+        Ok(id)
+    }
+
+    pub fn check_unit_name(&mut self, mut id: Identifier) -> Result<Identifier, CompilerError> {
+        self.declare_identifier(&mut id, Usage::Unit)?;
+        self.loading.push(id.get_id());
+        Ok(id)
     }
 
     /// Check if identifier is already defined and add in case it is not
     pub fn check_var_declaration(
         &mut self,
-        decl: VarDeclaration,
+        mut decl: VarDeclaration,
     ) -> Result<VarDeclaration, CompilerError> {
-        let len = self.scopes.len();
-        let cur_scope = &mut self.scopes[len - 1];
-        let str = decl.id.get_id();
+        let usage = Usage::Variable(decl.type_name.type_name());
+        self.declare_identifier(&mut decl.id, usage)?;
+        Ok(decl)
+    }
 
-        match cur_scope.get(str.clone()) {
-            Some(_) => Err(CompilerError::semantic(
-                format!("Redeclaration of {:?}", str),
-                decl.id.id.pos,
-            )),
-            None => {
-                cur_scope.insert(&decl.id, Usage::Variable(decl.type_name.get_id()));
-                Ok(decl)
-            }
+    /// Constants get their value right away, so later constants and case labels can use it
+    pub fn check_const_declaration(
+        &mut self,
+        mut decl: ConstDeclaration,
+    ) -> Result<ConstDeclaration, CompilerError> {
+        let value = ConstEvaluator::new(&self.tree, &self.options).eval_expr(&decl.value)?;
+        self.declare_identifier(&mut decl.id, Usage::Constant(value.type_name().into()))?;
+
+        if let Some(symbol) = decl.id.symbol {
+            self.tree.symbols[symbol.0].value = Some(value);
         }
+        Ok(decl)
     }
 
     pub fn check_type_declaration(
         &mut self,
-        decl: TypeDeclaration,
+        mut decl: TypeDeclaration,
     ) -> Result<TypeDeclaration, CompilerError> {
-        let len = self.scopes.len();
-        let cur_scope = &mut self.scopes[len - 1];
-        let str = decl.id.get_id();
+        let usage = Usage::Type(Some(decl.parent.type_name()));
+        self.declare_identifier(&mut decl.id, usage)?;
+        Ok(decl)
+    }
 
-        match cur_scope.get(str.clone()) {
-            Some(_) => Err(CompilerError::semantic(
-                format!("Redeclaration of {:?}", str),
-                decl.id.id.pos,
-            )),
-            None => {
-                cur_scope.insert(&decl.id, Usage::Type(Some(decl.parent.get_id())));
-                Ok(decl)
+    /// Named types must be declared types. Array bounds must be constants of the
+    /// same ordinal type with low <= high, record fields must have distinct names,
+    /// set elements must be ordinal. Enumerators are declared as constants.
+    /// Pointer targets may be unknown yet, see `check_pointer_targets`.
+    /// Returns the name of the type.
+    pub fn check_type_spec(&mut self, spec: &mut TypeSpec) -> Result<String, CompilerError> {
+        match spec {
+            TypeSpec::Named(id) => match self.resolve_identifier(id)? {
+                Usage::Type(_) => Ok(id.get_id()),
+                _ => Err(CompilerError::semantic("Identifier is not a type").with_span(id.id.pos)),
+            },
+            TypeSpec::Array(array) => self.check_array_type(array),
+            TypeSpec::Record(record) => self.check_record_type(record),
+            TypeSpec::Set(set) => self.check_set_type(set),
+            TypeSpec::Enum(enumeration) => self.check_enum_type(enumeration),
+            TypeSpec::String(string) => self.check_string_type(string),
+            TypeSpec::Subrange(subrange) => self.check_subrange_type(subrange),
+            TypeSpec::Pointer(pointer) => {
+                // Bound now if declared already, the rest when the section ends
+                let _ = self.resolve_identifier(&mut pointer.target);
+                let target = pointer.target.get_id();
+                let name = format!("^{}", target);
+
+                self.tree
+                    .types
+                    .insert(name.clone(), TypeDef::Pointer { target });
+                pointer.type_name = name.clone();
+                Ok(name)
+            }
+        }
+    }
+
+    /// Pointer targets in `spec` must be declared types by the end of the section
+    /// which uses them, so `type PNode = ^Node; Node = record ... end` works
+    pub fn check_pointer_targets(&self, spec: &mut TypeSpec) -> Result<(), CompilerError> {
+        match spec {
+            TypeSpec::Pointer(pointer) => match self.resolve_identifier(&mut pointer.target)? {
+                Usage::Type(_) => Ok(()),
+                _ => Err(CompilerError::semantic("Identifier is not a type")
+                    .with_span(pointer.target.id.pos)),
+            },
+            TypeSpec::Array(array) => self.check_pointer_targets(&mut array.element),
+            TypeSpec::Set(set) => self.check_pointer_targets(&mut set.element),
+            TypeSpec::Record(record) => {
+                for f in record.fields.iter_mut() {
+                    self.check_pointer_targets(&mut f.type_name)?;
+                }
+                match record.variant.as_mut() {
+                    Some(part) => self.check_variant_pointer_targets(part),
+                    None => Ok(()),
+                }
+            }
+            TypeSpec::Named(_)
+            | TypeSpec::Enum(_)
+            | TypeSpec::String(_)
+            | TypeSpec::Subrange(_) => Ok(()),
+        }
+    }
+
+    fn check_variant_pointer_targets(&self, part: &mut VariantPart) -> Result<(), CompilerError> {
+        for variant in part.variants.iter_mut() {
+            for f in variant.fields.iter_mut() {
+                self.check_pointer_targets(&mut f.type_name)?;
+            }
+            if let Some(nested) = variant.variant.as_deref_mut() {
+                self.check_variant_pointer_targets(nested)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Type the pointer type points to, aliases of the target followed
+    fn pointer_target(&self, type_name: &str) -> Option<String> {
+        // Pointer types made by `@` aren't declared anywhere, so go by the name
+        let resolved = self.resolve_type(type_name);
+        resolved.strip_prefix('^').map(|t| self.resolve_type(t))
+    }
+
+    /// Each enumeration is a type of its own, even when spelled like another one.
+    /// Its name is short, `(a, b, c)#1` or `(a, .., z)#2`, the values are kept in the
+    /// type definition.
+    fn check_enum_type(&mut self, enumeration: &mut EnumType) -> Result<String, CompilerError> {
+        let values: Vec<String> = enumeration.values.iter().map(|v| v.get_id()).collect();
+        let spelled = match values.as_slice() {
+            [first, _, _, .., last] => format!("{}, .., {}", first, last),
+            _ => values.join(", "),
+        };
+        let number = 1 + self
+            .tree
+            .types
+            .values()
+            .filter(|t| matches!(t, TypeDef::Enum { .. }))
+            .count();
+        let name = format!("({})#{}", spelled, number);
+
+        for (ordinal, id) in enumeration.values.iter_mut().enumerate() {
+            self.declare_identifier(id, Usage::Constant(name.clone()))?;
+
+            if let Some(symbol) = id.symbol {
+                self.tree.symbols[symbol.0].value = Some(ConstValue::Enum {
+                    type_name: name.clone(),
+                    name: id.get_id(),
+                    ordinal: ordinal as i32,
+                });
+            }
+        }
+
+        self.tree
+            .types
+            .insert(name.clone(), TypeDef::Enum { values });
+        enumeration.type_name = name.clone();
+        Ok(name)
+    }
+
+    /// Smallest and largest ordinal value of a builtin ordinal type, an enumeration
+    /// or a subrange
+    fn ordinal_range(&self, type_name: &str) -> Option<(i32, i32)> {
+        match self.tree.type_def(type_name) {
+            Some(TypeDef::Enum { values }) => Some((0, values.len() as i32 - 1)),
+            Some(TypeDef::Subrange { low, high, .. }) => Some((low.ordinal(), high.ordinal())),
+            _ => ordinal_range(type_name),
+        }
+    }
+
+    /// First and last value of an ordinal type, None for other types
+    fn ordinal_bounds(&self, type_name: &str) -> Option<(ConstValue, ConstValue)> {
+        let name = self.resolve_alias(type_name);
+
+        match self.tree.type_def(&name) {
+            Some(TypeDef::Subrange { low, high, .. }) => Some((low.clone(), high.clone())),
+            Some(TypeDef::Enum { values }) => {
+                let value = |ordinal: usize| ConstValue::Enum {
+                    type_name: name.clone(),
+                    name: values[ordinal].clone(),
+                    ordinal: ordinal as i32,
+                };
+                Some((value(0), value(values.len() - 1)))
+            }
+            _ => {
+                let (low, high) = ordinal_range(&name)?;
+                match name.as_str() {
+                    "boolean" => Some((ConstValue::Boolean(false), ConstValue::Boolean(true))),
+                    "char" => Some((
+                        ConstValue::Char(char::from_u32(low as u32)?),
+                        ConstValue::Char(char::from_u32(high as u32)?),
+                    )),
+                    _ => Some((ConstValue::Integer(low), ConstValue::Integer(high))),
+                }
+            }
+        }
+    }
+
+    /// Bounds are constants of one ordinal type, the host, and must be in order
+    fn check_subrange_type(
+        &mut self,
+        subrange: &mut SubrangeType,
+    ) -> Result<String, CompilerError> {
+        let evaluator = ConstEvaluator::new(&self.tree, &self.options);
+        let low = evaluator.eval_simple_expr(&subrange.low)?;
+        let high = evaluator.eval_simple_expr(&subrange.high)?;
+        let host = low.type_name().to_string();
+
+        if self.ordinal_range(&host).is_none() {
+            return Err(CompilerError::semantic(format!(
+                "Subrange bounds must be of an ordinal type, found {}",
+                host
+            ))
+            .with_span(subrange.low.pos()));
+        }
+
+        if high.type_name() != host {
+            return Err(CompilerError::semantic(format!(
+                "Upper bound of the subrange must be {}, found {}",
+                host,
+                high.type_name()
+            ))
+            .with_span(subrange.high.pos()));
+        }
+
+        if low.ordinal() > high.ordinal() {
+            return Err(CompilerError::semantic(format!(
+                "Subrange {:?}..{:?} is empty",
+                low, high
+            ))
+            .with_span(subrange.low.pos()));
+        }
+
+        let name = format!("{:?}..{:?}", low, high);
+        self.tree
+            .types
+            .insert(name.clone(), TypeDef::Subrange { host, low, high });
+        subrange.type_name = name.clone();
+        Ok(name)
+    }
+
+    /// Bounds of `type_name` if it is a subrange, or an alias of one
+    fn subrange_bounds(&self, type_name: &str) -> Option<(ConstValue, ConstValue)> {
+        match self.tree.type_def(&self.resolve_alias(type_name)) {
+            Some(TypeDef::Subrange { low, high, .. }) => Some((low.clone(), high.clone())),
+            _ => None,
+        }
+    }
+
+    fn check_set_type(&mut self, set: &mut SetType) -> Result<String, CompilerError> {
+        let element = self.check_type_spec(&mut set.element)?;
+        let element = self.resolve_type(&element);
+
+        if self.ordinal_range(&element).is_none() {
+            return Err(CompilerError::semantic(format!(
+                "Set element type must be ordinal, found {}",
+                element
+            ))
+            .with_span(set.element.pos()));
+        }
+
+        let name = format!("set of {}", element);
+
+        self.tree
+            .types
+            .insert(name.clone(), TypeDef::Set { element });
+        set.type_name = name.clone();
+        Ok(name)
+    }
+
+    fn check_string_type(&mut self, string: &mut StringType) -> Result<String, CompilerError> {
+        match self.resolve_identifier(&mut string.name)? {
+            Usage::Type(_) if self.resolve_type(&string.name.get_id()) == "string" => (),
+            _ => {
+                return Err(CompilerError::semantic(format!(
+                    "Only strings have a length, {:?} is not the string type",
+                    string.name.get_id()
+                ))
+                .with_span(string.name.id.pos))
+            }
+        }
+
+        let length =
+            ConstEvaluator::new(&self.tree, &self.options).eval_simple_expr(&string.length)?;
+        match length {
+            ConstValue::Integer(n @ 1..=MAX_STRING_LENGTH) => {
+                string.type_name = format!("string[{}]", n);
+                Ok(string.type_name.clone())
+            }
+            _ => Err(CompilerError::semantic(format!(
+                "String length must be an integer from 1 to {}, found {:?}",
+                MAX_STRING_LENGTH, length
+            ))
+            .with_span(string.length.pos())),
+        }
+    }
+
+    /// Declares what the unit exports among the builtins, so the program may
+    /// redeclare any of it. Units which aren't builtin are compiled from their
+    /// source on the unit path.
+    pub fn use_unit(&mut self, unit: &Identifier) -> Result<(), CompilerError> {
+        let name = unit.get_id();
+        if let Some(items) = units::unit(&name) {
+            for item in items {
+                self.declare_builtin(item.name.into(), item.usage, item.value);
+            }
+            return Ok(());
+        }
+
+        if self.loading.iter().any(|u| u.eq_ignore_ascii_case(&name)) {
+            return Err(CompilerError::semantic(format!(
+                "Circular unit reference {} -> {}",
+                self.loading.join(" -> "),
+                name
+            ))
+            .with_span(unit.id.pos));
+        }
+
+        let Some(path) = unit_files::find(&name, &self.options) else {
+            return Err(CompilerError::semantic(format!(
+                "Unknown unit {:?}, there is no {}.pas on the unit path and the builtin units are {}",
+                name,
+                name,
+                units::UNIT_NAMES
+            ))
+            .with_span(unit.id.pos));
+        };
+
+        let interface = unit_files::load(&name, &path, &self.options, &self.loading)
+            .map_err(|e| e.with_span(unit.id.pos))?;
+
+        for (id, symbol) in interface.exports.iter() {
+            let symbol = interface.tree.symbol(*symbol);
+            if symbol.usage != Usage::Unit {
+                self.declare_builtin(id.clone(), symbol.usage.clone(), symbol.value.clone());
+            }
+        }
+
+        // Record and array types go by their structure, the same name is the same type
+        for (name, type_def) in interface.tree.types {
+            self.tree.types.entry(name).or_insert(type_def);
+        }
+
+        Ok(())
+    }
+
+    // Symbol from a unit in the scope of builtins, it has no place in the source
+    fn declare_builtin(&mut self, name: String, usage: Usage, value: Option<ConstValue>) {
+        let symbol = SymbolId(self.tree.symbols.len());
+        self.tree.symbols.push(Symbol {
+            name: name.clone(),
+            usage,
+            value,
+            pos: (0, 0),
+        });
+        self.tree.scopes[0].insert(name, symbol);
+    }
+
+    /// Names declared in the innermost scope so far, e.g. the interface of a unit
+    pub fn current_symbols(&self) -> Vec<(String, SymbolId)> {
+        let scope = self.scopes[self.scopes.len() - 1];
+        self.tree.scopes[scope.0]
+            .identifiers()
+            .map(|(id, symbol)| (id.to_string(), symbol))
+            .collect()
+    }
+
+    fn check_array_type(&mut self, array: &mut ArrayType) -> Result<String, CompilerError> {
+        // A subrange checks its own bounds
+        let index = self.check_type_spec(&mut array.index)?;
+        let (low, high) = self.ordinal_bounds(&index).ok_or_else(|| {
+            CompilerError::semantic(format!(
+                "Array index must be of an ordinal type, found {}",
+                index
+            ))
+            .with_span(array.index.pos())
+        })?;
+        let index_type = low.type_name().to_string();
+
+        let element = self.check_type_spec(&mut array.element)?;
+        let name = format!("array[{:?}..{:?}] of {}", low, high, element);
+
+        self.tree.types.insert(
+            name.clone(),
+            TypeDef::Array {
+                index_type,
+                low,
+                high,
+                element,
+            },
+        );
+        array.type_name = name.clone();
+        Ok(name)
+    }
+
+    fn check_record_type(&mut self, record: &mut RecordType) -> Result<String, CompilerError> {
+        let mut fields = Vec::new();
+        let mut positions = Vec::new();
+        let mut spelled = self.check_fields(&mut record.fields, &mut fields, &mut positions)?;
+
+        let variant = match record.variant.as_mut() {
+            Some(part) => {
+                let (layout, part_spelled) =
+                    self.check_variant_part(part, &mut fields, &mut positions)?;
+                spelled.push(part_spelled);
+                Some(layout)
+            }
+            None => None,
+        };
+
+        let name = match spelled.is_empty() {
+            true => "record end".into(),
+            false => format!("record {} end", spelled.join("; ")),
+        };
+
+        self.tree
+            .types
+            .insert(name.clone(), TypeDef::Record { fields, variant });
+        record.type_name = name.clone();
+        Ok(name)
+    }
+
+    /// Adds `decls` to the fields of a record, `positions` tells where each field was
+    /// declared. Returns the fields as they are spelled in the record's name.
+    fn check_fields(
+        &mut self,
+        decls: &mut [FieldDeclaration],
+        fields: &mut Vec<(String, String)>,
+        positions: &mut Vec<(usize, usize)>,
+    ) -> Result<Vec<String>, CompilerError> {
+        let mut spelled = Vec::new();
+
+        for decl in decls.iter_mut() {
+            check_field_name(&decl.id, fields, positions)?;
+            let field_type = self.check_type_spec(&mut decl.type_name)?;
+
+            spelled.push(format!("{} : {}", decl.id.get_id(), field_type));
+            fields.push((decl.id.get_id(), field_type));
+            positions.push(decl.id.id.pos);
+        }
+
+        Ok(spelled)
+    }
+
+    /// Fields of the variants follow those before the part, names are unique across
+    /// the whole record. The tag type and labels were checked while parsing.
+    fn check_variant_part(
+        &mut self,
+        part: &mut VariantPart,
+        fields: &mut Vec<(String, String)>,
+        positions: &mut Vec<(usize, usize)>,
+    ) -> Result<(VariantLayout, String), CompilerError> {
+        let tag_type = part.tag_type.get_id();
+
+        let tag = match &part.tag {
+            Some(id) => {
+                check_field_name(id, fields, positions)?;
+                fields.push((id.get_id(), tag_type.clone()));
+                positions.push(id.id.pos);
+                Some(fields.len() - 1)
+            }
+            None => None,
+        };
+
+        let mut variants = Vec::new();
+        let mut spelled = Vec::new();
+
+        for variant in part.variants.iter_mut() {
+            // Labels which don't evaluate have been reported already
+            let evaluator = ConstEvaluator::new(&self.tree, &self.options);
+            let labels: Vec<(ConstValue, ConstValue)> = variant
+                .labels
+                .iter()
+                .filter_map(|label| {
+                    let low = evaluator.eval_simple_expr(&label.low).ok()?;
+                    let high = match &label.high {
+                        Some(high) => evaluator.eval_simple_expr(high).ok()?,
+                        None => low.clone(),
+                    };
+                    Some((low, high))
+                })
+                .collect();
+
+            let start = fields.len();
+            let mut own = self.check_fields(&mut variant.fields, fields, positions)?;
+            let nested = match variant.variant.as_deref_mut() {
+                Some(nested) => {
+                    let (layout, nested_spelled) =
+                        self.check_variant_part(nested, fields, positions)?;
+                    own.push(nested_spelled);
+                    Some(layout)
+                }
+                None => None,
+            };
+
+            let spelled_labels: Vec<String> = labels
+                .iter()
+                .map(|(low, high)| match low == high {
+                    true => format!("{:?}", low),
+                    false => format!("{:?}..{:?}", low, high),
+                })
+                .collect();
+            spelled.push(format!(
+                "{}: ({})",
+                spelled_labels.join(", "),
+                own.join("; ")
+            ));
+
+            variants.push(VariantFields {
+                labels,
+                fields: start..fields.len(),
+                nested,
+            });
+        }
+
+        let head = match &part.tag {
+            Some(id) => format!("{} : {}", id.get_id(), tag_type),
+            None => tag_type.clone(),
+        };
+        let layout = VariantLayout {
+            tag,
+            tag_type,
+            variants,
+        };
+
+        Ok((layout, format!("case {} of {}", head, spelled.join("; "))))
+    }
+
+    /// Tag type of a variant part must be an ordinal type, returns it with aliases followed
+    pub fn check_variant_tag(
+        &mut self,
+        tag_type: &mut Identifier,
+    ) -> Result<String, CompilerError> {
+        if !matches!(self.resolve_identifier(tag_type)?, Usage::Type(_)) {
+            return Err(
+                CompilerError::semantic("Identifier is not a type").with_span(tag_type.id.pos)
+            );
+        }
+
+        let resolved = self.resolve_type(&tag_type.get_id());
+        match self.ordinal_range(&resolved) {
+            Some(_) => Ok(resolved),
+            None => Err(CompilerError::semantic(format!(
+                "Tag of a variant part must be of an ordinal type, found {}",
+                resolved
+            ))
+            .with_span(tag_type.id.pos)
+            .with_note(CASE_ORDINAL_NOTE)),
+        }
+    }
+
+    /// Elements of a set constructor must all be of the same ordinal type
+    pub fn check_set_constructor(&self, set: &mut SetConstructor) -> Result<(), CompilerError> {
+        let mut element_type: Option<String> = None;
+
+        for e in set
+            .elements
+            .iter()
+            .flat_map(|e| [Some(&e.low), e.high.as_ref()])
+        {
+            let Some(e) = e else { continue };
+            let found = self.resolve_type(&self.expr_type(e));
+
+            match &element_type {
+                None if self.ordinal_range(&found).is_none() => {
+                    return Err(CompilerError::semantic(format!(
+                        "Set element must be of an ordinal type, found {}",
+                        found
+                    ))
+                    .with_span(e.pos()))
+                }
+                None => element_type = Some(found),
+                Some(expected) if *expected != found => {
+                    return Err(CompilerError::semantic(format!(
+                        "Set element must be {}, found {}",
+                        expected, found
+                    ))
+                    .with_span(e.pos()))
+                }
+                Some(_) => (),
+            }
+        }
+
+        set.set_type = match element_type {
+            Some(element) => format!("set of {}", element),
+            None => EMPTY_SET.into(),
+        };
+        Ok(())
+    }
+
+    fn is_set(&self, type_name: &str) -> bool {
+        // Constructors make set types nobody declared, so go by the name
+        let resolved = self.resolve_type(type_name);
+        resolved == EMPTY_SET || resolved.starts_with("set of ")
+    }
+
+    fn is_string(&self, type_name: &str) -> bool {
+        // Like sets, `string[n]` types are known by their name only
+        let resolved = self.resolve_type(type_name);
+        resolved == "string" || resolved.starts_with("string[")
+    }
+
+    /// Sets only have union (+), difference (-) and intersection (*), strings
+    /// only have concatenation (+). `operand_type` is the type of the right
+    /// operand of `operator`.
+    pub fn check_operator(
+        &self,
+        operator: &str,
+        operand_type: &str,
+        pos: (usize, usize),
+    ) -> Result<(), CompilerError> {
+        if self.is_set(operand_type) && !matches!(operator, "+" | "-" | "*") {
+            return Err(CompilerError::semantic(format!(
+                "Operator '{}' is not defined for sets",
+                operator
+            ))
+            .with_span(pos)
+            .with_note("sets have union (+), difference (-) and intersection (*)"));
+        }
+
+        if self.is_string(operand_type) && operator != "+" {
+            return Err(CompilerError::semantic(format!(
+                "Operator '{}' is not defined for strings",
+                operator
+            ))
+            .with_span(pos)
+            .with_note("strings only have concatenation (+)"));
+        }
+
+        Ok(())
+    }
+
+    /// Divisor of `div` or `mod` at `op_pos` must not fold to 0, even when the
+    /// rest of the expression has variables in it
+    pub fn check_divisor(
+        &self,
+        op: &MultiplicativeOp,
+        op_pos: (usize, usize),
+        divisor: &Factor,
+    ) -> Result<(), CompilerError> {
+        if !matches!(op, MultiplicativeOp::Div | MultiplicativeOp::Mod) {
+            return Ok(());
+        }
+
+        let evaluator = ConstEvaluator::new(&self.tree, &self.options);
+        match evaluator.eval_factor(divisor) {
+            Ok(ConstValue::Integer(0)) => Err(CompilerError::semantic(format!(
+                "Division by zero, the divisor of '{}' is always 0",
+                match op {
+                    MultiplicativeOp::Div => "div",
+                    _ => "mod",
+                }
+            ))
+            .with_span(op_pos)
+            .with_label(divisor.pos(), "this evaluates to 0")),
+            _ => Ok(()),
+        }
+    }
+
+    /// Only numbers take a sign
+    pub fn check_sign(
+        &self,
+        sign: &Sign,
+        operand_type: &str,
+        pos: (usize, usize),
+    ) -> Result<(), CompilerError> {
+        match self.resolve_type(operand_type).as_str() {
+            "integer" | "real" => Ok(()),
+            t => Err(CompilerError::semantic(format!(
+                "Sign '{}' needs an integer or real operand, found {}",
+                match sign {
+                    Sign::Plus => "+",
+                    Sign::Minus => "-",
+                },
+                t
+            ))
+            .with_span(pos)),
+        }
+    }
+
+    /// Type of the component `selectors` pick from a value of `base_type`. Each index
+    /// must be of the array's index type and, when constant, inside its range. Each
+    /// field must belong to the record, each `^` must follow a pointer.
+    pub fn check_selectors(
+        &self,
+        name: &Identifier,
+        base_type: &str,
+        selectors: &[Selector],
+    ) -> Result<String, CompilerError> {
+        let mut current = base_type.to_string();
+
+        for (i, selector) in selectors.iter().enumerate() {
+            let type_def = self.tree.type_def(&self.resolve_type(&current));
+
+            current = match (selector, type_def) {
+                (
+                    Selector::Index(index),
+                    Some(TypeDef::Array {
+                        index_type,
+                        low,
+                        high,
+                        element,
+                    }),
+                ) => {
+                    let found = self.resolve_type(&self.expr_type(index));
+                    if found != *index_type {
+                        return Err(CompilerError::semantic(format!(
+                            "Index of {:?} must be {}, found {}",
+                            name.get_id(),
+                            index_type,
+                            found
+                        ))
+                        .with_span(index.pos()));
+                    }
+
+                    // Only constant indexes can be checked before the program runs
+                    let evaluator = ConstEvaluator::new(&self.tree, &self.options);
+                    match evaluator.eval_expr(index) {
+                        Ok(value)
+                            if self.options.range_checks
+                                && (value.ordinal() < low.ordinal()
+                                    || value.ordinal() > high.ordinal()) =>
+                        {
+                            return Err(CompilerError::semantic(format!(
+                                "Index {:?} is out of range {:?}..{:?}",
+                                value, low, high
+                            ))
+                            .with_span(index.pos()));
+                        }
+                        _ => {}
+                    }
+
+                    element.clone()
+                }
+                (Selector::Field(field), Some(TypeDef::Record { fields, .. })) => {
+                    match fields.iter().find(|(f, _)| *f == field.get_id()) {
+                        Some((_, field_type)) => field_type.clone(),
+                        None => {
+                            return Err(CompilerError::semantic(format!(
+                                "No field {:?} in {}",
+                                field.get_id(),
+                                current
+                            ))
+                            .with_span(field.id.pos))
+                        }
+                    }
+                }
+                (Selector::Deref(pos), _) => match self.pointer_target(&current) {
+                    Some(target) => target,
+                    None => {
+                        let what = match i {
+                            0 => format!("{:?}", name.get_id()),
+                            _ => format!("Component of {:?}", name.get_id()),
+                        };
+
+                        return Err(CompilerError::semantic(format!(
+                            "{} is not a pointer, it is {}",
+                            what, current
+                        ))
+                        .with_span(*pos));
+                    }
+                },
+                (Selector::Index(index), _) if i > 0 => {
+                    return Err(CompilerError::semantic(format!(
+                        "Too many indexes for {:?}",
+                        name.get_id()
+                    ))
+                    .with_span(index.pos()))
+                }
+                (Selector::Index(index), _) => {
+                    return Err(CompilerError::semantic(format!(
+                        "{:?} is not an array, it is {}",
+                        name.get_id(),
+                        current
+                    ))
+                    .with_span(index.pos()))
+                }
+                (Selector::Field(field), _) => {
+                    let what = match i {
+                        0 => format!("{:?}", name.get_id()),
+                        _ => format!("Component of {:?}", name.get_id()),
+                    };
+
+                    return Err(CompilerError::semantic(format!(
+                        "{} is not a record, it is {}",
+                        what, current
+                    ))
+                    .with_span(field.id.pos));
+                }
+            };
+        }
+
+        Ok(current)
+    }
+
+    /// Declare procedure or function in the enclosing scope, before its parameters and body
+    /// The body of a forward declared routine is bound to the symbol of the
+    /// forward declaration and must repeat its heading exactly, if at all
+    pub fn check_procedure_declaration(
+        &mut self,
+        id: &mut Identifier,
+        params: &[FormalParameter],
+        result_type: Option<&Identifier>,
+        forward: bool,
+    ) -> Result<(), CompilerError> {
+        let signature = Signature {
+            params: params
+                .iter()
+                .map(|p| Param {
+                    type_name: p.type_name.get_id(),
+                    by_reference: p.by_reference,
+                })
+                .collect(),
+            result: result_type.map(|t| t.get_id()),
+            optional: 0,
+            rest: false,
+        };
+
+        let usage = match signature.result {
+            Some(_) => Usage::Function(signature),
+            None => Usage::Procedure(signature),
+        };
+
+        if let Some(i) = self.pending_forward(id).filter(|_| !forward) {
+            let symbol = self.forwards.remove(i).symbol;
+            id.symbol = Some(symbol);
+
+            if self.symbol(symbol).usage != usage {
+                return Err(CompilerError::semantic(format!(
+                    "Heading of {:?} differs from its forward declaration",
+                    id.get_id()
+                ))
+                .with_span(id.id.pos)
+                .with_label(self.symbol(symbol).pos, "declared forward here"));
+            }
+            return Ok(());
+        }
+
+        self.declare_identifier(id, usage)
+    }
+
+    /// Routine `id` has been declared forward, `params` are checked already so the
+    /// body can share their symbols. Headings in the interface of a unit are
+    /// forward declarations of the bodies in its implementation.
+    pub fn add_forward(
+        &mut self,
+        id: &Identifier,
+        params: &[FormalParameter],
+        result_type: Option<&Identifier>,
+        interface: bool,
+    ) {
+        if let Some(symbol) = id.symbol {
+            self.forwards.push(Forward {
+                symbol,
+                params: params.to_vec(),
+                result_type: result_type.cloned(),
+                interface,
+            });
+        }
+    }
+
+    // Index of the forward declaration in the current scope `id` gives the body of
+    fn pending_forward(&self, id: &Identifier) -> Option<usize> {
+        let scope = self.scopes.last()?;
+        let symbol = self.tree.scopes[scope.0].get(id.get_id())?;
+        self.forwards.iter().position(|f| f.symbol == symbol)
+    }
+
+    /// Parameters and result type of the forward declaration whose body `id`
+    /// starts, None when `id` wasn't declared forward
+    pub fn forward_heading(
+        &self,
+        id: &Identifier,
+    ) -> Option<(Vec<FormalParameter>, Option<Identifier>)> {
+        let forward = &self.forwards[self.pending_forward(id)?];
+        Some((forward.params.clone(), forward.result_type.clone()))
+    }
+
+    /// Routines declared forward so far and still without a body
+    pub fn forward_count(&self) -> usize {
+        self.forwards.len()
+    }
+
+    /// Forward declarations from the `first` on never got a body. They are dropped,
+    /// so each is reported once, by the block which declared it.
+    pub fn unresolved_forwards(&mut self, first: usize) -> Vec<CompilerError> {
+        self.forwards
+            .drain(first..)
+            .map(|f| {
+                let symbol = self.tree.symbol(f.symbol);
+                let kind = match f.result_type {
+                    Some(_) => "Function",
+                    None => "Procedure",
+                };
+
+                let declared = match f.interface {
+                    true => "in the interface",
+                    false => "forward",
+                };
+
+                CompilerError::semantic(format!(
+                    "{} {:?} is declared {} but its body never follows",
+                    kind, symbol.name, declared
+                ))
+                .with_span(symbol.pos)
+            })
+            .collect()
+    }
+
+    /// Body of a procedure or function starts, None if its declaration failed
+    pub fn enter_routine(&mut self, symbol: Option<SymbolId>) {
+        self.routines.push(symbol);
+    }
+
+    pub fn leave_routine(&mut self) {
+        self.routines.pop();
+    }
+
+    /// Body of a `for` loop starts, its control variable is bound by `check_for_header`
+    pub fn enter_for_loop(&mut self, variable: &Identifier) {
+        self.loop_variables.push(variable.symbol);
+    }
+
+    pub fn leave_for_loop(&mut self) {
+        self.loop_variables.pop();
+    }
+
+    fn check_not_loop_variable(&self, id: &Identifier) -> Result<(), CompilerError> {
+        match self.lookup(id) {
+            Ok(symbol) if self.loop_variables.contains(&Some(symbol)) => {
+                Err(CompilerError::semantic(format!(
+                    "Cannot change control variable {:?} inside its 'for' loop",
+                    id.get_id()
+                ))
+                .with_span(id.id.pos))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Parameters are variables of the procedure's own scope
+    pub fn check_parameter(
+        &mut self,
+        mut param: FormalParameter,
+    ) -> Result<FormalParameter, CompilerError> {
+        // Body of a forward declared routine, the parameter was declared with the heading
+        if let Some(symbol) = param.id.symbol {
+            self.current_scope().insert(param.id.get_id(), symbol);
+            return Ok(param);
+        }
+
+        let usage = Usage::Variable(param.type_name.get_id());
+        self.declare_identifier(&mut param.id, usage)?;
+        Ok(param)
+    }
+
+    /// Arguments must match the parameters in number and type, VAR parameters need
+    /// a variable of exactly their type. Returns the result type for functions.
+    pub fn check_call(&self, call: &mut Call) -> Result<Option<String>, CompilerError> {
+        let name = call.name.get_id();
+        let (kind, signature) = match self.resolve_identifier(&mut call.name)? {
+            Usage::Procedure(s) => ("Procedure", s.clone()),
+            Usage::Function(s) => ("Function", s.clone()),
+            _ => {
+                return Err(
+                    CompilerError::semantic(format!("{:?} is not a procedure", name))
+                        .with_span(call.name.id.pos),
+                )
+            }
+        };
+
+        let most = signature.params.len();
+        let least = most - signature.optional;
+        if call.arguments.len() < least || (call.arguments.len() > most && !signature.rest) {
+            let count = match least == most {
+                true => most.to_string(),
+                false => format!("{} to {}", least, most),
+            };
+
+            return Err(CompilerError::semantic(format!(
+                "{} {:?} takes {} argument(s), found {}",
+                kind,
+                name,
+                count,
+                call.arguments.len()
+            ))
+            .with_span(call.name.id.pos));
+        }
+
+        // write(output, ...) and read(input, ...) name their file first
+        let file = signature.rest
+            && call
+                .arguments
+                .first()
+                .is_some_and(|a| self.resolve_type(&self.expr_type(a)) == TEXT_FILE);
+
+        for (i, arg) in call.arguments.iter().enumerate().skip(file as usize) {
+            // Arguments past the parameters repeat the last one
+            let param = &signature.params[i.min(signature.params.len() - 1)];
+
+            if param.by_reference {
+                let variable = match arg {
+                    Expression::Simple(s) => s
+                        .as_variable()
+                        .and_then(|id| self.find_identifier(id).ok())
+                        .is_some_and(|u| matches!(u, Usage::Variable(_))),
+                    Expression::Relational(_) => false,
+                };
+
+                if !variable {
+                    return Err(CompilerError::semantic(
+                        "Argument for a VAR parameter must be a variable",
+                    )
+                    .with_span(arg.pos()));
+                }
+
+                if let Expression::Simple(s) = arg {
+                    if let Some(id) = s.as_variable() {
+                        self.check_not_loop_variable(id)?;
+                    }
+                }
+            }
+
+            let param_type = self.resolve_type(&param.type_name);
+            let arg_type = self.resolve_type(&self.expr_type(arg));
+
+            if param_type == ANY_POINTER && self.pointer_target(&arg_type).is_none() {
+                return Err(CompilerError::semantic(format!(
+                    "Argument of {:?} must be a pointer, found {}",
+                    name, arg_type
+                ))
+                .with_span(arg.pos()));
+            }
+
+            if param_type == ANY_ORDINAL && self.ordinal_range(&arg_type).is_none() {
+                return Err(CompilerError::semantic(format!(
+                    "Argument of {:?} must be of an ordinal type, found {}",
+                    name, arg_type
+                ))
+                .with_span(arg.pos()));
+            }
+
+            if param_type == ANY_NUMBER && !matches!(arg_type.as_str(), "integer" | "real") {
+                return Err(CompilerError::semantic(format!(
+                    "Argument of {:?} must be an integer or real, found {}",
+                    name, arg_type
+                ))
+                .with_span(arg.pos()));
+            }
+
+            self.check_text_argument(&name, &param_type, &arg_type, arg.pos())?;
+
+            // A VAR parameter is the variable itself, so even strings must agree exactly.
+            // A value parameter takes what could be assigned to it, e.g. an integer for a real.
+            let any = matches!(
+                param_type.as_str(),
+                ANY_POINTER | ANY_ORDINAL | ANY_NUMBER | ANY_WRITABLE | ANY_READABLE
+            );
+            let strings =
+                !param.by_reference && self.is_string(&param_type) && self.is_string(&arg_type);
+            let widened = !param.by_reference && param_type == "real" && arg_type == "integer";
+            if param_type != arg_type && !any && !strings && !widened {
+                let e = CompilerError::semantic(format!(
+                    "Argument of {:?} must be {}, found {}",
+                    name, param_type, arg_type
+                ))
+                .with_span(arg.pos());
+
+                return Err(match param.by_reference {
+                    true => e.with_note(VAR_ARGUMENT_NOTE),
+                    false => e,
+                });
+            }
+
+            let declared = self.declared_type(arg);
+            if self.distinct_structures(&param.type_name, &declared) {
+                return Err(CompilerError::semantic(format!(
+                    "Argument of {:?} must be {}, found {}",
+                    name, param.type_name, declared
+                ))
+                .with_span(arg.pos())
+                .with_note(DISTINCT_TYPES_NOTE));
+            }
+        }
+
+        self.check_write_formats(call, &signature)?;
+        Ok(self.call_result(&signature, call))
+    }
+
+    // abs(-3) is an integer, succ of an enumeration value is of that enumeration:
+    // a result of the parameter's kind is of the argument's type
+    fn call_result(&self, signature: &Signature, call: &Call) -> Option<String> {
+        match signature.result.as_deref() {
+            Some(ANY_NUMBER | ANY_ORDINAL) => call.arguments.first().map(|a| self.expr_type(a)),
+            result => result.map(String::from),
+        }
+    }
+
+    // Values write and read can handle, read takes no booleans
+    fn check_text_argument(
+        &self,
+        name: &str,
+        param_type: &str,
+        arg_type: &str,
+        pos: (usize, usize),
+    ) -> Result<(), CompilerError> {
+        let (allowed, expected) = match param_type {
+            ANY_WRITABLE => (
+                matches!(arg_type, "integer" | "real" | "char" | "boolean"),
+                "an integer, real, char, boolean or string",
+            ),
+            ANY_READABLE => (
+                matches!(arg_type, "integer" | "real" | "char"),
+                "an integer, real, char or string variable",
+            ),
+            _ => return Ok(()),
+        };
+
+        match allowed || self.is_string(arg_type) {
+            true => Ok(()),
+            false => Err(CompilerError::semantic(format!(
+                "Argument of {:?} must be {}, found {}",
+                name, expected, arg_type
+            ))
+            .with_span(pos)),
+        }
+    }
+
+    /// `x:width` and `x:width:decimals` are for arguments of write and writeln.
+    /// Widths are integers, only reals have decimals.
+    fn check_write_formats(&self, call: &Call, signature: &Signature) -> Result<(), CompilerError> {
+        let writes = signature
+            .params
+            .last()
+            .is_some_and(|p| p.type_name == ANY_WRITABLE);
+
+        for (i, format) in call.formats.iter() {
+            if !writes {
+                return Err(CompilerError::semantic(format!(
+                    "Only arguments of write and writeln have a field width, {:?} doesn't take one",
+                    call.name.get_id()
+                ))
+                .with_span(format.width.pos()));
+            }
+
+            for e in std::iter::once(&format.width).chain(format.decimals.iter()) {
+                let width_type = self.resolve_type(&self.expr_type(e));
+                if width_type != "integer" {
+                    return Err(CompilerError::semantic(format!(
+                        "Field width and decimals must be integer, found {}",
+                        width_type
+                    ))
+                    .with_span(e.pos()));
+                }
+            }
+
+            let arg_type = self.resolve_type(&self.expr_type(&call.arguments[*i]));
+            if let Some(decimals) = &format.decimals {
+                if arg_type != "real" {
+                    return Err(CompilerError::semantic(format!(
+                        "Only real values are written with decimals, found {}",
+                        arg_type
+                    ))
+                    .with_span(decimals.pos()));
+                }
             }
         }
+
+        Ok(())
     }
 
     pub fn get_factor_type(&self, f: &Factor) -> Result<Usage, CompilerError> {
         match f {
             Factor::Real(_) => Ok(Usage::Constant("real".into())),
             Factor::Integer(_) => Ok(Usage::Constant("integer".into())),
+            Factor::Char(_) => Ok(Usage::Constant("char".into())),
+            Factor::String(_) => Ok(Usage::Constant("string".into())),
             Factor::Identifier(s) => {
                 let usg = self.find_identifier(s)?;
                 Ok(usg.clone())
             }
-            Factor::Expression(e) => Ok(Usage::Variable(e.expr_type.clone())),
-        }
-    }
-
-    pub fn find_identifier(&self, id: &Identifier) -> Result<&Usage, CompilerError> {
-        let mut scopes = self.scopes.iter().rev();
+            Factor::Call(c) => match self.find_identifier(&c.name)? {
+                Usage::Function(s) => {
+                    Ok(Usage::Variable(self.call_result(s, c).unwrap_or_default()))
+                }
+                usage => Ok(usage.clone()),
+            },
+            Factor::Selected(v) => match self.find_identifier(&v.name)? {
+                Usage::Variable(t) => Ok(Usage::Variable(self.check_selectors(
+                    &v.name,
+                    t,
+                    &v.selectors,
+                )?)),
+                usage => Err(CompilerError::semantic(format!(
+                    "{:?} is not an array, it is a {:?}",
+                    v.name.get_id(),
+                    usage
+                ))
+                .with_span(v.name.id.pos)),
+            },
+            Factor::Set(c) => Ok(Usage::Variable(c.set_type.clone())),
+            Factor::Address(v) => match self.find_identifier(&v.name)? {
+                Usage::Variable(t) => Ok(Usage::Variable(format!(
+                    "^{}",
+                    self.check_selectors(&v.name, t, &v.selectors)?
+                ))),
+                usage => Err(CompilerError::semantic(format!(
+                    "Cannot take the address of {:?}, it is a {:?}",
+                    v.name.get_id(),
+                    usage
+                ))
+                .with_span(v.name.id.pos)),
+            },
+            Factor::Nil(_) => Ok(Usage::Constant(NIL.into())),
+            Factor::Not(operand) => {
+                let operand_type = match self.get_factor_type(operand)? {
+                    Usage::Variable(t) | Usage::Constant(t) => self.resolve_type(&t),
+                    _ => String::new(),
+                };
 
-        loop {
-            let cur_scope = scopes.next();
+                match operand_type.as_str() {
+                    "boolean" => Ok(Usage::Variable("boolean".into())),
+                    "integer"
+                        if self.options.bitwise_not
+                            && self.options.conformance != Conformance::Iso7185 =>
+                    {
+                        Ok(Usage::Variable("integer".into()))
+                    }
+                    t => {
+                        let err = CompilerError::semantic(format!(
+                            "Operand of 'not' must be boolean, found {}",
+                            t
+                        ))
+                        .with_span(operand.pos());
 
-            if cur_scope.is_none() {
-                break Err(CompilerError::semantic(
-                    format!("Unknown identifier {:?}", id.get_id()),
-                    id.id.pos,
-                ));
-            } else {
-                match cur_scope.unwrap().get(id.get_id()) {
-                    Some(u) => break Ok(u),
-                    None => continue,
+                        Err(match t {
+                            "integer" => err
+                                .with_note("bitwise not of integers needs the bitwise_not option"),
+                            _ => err,
+                        })
+                    }
                 }
             }
+            Factor::Expression(e) => Ok(Usage::Variable(self.expr_type(e))),
         }
     }
 
+    fn lookup(&self, id: &Identifier) -> Result<SymbolId, CompilerError> {
+        if let Some(symbol) = id.symbol {
+            return Ok(symbol);
+        }
+
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| self.tree.scope(*scope).get(id.get_id()))
+            .ok_or_else(|| {
+                CompilerError::semantic(format!("Unknown identifier {:?}", id.get_id()))
+                    .with_span(id.id.pos)
+            })
+    }
+
+    pub fn find_identifier(&self, id: &Identifier) -> Result<&Usage, CompilerError> {
+        let symbol = self.lookup(id)?;
+        Ok(&self.symbol(symbol).usage)
+    }
+
+    /// Same as `find_identifier`, but also binds identifier to the found symbol
+    pub fn resolve_identifier(&self, id: &mut Identifier) -> Result<&Usage, CompilerError> {
+        let symbol = self.lookup(id)?;
+        id.symbol = Some(symbol);
+        Ok(&self.symbol(symbol).usage)
+    }
+
     pub fn merge_types(
         &self,
-        type1: &String,
-        type2: &String,
+        type1: &str,
+        type2: &str,
         pos: (usize, usize),
         strong: bool,
     ) -> Result<String, CompilerError> {
+        let (type1, type2) = (self.resolve_type(type1), self.resolve_type(type2));
+
         match (type1.as_str(), type2.as_str()) {
-            ("integer", "real") | ("real", "integer") => {
-                if strong {
-                    Err(CompilerError::semantic("Type mismatch".into(), pos))
-                } else {
-                    Ok("real".into())
-                }
-            }
+            // Integers are assignment compatible with reals, not the other way round
+            ("integer", "real") if strong => Err(CompilerError::semantic(
+                "Type mismatch, a real can't be assigned to an integer",
+            )
+            .with_span(pos)
+            .with_note(
+                "use trunc(...) to drop the fraction or round(...) to round to the nearest integer",
+            )),
+            ("integer", "real") | ("real", "integer") => Ok("real".into()),
             (x, y) if x == y => Ok(x.into()),
             (x, "") => Ok(x.into()),
-            _ => Err(CompilerError::semantic("Type mismatch".into(), pos)),
+            // A char is a string of length one, not the other way round.
+            // Strings of any length mix, assignment cuts them to the target.
+            (x, "char") if self.is_string(x) => Ok(x.into()),
+            ("char", y) if !strong && self.is_string(y) => Ok(y.into()),
+            (x, y) if self.is_string(x) && self.is_string(y) => match strong {
+                true => Ok(x.into()),
+                false => Ok("string".into()),
+            },
+            (x, EMPTY_SET) if self.is_set(x) => Ok(x.into()),
+            (EMPTY_SET, y) if self.is_set(y) => Ok(y.into()),
+            (x, NIL) if self.pointer_target(x).is_some() => Ok(x.into()),
+            (NIL, y) if self.pointer_target(y).is_some() => Ok(y.into()),
+            // Pointers to aliases of one type are the same
+            (x, y)
+                if self.pointer_target(x).is_some()
+                    && self.pointer_target(x) == self.pointer_target(y) =>
+            {
+                Ok(x.into())
+            }
+            _ => Err(CompilerError::semantic("Type mismatch").with_span(pos)),
+        }
+    }
+
+    /// Follows type aliases down to the builtin type, values of a subrange are
+    /// those of its host type
+    pub fn resolve_type(&self, type_name: &str) -> String {
+        let name = self.resolve_alias(type_name);
+
+        match self.tree.type_def(&name) {
+            Some(TypeDef::Subrange { host, .. }) => host.clone(),
+            _ => name,
+        }
+    }
+
+    // Like resolve_type, but a subrange stays itself
+    fn resolve_alias(&self, type_name: &str) -> String {
+        let mut name = type_name.to_string();
+        // A unit or a failed declaration may still leave a loop behind, `A = B; B = A`
+        let mut seen = BTreeSet::new();
+
+        while let Some(parent) = self.alias_of(&name) {
+            if !seen.insert(name) {
+                return parent;
+            }
+            name = parent;
+        }
+
+        name
+    }
+
+    // What the type name `name` was declared as, None for other names and builtin types
+    fn alias_of(&self, name: &str) -> Option<String> {
+        let symbol = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|s| self.tree.scope(*s).get(name.to_string()))?;
+
+        match &self.symbol(symbol).usage {
+            Usage::Type(Some(parent)) => Some(parent.clone()),
+            _ => None,
+        }
+    }
+
+    // Definition a type comes from: aliases are followed as far as they name another
+    // type, `type R3 = R1` is R1 while R1 itself stands for its record
+    fn type_identity(&self, type_name: &str) -> String {
+        let mut name = type_name.to_string();
+        let mut seen = BTreeSet::new();
+
+        while let Some(parent) = self.alias_of(&name) {
+            if self.alias_of(&parent).is_none() && self.tree.type_def(&parent).is_some() {
+                break;
+            }
+            if !seen.insert(name) {
+                return parent;
+            }
+            name = parent;
+        }
+
+        name
+    }
+
+    /// Records and arrays are one type only when they come from one definition, alike
+    /// ones declared apart are different types (ISO 7185 6.4.7)
+    fn distinct_structures(&self, first: &str, second: &str) -> bool {
+        let resolved = self.resolve_type(first);
+
+        matches!(
+            self.tree.type_def(&resolved),
+            Some(TypeDef::Record { .. } | TypeDef::Array { .. })
+        ) && resolved == self.resolve_type(second)
+            && self.type_identity(first) != self.type_identity(second)
+    }
+
+    /// Type of a variable, constant or call as declared, aliases not followed. Records
+    /// and arrays are only ever values like these, other expressions give expr_type.
+    fn declared_type(&self, e: &Expression) -> String {
+        let factor = match e {
+            Expression::Simple(s) => s.as_factor(),
+            Expression::Relational(_) => None,
+        };
+
+        match factor.map(|f| self.get_factor_type(f)) {
+            Some(Ok(Usage::Variable(t) | Usage::Constant(t))) => t,
+            _ => self.expr_type(e),
+        }
+    }
+
+    pub fn expr_type(&self, e: &Expression) -> String {
+        match e {
+            Expression::Simple(s) => s.expr_type.clone(),
+            Expression::Relational(_) => "boolean".into(),
         }
     }
 
-    pub fn get_sub_term_type(&self, sub_term: &SubTerm) -> Result<String, CompilerError> {
-        // Type of subterm is its factor's type or merge with inner subterm type
-        let usg = self.get_factor_type(&sub_term.factor)?;
-        let factor_type_str = match usg {
-            Usage::Constant(s) | Usage::Variable(s) => s,
-            _ => todo!(),
+    /// Loop variable of `for` must be an ordinal variable, both bounds of its type
+    pub fn check_for_header(
+        &self,
+        variable: &mut Identifier,
+        start: &Expression,
+        end: &Expression,
+    ) -> Result<(), CompilerError> {
+        let var_type = match self.resolve_identifier(variable)? {
+            Usage::Variable(t) => self.resolve_type(t),
+            _ => {
+                return Err(CompilerError::semantic(format!(
+                    "Loop variable {:?} must be a variable",
+                    variable.get_id()
+                ))
+                .with_span(variable.id.pos))
+            }
         };
 
-        if sub_term.sub_term.is_none() {
-            Ok(factor_type_str)
-        } else {
-            let sub_term_type = self.get_sub_term_type(sub_term.sub_term.as_ref().unwrap())?;
-            // TODO: pass actual position
-            self.merge_types(&factor_type_str, &sub_term_type, (0, 0), false)
+        self.check_not_loop_variable(variable)?;
+
+        if self.ordinal_range(&var_type).is_none() {
+            return Err(CompilerError::semantic(format!(
+                "Loop variable {:?} must be of an ordinal type, found {}",
+                variable.get_id(),
+                var_type
+            ))
+            .with_span(variable.id.pos));
+        }
+
+        for bound in [start, end] {
+            let bound_type = self.resolve_type(&self.expr_type(bound));
+
+            if bound_type != var_type {
+                return Err(CompilerError::semantic(format!(
+                    "Bound of 'for' loop must be {}, found {}",
+                    var_type, bound_type
+                ))
+                .with_span(bound.pos()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Selector of `case` must be ordinal, returns its type
+    pub fn check_case_selector(&self, e: &Expression) -> Result<String, CompilerError> {
+        let selector_type = self.resolve_type(&self.expr_type(e));
+
+        match self.ordinal_range(&selector_type) {
+            Some(_) => Ok(selector_type),
+            None => Err(CompilerError::semantic(format!(
+                "Selector of 'case' must be of an ordinal type, found {}",
+                selector_type
+            ))
+            .with_span(e.pos())
+            .with_note(CASE_ORDINAL_NOTE)),
+        }
+    }
+
+    /// Case label must be an ordinal constant of the selector type which no other
+    /// label of the statement covers. `seen` holds ranges of the previous labels.
+    /// With an empty `selector_type` (the selector was wrong) labels are only
+    /// checked for being ordinal.
+    pub fn check_case_label(
+        &self,
+        label: &CaseLabel,
+        selector_type: &str,
+        seen: &mut Vec<(i32, i32, (usize, usize))>,
+    ) -> Result<(), CompilerError> {
+        let evaluator = ConstEvaluator::new(&self.tree, &self.options);
+        let high_expr = label.high.as_ref().unwrap_or(&label.low);
+        let low = evaluator.eval_simple_expr(&label.low)?;
+        let high = evaluator.eval_simple_expr(high_expr)?;
+
+        for (value, expr) in [(&low, &label.low), (&high, high_expr)] {
+            if !value.is_ordinal() {
+                return Err(CompilerError::semantic(format!(
+                    "Case label must be an ordinal constant, found {} {:?}",
+                    value.type_name(),
+                    value
+                ))
+                .with_span(expr.pos())
+                .with_note(CASE_ORDINAL_NOTE));
+            }
+        }
+
+        if selector_type.is_empty() {
+            return Ok(());
+        }
+
+        for (value, expr) in [(&low, &label.low), (&high, high_expr)] {
+            if value.type_name() != selector_type {
+                return Err(CompilerError::semantic(format!(
+                    "Case label must be {}, found {}",
+                    selector_type,
+                    value.type_name()
+                ))
+                .with_span(expr.pos()));
+            }
+        }
+
+        let pos = label.low.pos();
+
+        if low.ordinal() > high.ordinal() {
+            return Err(CompilerError::semantic(format!(
+                "Case label range {:?}..{:?} is empty",
+                low, high
+            ))
+            .with_span(pos));
+        }
+
+        let (low, high) = (low.ordinal(), high.ordinal());
+
+        if let Some((_, _, prev)) = seen.iter().find(|(l, h, _)| low <= *h && *l <= high) {
+            return Err(CompilerError::semantic("Duplicate case label")
+                .with_span(pos)
+                .with_label(*prev, "previously used here"));
+        }
+
+        seen.push((low, high, pos));
+        Ok(())
+    }
+
+    /// Operands of a relational operator must be comparable, the result is boolean
+    pub fn check_comparison(
+        &self,
+        first: &str,
+        op: &RelationalOp,
+        second: &str,
+        pos: (usize, usize),
+    ) -> Result<(), CompilerError> {
+        let (first, second) = (self.resolve_type(first), self.resolve_type(second));
+
+        if matches!(op, RelationalOp::In) {
+            return self.check_membership(&first, &second, pos);
+        }
+
+        match (first.as_str(), second.as_str()) {
+            ("integer" | "real", "integer" | "real") => Ok(()),
+            (x, y) if x == y => Ok(()),
+            (x, y) if (self.is_string(x) || x == "char") && (self.is_string(y) || y == "char") => {
+                Ok(())
+            }
+            (x, EMPTY_SET) | (EMPTY_SET, x) if self.is_set(x) => Ok(()),
+            (x, NIL) | (NIL, x) if self.pointer_target(x).is_some() => Ok(()),
+            (x, y)
+                if self.pointer_target(x).is_some()
+                    && self.pointer_target(x) == self.pointer_target(y) =>
+            {
+                Ok(())
+            }
+            (x, y) => {
+                let err = CompilerError::semantic(format!(
+                    "Operator {:?} cannot compare {} with {}",
+                    op, x, y
+                ))
+                .with_span(pos);
+
+                Err(match (x, y) {
+                    ("char", "integer") | ("integer", "char") => {
+                        err.with_note("use ord() to compare a char with its code")
+                    }
+                    ("boolean", "integer") | ("integer", "boolean") => {
+                        err.with_note("booleans are not numbers, use ord() to get 0 or 1")
+                    }
+                    _ => err,
+                })
+            }
         }
     }
 
-    pub fn check_expr(
+    // `x in s`: s is a set and x an ordinal of its element type, both resolved
+    fn check_membership(
         &self,
-        e: &Expression,
-        type_name: &String,
+        element: &str,
+        set: &str,
         pos: (usize, usize),
     ) -> Result<(), CompilerError> {
+        let expected = match self.tree.type_def(set) {
+            Some(TypeDef::Set { element }) => Some(element.as_str()),
+            _ if set == EMPTY_SET => None,
+            _ => {
+                return Err(CompilerError::semantic(format!(
+                    "Operator in needs a set on its right, found {}",
+                    set
+                ))
+                .with_span(pos))
+            }
+        };
+
+        match expected {
+            Some(expected) if expected != element => Err(CompilerError::semantic(format!(
+                "Operator in cannot look for {} in {}",
+                element, set
+            ))
+            .with_span(pos)),
+            None if self.ordinal_range(element).is_none() => Err(CompilerError::semantic(format!(
+                "Operator in cannot look for {} in a set",
+                element
+            ))
+            .with_span(pos)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Conditions of if/while/repeat must be boolean, `statement` names the keyword
+    pub fn check_condition(&self, e: &Expression, statement: &str) -> Result<(), CompilerError> {
         match e {
-            Expression::Simple(e) if &e.expr_type == type_name => Ok(()),
-            Expression::Relational(_) if type_name.as_str() == "boolean" => Ok(()),
-            _ => Err(CompilerError::semantic("Expected boolean type".into(), pos)),
+            Expression::Relational(_) => Ok(()),
+            Expression::Simple(s) => match self.resolve_type(&s.expr_type).as_str() {
+                "boolean" => Ok(()),
+                other => Err(CompilerError::semantic(format!(
+                    "Condition of '{}' must be boolean, found {}",
+                    statement, other
+                ))
+                .with_span(e.pos())),
+            },
+        }
+    }
+
+    /// Warning about an integer value of `a` silently turned into a real, None
+    /// when there is no such conversion
+    pub fn check_conversion(&self, a: &VarAssignment) -> Option<CompilerError> {
+        let target = match &self.symbol(a.name.symbol?).usage {
+            Usage::Variable(t) => t.clone(),
+            Usage::Function(s) => s.result.clone()?,
+            _ => return None,
+        };
+        let target = self.check_selectors(&a.name, &target, &a.selectors).ok()?;
+        let value = match &*a.value {
+            Expression::Simple(expr) => self.resolve_type(&expr.expr_type),
+            Expression::Relational(_) => return None,
+        };
+
+        (self.resolve_type(&target) == "real" && value == "integer").then(|| {
+            CompilerError::lint("Integer value is converted to real [pedantic]")
+                .with_span(a.value.pos())
+                .with_note("the target is a real, so the integer is converted when assigned")
+        })
+    }
+
+    // Arrays and records are assigned whole, from a value of the same structure
+    fn check_assignable(
+        &self,
+        target: &str,
+        value: &str,
+        pos: (usize, usize),
+    ) -> Result<(), CompilerError> {
+        let err = match self.merge_types(target, value, pos, true) {
+            Ok(_) => return Ok(()),
+            Err(e) => e,
+        };
+
+        let note = match self.tree.type_def(&self.resolve_type(target)) {
+            Some(TypeDef::Array { .. }) => {
+                "a whole array takes an array of the same bounds and element type"
+            }
+            Some(TypeDef::Record { .. }) => "a whole record takes a record with the same fields",
+            _ => return Err(err),
+        };
+
+        Err(CompilerError::semantic(format!(
+            "Cannot assign a value of type {} to {}",
+            value, target
+        ))
+        .with_span(pos)
+        .with_note(note))
+    }
+
+    fn check_same_definition(
+        &self,
+        target: &str,
+        value: &Expression,
+        pos: (usize, usize),
+    ) -> Result<(), CompilerError> {
+        let declared = self.declared_type(value);
+
+        match self.distinct_structures(target, &declared) {
+            true => Err(CompilerError::semantic(format!(
+                "Cannot assign a value of type {} to {}",
+                declared, target
+            ))
+            .with_span(pos)
+            .with_note(DISTINCT_TYPES_NOTE)),
+            false => Ok(()),
         }
     }
 
-    pub fn check_assignment(&self, a: VarAssignment) -> Result<VarAssignment, CompilerError> {
-        let var_id = &a.name;
-        let var_type = self.find_identifier(var_id)?;
+    // Like array indexes, only constants can be checked before the program runs
+    fn check_subrange_value(&self, target: &str, value: &Expression) -> Result<(), CompilerError> {
+        let Some((low, high)) = self.subrange_bounds(target) else {
+            return Ok(());
+        };
+
+        let evaluator = ConstEvaluator::new(&self.tree, &self.options);
+        match evaluator.eval_expr(value) {
+            Ok(v)
+                if self.options.range_checks
+                    && (v.ordinal() < low.ordinal() || v.ordinal() > high.ordinal()) =>
+            {
+                Err(CompilerError::semantic(format!(
+                    "Value {:?} is out of range {:?}..{:?}",
+                    v, low, high
+                ))
+                .with_span(value.pos()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_assignment(&self, a: &mut VarAssignment) -> Result<(), CompilerError> {
+        let var_type = self.resolve_identifier(&mut a.name)?;
         let mut value_type = String::from("boolean");
 
         // Borrow what's inside the box
@@ -158,11 +2019,47 @@ impl Analyzer {
 
         match var_type {
             Usage::Variable(s) => {
-                self.merge_types(s, &value_type, a.name.id.pos, true)?;
-                Ok(a)
+                self.check_not_loop_variable(&a.name)?;
+                let target = self.check_selectors(&a.name, s, &a.selectors)?;
+                self.check_same_definition(&target, &a.value, a.name.id.pos)?;
+                self.check_assignable(&target, &value_type, a.name.id.pos)?;
+                self.check_subrange_value(&target, &a.value)
+            }
+            Usage::Constant(_) => Err(CompilerError::semantic(format!(
+                "Cannot assign to constant {:?}",
+                a.name.get_id()
+            ))
+            .with_span(a.name.id.pos)),
+            // Function result is set by assigning to its name inside the body
+            Usage::Function(s) if self.routines.contains(&a.name.symbol) => {
+                let result = s.result.clone().unwrap_or_default();
+                let target = self.check_selectors(&a.name, &result, &a.selectors)?;
+                self.check_same_definition(&target, &a.value, a.name.id.pos)?;
+                self.check_assignable(&target, &value_type, a.name.id.pos)?;
+                self.check_subrange_value(&target, &a.value)
             }
-            // We can't actually get here but Rust enforces to do check anyway
-            _ => todo!(),
+            usage => Err(CompilerError::semantic(format!(
+                "Cannot assign to {:?}, it is a {:?}",
+                a.name.get_id(),
+                usage
+            ))
+            .with_span(a.name.id.pos)),
         }
     }
 }
+
+// Field names are unique across a record, variants included
+fn check_field_name(
+    id: &Identifier,
+    fields: &[(String, String)],
+    positions: &[(usize, usize)],
+) -> Result<(), CompilerError> {
+    match fields.iter().position(|(name, _)| *name == id.get_id()) {
+        Some(prev) => Err(
+            CompilerError::semantic(format!("Duplicate field {:?}", id.get_id()))
+                .with_span(id.id.pos)
+                .with_label(positions[prev], "first declared here"),
+        ),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,61 @@
+use crate::error::Diagnostic;
+use crate::json::{self, Value};
+
+/// Diagnostics recorded from an earlier compilation, so only new ones are reported.
+/// Positions aren't compared: recorded diagnostics stay suppressed while the code
+/// around them moves.
+pub struct Baseline {
+    // Type and message of each recorded diagnostic, repeats included
+    entries: Vec<(String, String)>,
+}
+
+impl Baseline {
+    /// Reads a document written by `record`, or any with the same `diagnostics` array
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let document = json::parse(text)?;
+        let diagnostics = document
+            .get("diagnostics")
+            .and_then(Value::as_array)
+            .ok_or("expected an object with a \"diagnostics\" array")?;
+
+        let entries = diagnostics
+            .iter()
+            .map(|d| {
+                let field = |name| d.get(name).and_then(Value::as_str).map(String::from);
+                field("type")
+                    .zip(field("message"))
+                    .ok_or_else(|| "expected \"type\" and \"message\" in every diagnostic".into())
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self { entries })
+    }
+
+    pub fn record(diagnostics: &[Diagnostic]) -> String {
+        let entries = diagnostics.iter().enumerate().map(|(i, d)| d.to_json(i));
+        format!("{{\"diagnostics\":{}}}", json::array(entries))
+    }
+
+    /// Diagnostics the baseline doesn't have, each recorded one suppresses one equal
+    /// diagnostic. A warning which appears once more than before is new.
+    pub fn new_diagnostics(&self, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        let mut unmatched: Vec<&(String, String)> = self.entries.iter().collect();
+
+        diagnostics
+            .into_iter()
+            .filter(|d| {
+                let found = unmatched
+                    .iter()
+                    .position(|(t, m)| *t == d.err_type().to_string() && m == d.message());
+
+                match found {
+                    Some(i) => {
+                        unmatched.swap_remove(i);
+                        false
+                    }
+                    None => true,
+                }
+            })
+            .collect()
+    }
+}
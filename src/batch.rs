@@ -0,0 +1,146 @@
+use crate::driver::Compiler;
+use crate::json;
+use crate::options::Options;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Outcome of compiling one file
+pub struct FileSummary {
+    pub file: String,
+    pub errors: usize,
+    pub warnings: usize,
+    // `[line:column] message` of the first error, if any
+    pub first_error: Option<String>,
+}
+
+/// Summary of many programs (e.g. a directory of submissions), one row per
+/// file in path order so reports of the same files compare line by line
+pub struct BatchReport {
+    pub files: Vec<FileSummary>,
+}
+
+impl BatchReport {
+    pub fn check(files: &[PathBuf], options: &Options) -> Self {
+        let files = files.iter().map(|path| check_file(path, options)).collect();
+        Self { files }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.files.iter().any(|f| f.errors > 0)
+    }
+
+    pub fn to_json(&self) -> String {
+        let files = self.files.iter().map(|f| {
+            format!(
+                "{{\"file\":{},\"errors\":{},\"warnings\":{},\"first_error\":{}}}",
+                json::string(&f.file),
+                f.errors,
+                f.warnings,
+                f.first_error.as_deref().map_or("null".into(), json::string)
+            )
+        });
+
+        format!("{{\"files\":{}}}", json::array(files))
+    }
+
+    /// RFC 4180 CSV with a header row
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("file,errors,warnings,first_error\r\n");
+
+        for f in self.files.iter() {
+            csv.push_str(&format!(
+                "{},{},{},{}\r\n",
+                csv_field(&f.file),
+                f.errors,
+                f.warnings,
+                csv_field(f.first_error.as_deref().unwrap_or(""))
+            ));
+        }
+
+        csv
+    }
+}
+
+/// `.pas` files in `dir` sorted by path, those in subdirectories too when `recursive`
+pub fn pascal_files(dir: &Path, recursive: bool) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            if recursive {
+                files.extend(pascal_files(&path, recursive)?);
+            }
+        } else if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("pas"))
+        {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+fn check_file(path: &Path, options: &Options) -> FileSummary {
+    let file = path.display().to_string();
+
+    // A file which can't be read counts as one error
+    let result = match Compiler::new(options.clone()).compile_file(&file) {
+        Ok(result) => result,
+        Err(e) => {
+            return FileSummary {
+                file,
+                errors: 1,
+                warnings: 0,
+                first_error: Some(e.message().to_string()),
+            }
+        }
+    };
+
+    let errors = result.diagnostics.iter().filter(|d| d.is_error()).count();
+    let first_error = result.diagnostics.iter().find(|d| d.is_error()).map(|d| {
+        let (line, column) = d.pos();
+        format!("[{}:{}] {}", line, column, d.message())
+    });
+
+    FileSummary {
+        file,
+        errors,
+        warnings: result.diagnostics.len() - errors,
+        first_error,
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    match s.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Wide enough for the longest path, so the numbers line up
+        let width = self.files.iter().map(|s| s.file.len()).fold(4, usize::max);
+
+        write!(f, "{:<width$} Errors Warnings  First error", "File")?;
+
+        for s in self.files.iter() {
+            let row = format!(
+                "{:<width$} {:>6} {:>8}  {}",
+                s.file,
+                s.errors,
+                s.warnings,
+                s.first_error.as_deref().unwrap_or("")
+            );
+            write!(f, "\n{}", row.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
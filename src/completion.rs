@@ -0,0 +1,121 @@
+use crate::analyzer::Analyzer;
+use crate::driver::compile_source;
+use crate::hover::describe;
+use crate::options::Options;
+use crate::scope::Usage;
+use std::fmt;
+
+/// Identifier an editor can offer at the cursor
+pub struct Completion {
+    pub name: String,
+    // variable, constant, type, procedure, function or program
+    pub kind: &'static str,
+    // Type of variables and constants, signature for procedures and functions
+    pub type_name: String,
+}
+
+impl fmt::Display for Completion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.name)?;
+
+        if !self.type_name.is_empty() && self.type_name != self.name {
+            write!(f, " : {}", self.type_name)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What may be written at the cursor, judged by the text in front of it
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Context {
+    // After `:` of a declaration
+    Type,
+    // After `:=`
+    Value,
+    Any,
+}
+
+impl Context {
+    fn allows(self, usage: &Usage) -> bool {
+        match self {
+            Context::Type => matches!(usage, Usage::Type(_)),
+            Context::Value => matches!(
+                usage,
+                Usage::Variable(_) | Usage::Constant(_) | Usage::Function(_)
+            ),
+            Context::Any => true,
+        }
+    }
+}
+
+/// Compiles the source with `options` and lists the identifiers visible at `pos`
+/// (line and column of the cursor, both from 1) which start with the partial
+/// identifier in front of the cursor, in any case, sorted by name. Inner declarations
+/// shadow outer ones. When the program can't be parsed at all only builtins are offered.
+pub fn completions(source: &str, pos: (usize, usize), options: &Options) -> Vec<Completion> {
+    let before = text_before(source, pos);
+    // The partial identifier being typed
+    let rest = before.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_');
+    let prefix = &before[rest.len()..];
+    let context = context(rest);
+
+    let result = compile_source(source, options);
+    let builtins;
+    let tree = match &result.ast {
+        Some(program) => program.scope_tree(),
+        None => {
+            builtins = Analyzer::new().take_scope_tree();
+            &builtins
+        }
+    };
+
+    tree.visible_at(pos)
+        .into_iter()
+        .filter(|(name, _)| {
+            name.get(..prefix.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+        })
+        .filter_map(|(name, symbol)| {
+            let usage = &tree.symbol(symbol).usage;
+            if !context.allows(usage) {
+                return None;
+            }
+
+            let (kind, type_name) = describe(&name, usage);
+            Some(Completion {
+                name,
+                kind,
+                type_name,
+            })
+        })
+        .collect()
+}
+
+// Source up to, not including, the character at `pos`
+fn text_before(source: &str, (line, column): (usize, usize)) -> String {
+    let mut text = String::new();
+
+    for (i, l) in source.lines().enumerate().take(line) {
+        if i + 1 < line {
+            text.push_str(l);
+            text.push('\n');
+        } else {
+            text.extend(l.chars().take(column.saturating_sub(1)));
+        }
+    }
+
+    text
+}
+
+fn context(text: &str) -> Context {
+    let text = text.trim_end();
+
+    if text.ends_with(":=") {
+        Context::Value
+    } else if text.ends_with(':') {
+        Context::Type
+    } else {
+        Context::Any
+    }
+}
@@ -0,0 +1,369 @@
+use crate::error::CompilerError;
+use crate::options::{DivMod, Options};
+use crate::scope::ScopeTree;
+use crate::syntax::*;
+use crate::token::TokenType;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Value of an expression known at compile time
+#[derive(PartialEq, Clone)]
+pub enum ConstValue {
+    Integer(i32),
+    Boolean(bool),
+    Char(char),
+    Real(f32),
+    String(String),
+    // Enumerator, numbered from 0 in declaration order
+    Enum {
+        type_name: String,
+        name: String,
+        ordinal: i32,
+    },
+}
+
+impl ConstValue {
+    pub fn type_name(&self) -> &str {
+        match self {
+            ConstValue::Integer(_) => "integer",
+            ConstValue::Boolean(_) => "boolean",
+            ConstValue::Char(_) => "char",
+            ConstValue::Real(_) => "real",
+            ConstValue::String(_) => "string",
+            ConstValue::Enum { type_name, .. } => type_name,
+        }
+    }
+
+    /// Ordinal number of the value (used for case labels and subrange bounds).
+    /// Reals are not ordinal and get truncated, strings are not ordinal either
+    /// and give 0, callers check the type first.
+    pub fn ordinal(&self) -> i32 {
+        match self {
+            ConstValue::Integer(i) => *i,
+            ConstValue::Boolean(b) => *b as i32,
+            ConstValue::Char(c) => *c as i32,
+            ConstValue::Real(r) => *r as i32,
+            ConstValue::String(_) => 0,
+            ConstValue::Enum { ordinal, .. } => *ordinal,
+        }
+    }
+
+    pub fn is_ordinal(&self) -> bool {
+        !matches!(self, ConstValue::Real(_) | ConstValue::String(_))
+    }
+
+    // Integers and reals mix in arithmetic and comparisons
+    fn as_real(&self) -> Option<f32> {
+        match self {
+            ConstValue::Integer(i) => Some(*i as f32),
+            ConstValue::Real(r) => Some(*r),
+            _ => None,
+        }
+    }
+
+    // Comparisons with a real take the integer side as f64, which holds any i32
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            ConstValue::Integer(i) => Some(*i as f64),
+            ConstValue::Real(r) => Some(*r as f64),
+            _ => None,
+        }
+    }
+
+    // Chars and strings mix in concatenation and comparisons
+    fn as_text(&self) -> Option<String> {
+        match self {
+            ConstValue::Char(c) => Some(c.to_string()),
+            ConstValue::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Both operands as reals when at least one of them is real
+fn real_operands(a: &ConstValue, b: &ConstValue) -> Option<(f32, f32)> {
+    match (a, b) {
+        (ConstValue::Real(_), _) | (_, ConstValue::Real(_)) => Some((a.as_real()?, b.as_real()?)),
+        _ => None,
+    }
+}
+
+/// Smallest and largest ordinal value of a builtin ordinal type, None for
+/// types which are not ordinal (real). Case selectors, subranges and for
+/// loop counters all need an ordinal type. Enumerations are ordinal too,
+/// the analyzer knows their ranges.
+pub fn ordinal_range(type_name: &str) -> Option<(i32, i32)> {
+    match type_name {
+        "integer" => Some((i32::MIN, i32::MAX)),
+        "boolean" => Some((0, 1)),
+        "char" => Some((0, char::MAX as i32)),
+        _ => None,
+    }
+}
+
+impl fmt::Debug for ConstValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstValue::Integer(i) => write!(f, "{}", i),
+            ConstValue::Boolean(b) => write!(f, "{}", b),
+            ConstValue::Char(c) => write!(f, "{:?}", c),
+            ConstValue::Real(r) => write!(f, "{:?}", r),
+            ConstValue::String(s) => write!(f, "'{}'", s.replace('\'', "''")),
+            ConstValue::Enum { name, .. } => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Evaluates expressions which must be constant (const declarations, case labels…).
+/// Identifiers are looked up through symbols bound by the analyzer.
+pub struct ConstEvaluator<'a> {
+    tree: &'a ScopeTree,
+    div_mod: DivMod,
+}
+
+impl<'a> ConstEvaluator<'a> {
+    pub fn new(tree: &'a ScopeTree, options: &Options) -> Self {
+        Self {
+            tree,
+            div_mod: options.div_mod,
+        }
+    }
+
+    pub fn eval_expr(&self, e: &Expression) -> Result<ConstValue, CompilerError> {
+        match e {
+            Expression::Simple(s) => self.eval_simple_expr(s),
+            Expression::Relational(r) => {
+                let first = self.eval_simple_expr(&r.first)?;
+                let second = self.eval_simple_expr(&r.second)?;
+                let pos = r.first.pos();
+
+                // None when a real is NaN, which is only unequal to things
+                let ordering = match real_operands(&first, &second) {
+                    Some(_) => first.as_f64().partial_cmp(&second.as_f64()),
+                    None if first.as_text().is_some() && second.as_text().is_some() => {
+                        Some(first.as_text().cmp(&second.as_text()))
+                    }
+                    None if first.type_name() == second.type_name() => {
+                        Some(first.ordinal().cmp(&second.ordinal()))
+                    }
+                    None => {
+                        return Err(CompilerError::semantic(format!(
+                            "Cannot compare constants of types {} and {}",
+                            first.type_name(),
+                            second.type_name()
+                        ))
+                        .with_span(pos))
+                    }
+                };
+
+                let res = match *r.op {
+                    RelationalOp::Less => ordering == Some(Ordering::Less),
+                    RelationalOp::Bigger => ordering == Some(Ordering::Greater),
+                    RelationalOp::LessEq => ordering.is_some_and(Ordering::is_le),
+                    RelationalOp::BiggerEq => ordering.is_some_and(Ordering::is_ge),
+                    RelationalOp::Eq => ordering == Some(Ordering::Equal),
+                    RelationalOp::UnEq => ordering != Some(Ordering::Equal),
+                    // Sets have no constant values
+                    RelationalOp::In => return Err(not_constant(pos)),
+                };
+
+                Ok(ConstValue::Boolean(res))
+            }
+        }
+    }
+
+    pub fn eval_simple_expr(&self, e: &SimpleExpression) -> Result<ConstValue, CompilerError> {
+        // Subexpressions are chained to the right, but operators are left-associative
+        let mut acc = match (&e.sign, self.eval_term(&e.term)?) {
+            (Some(Sign::Minus), ConstValue::Integer(i)) => {
+                ConstValue::Integer(i.checked_neg().ok_or_else(|| overflow(e.pos()))?)
+            }
+            (Some(Sign::Minus), ConstValue::Real(r)) => ConstValue::Real(-r),
+            (Some(_), v @ (ConstValue::Integer(_) | ConstValue::Real(_))) | (None, v) => v,
+            (Some(sign), v) => {
+                return Err(CompilerError::semantic(format!(
+                    "Sign {:?} cannot be applied to a constant of type {}",
+                    sign,
+                    v.type_name()
+                ))
+                .with_span(e.pos()))
+            }
+        };
+        let mut sub_expr = e.sub_expr.as_ref();
+
+        while let Some(s) = sub_expr {
+            let rhs = self.eval_term(&s.term)?;
+            let pos = s.term.factor.pos();
+
+            acc = match (&*s.op, acc, rhs) {
+                (AdditiveOp::Plus | AdditiveOp::Minus, a, b) if real_operands(&a, &b).is_some() => {
+                    let (a, b) = real_operands(&a, &b).unwrap_or_default();
+                    match *s.op {
+                        AdditiveOp::Plus => ConstValue::Real(a + b),
+                        _ => ConstValue::Real(a - b),
+                    }
+                }
+                (AdditiveOp::Plus, ConstValue::Integer(a), ConstValue::Integer(b)) => {
+                    ConstValue::Integer(a.checked_add(b).ok_or_else(|| overflow(pos))?)
+                }
+                (AdditiveOp::Minus, ConstValue::Integer(a), ConstValue::Integer(b)) => {
+                    ConstValue::Integer(a.checked_sub(b).ok_or_else(|| overflow(pos))?)
+                }
+                (AdditiveOp::Or, ConstValue::Boolean(a), ConstValue::Boolean(b)) => {
+                    ConstValue::Boolean(a || b)
+                }
+                (AdditiveOp::Plus, a @ ConstValue::String(_), b)
+                | (AdditiveOp::Plus, a, b @ ConstValue::String(_))
+                    if a.as_text().is_some() && b.as_text().is_some() =>
+                {
+                    ConstValue::String(
+                        a.as_text().unwrap_or_default() + &b.as_text().unwrap_or_default(),
+                    )
+                }
+                (op, a, b) => return Err(invalid_operands(op, a, b, pos)),
+            };
+
+            sub_expr = s.sub_expr.as_deref();
+        }
+
+        Ok(acc)
+    }
+
+    fn eval_term(&self, t: &Term) -> Result<ConstValue, CompilerError> {
+        let mut acc = self.eval_factor(&t.factor)?;
+        let mut sub_term = t.sub_term.as_deref();
+
+        while let Some(s) = sub_term {
+            let rhs = self.eval_factor(&s.factor)?;
+            let pos = s.factor.pos();
+
+            acc = match (&s.op, acc, rhs) {
+                (
+                    MultiplicativeOp::Div | MultiplicativeOp::Mod,
+                    ConstValue::Integer(_),
+                    ConstValue::Integer(0),
+                ) => {
+                    return Err(
+                        CompilerError::semantic("Division by zero in constant expression")
+                            .with_span(pos),
+                    )
+                }
+                (MultiplicativeOp::Mul, a, b) if real_operands(&a, &b).is_some() => {
+                    let (a, b) = real_operands(&a, &b).unwrap_or_default();
+                    ConstValue::Real(a * b)
+                }
+                (MultiplicativeOp::Mul, ConstValue::Integer(a), ConstValue::Integer(b)) => {
+                    ConstValue::Integer(a.checked_mul(b).ok_or_else(|| overflow(pos))?)
+                }
+                (MultiplicativeOp::Div, ConstValue::Integer(a), ConstValue::Integer(b)) => {
+                    ConstValue::Integer(a.checked_div(b).ok_or_else(|| overflow(pos))?)
+                }
+                (MultiplicativeOp::Mod, ConstValue::Integer(_), ConstValue::Integer(b))
+                    if b < 0 && self.div_mod == DivMod::Iso =>
+                {
+                    return Err(CompilerError::semantic(
+                        "Divisor of mod must be positive in ISO Pascal",
+                    )
+                    .with_span(pos))
+                }
+                (MultiplicativeOp::Mod, ConstValue::Integer(a), ConstValue::Integer(b)) => {
+                    let rem = match self.div_mod {
+                        DivMod::Iso => a.checked_rem_euclid(b),
+                        DivMod::Truncated => a.checked_rem(b),
+                    };
+                    ConstValue::Integer(rem.ok_or_else(|| overflow(pos))?)
+                }
+                (MultiplicativeOp::And, ConstValue::Boolean(a), ConstValue::Boolean(b)) => {
+                    ConstValue::Boolean(a && b)
+                }
+                (op, a, b) => return Err(invalid_operands(op, a, b, pos)),
+            };
+
+            sub_term = s.sub_term.as_deref();
+        }
+
+        Ok(acc)
+    }
+
+    pub fn eval_factor(&self, f: &Factor) -> Result<ConstValue, CompilerError> {
+        match f {
+            Factor::Integer(t) => match t.token {
+                TokenType::Integer(i) | TokenType::HexInteger(i) => Ok(ConstValue::Integer(i)),
+                _ => Err(not_constant(t.pos)),
+            },
+            Factor::Char(t) => match &t.token {
+                TokenType::StringLiteral(s) => match s.chars().next() {
+                    Some(c) => Ok(ConstValue::Char(c)),
+                    None => Err(not_constant(t.pos)),
+                },
+                TokenType::CharCode(c) => Ok(ConstValue::Char(*c)),
+                _ => Err(not_constant(t.pos)),
+            },
+            Factor::String(t) => match &t.token {
+                TokenType::StringLiteral(s) => Ok(ConstValue::String(s.clone())),
+                _ => Err(not_constant(t.pos)),
+            },
+            Factor::Identifier(id) => {
+                let value = id
+                    .symbol
+                    .and_then(|symbol| self.tree.symbol(symbol).value.clone());
+
+                value.ok_or_else(|| {
+                    CompilerError::semantic(format!("{:?} is not a constant", id.get_id()))
+                        .with_span(id.id.pos)
+                })
+            }
+            Factor::Call(c) => Err(CompilerError::semantic(format!(
+                "Function call {:?} is not a constant",
+                c.name.get_id()
+            ))
+            .with_span(c.name.id.pos)),
+            Factor::Selected(v) => Err(CompilerError::semantic(format!(
+                "Component of {:?} is not a constant",
+                v.name.get_id()
+            ))
+            .with_span(v.name.id.pos)),
+            // No constant values of set types yet
+            Factor::Set(s) => Err(not_constant(s.pos)),
+            Factor::Address(v) => Err(not_constant(v.name.id.pos)),
+            Factor::Nil(t) => Err(not_constant(t.pos)),
+            Factor::Not(operand) => match self.eval_factor(operand)? {
+                ConstValue::Boolean(b) => Ok(ConstValue::Boolean(!b)),
+                ConstValue::Integer(i) => Ok(ConstValue::Integer(!i)),
+                value => Err(CompilerError::semantic(format!(
+                    "Operator 'not' cannot be applied to a constant of type {}",
+                    value.type_name()
+                ))
+                .with_span(operand.pos())),
+            },
+            Factor::Expression(e) => self.eval_expr(e),
+            Factor::Real(t) => match t.token {
+                TokenType::Real(r) => Ok(ConstValue::Real(r)),
+                _ => Err(not_constant(t.pos)),
+            },
+        }
+    }
+}
+
+fn not_constant(pos: (usize, usize)) -> CompilerError {
+    CompilerError::semantic("Expected constant expression").with_span(pos)
+}
+
+fn overflow(pos: (usize, usize)) -> CompilerError {
+    CompilerError::semantic("Integer overflow in constant expression").with_span(pos)
+}
+
+fn invalid_operands<T: fmt::Debug>(
+    op: &T,
+    a: ConstValue,
+    b: ConstValue,
+    pos: (usize, usize),
+) -> CompilerError {
+    CompilerError::semantic(format!(
+        "Operator {:?} cannot be applied to constants of types {} and {}",
+        op,
+        a.type_name(),
+        b.type_name()
+    ))
+    .with_span(pos)
+}
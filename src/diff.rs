@@ -0,0 +1,679 @@
+use crate::scope::SymbolId;
+use crate::syntax::*;
+use crate::token::TokenType;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Default, Clone)]
+pub struct DiffOptions {
+    // Programs that differ only by a consistent renaming of user identifiers are equal
+    pub ignore_names: bool,
+}
+
+/// First place where two programs differ, with a position in each of them
+pub struct Difference {
+    pub first: (usize, usize),
+    pub second: (usize, usize),
+    pub description: String,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}:{}] vs [{}:{}] {}",
+            self.first.0, self.first.1, self.second.0, self.second.1, self.description
+        )
+    }
+}
+
+/// Compares two programs structurally. Formatting and identifier casing
+/// never matter, names don't matter either with `ignore_names`.
+pub fn diff_programs(a: &Program, b: &Program, options: &DiffOptions) -> Option<Difference> {
+    let mut differ = Differ {
+        a,
+        b,
+        options,
+        renamed: HashMap::new(),
+        renamed_back: HashMap::new(),
+    };
+
+    differ.program().err()
+}
+
+type DiffResult = Result<(), Difference>;
+
+// Const, type and var sections of a program or procedure
+type Sections<'a> = (
+    &'a Option<Box<ConstSection>>,
+    &'a Option<Box<TypeSection>>,
+    &'a Option<Box<VarSection>>,
+);
+
+struct Differ<'a> {
+    a: &'a Program,
+    b: &'a Program,
+    options: &'a DiffOptions,
+    // Symbols matched so far in both directions, so renaming stays consistent
+    renamed: HashMap<SymbolId, SymbolId>,
+    renamed_back: HashMap<SymbolId, SymbolId>,
+}
+
+fn differ(first: (usize, usize), second: (usize, usize), description: String) -> DiffResult {
+    Err(Difference {
+        first,
+        second,
+        description,
+    })
+}
+
+impl Differ<'_> {
+    fn program(&mut self) -> DiffResult {
+        let (a, b) = (self.a, self.b);
+        self.identifier(&a.identifier, &b.identifier)?;
+        let pos = a.identifier.id.pos;
+        self.list(
+            "program parameters",
+            &a.parameters,
+            &b.parameters,
+            pos,
+            |d, x, y| d.identifier(x, y),
+        )?;
+        self.list("units", &a.units, &b.units, pos, |d, x, y| {
+            d.identifier(x, y)
+        })?;
+
+        self.sections(
+            (&a.const_section, &a.type_section, &a.var_section),
+            (&b.const_section, &b.type_section, &b.var_section),
+            pos,
+        )?;
+        self.list(
+            "procedures",
+            &a.procedures,
+            &b.procedures,
+            pos,
+            |d, x, y| d.procedure(x, y),
+        )?;
+        self.compound(&a.compound, &b.compound, pos)
+    }
+
+    fn sections(&mut self, a: Sections, b: Sections, pos: (usize, usize)) -> DiffResult {
+        let consts_a = a.0.as_ref().map_or(&[][..], |s| &s.constants);
+        let consts_b = b.0.as_ref().map_or(&[][..], |s| &s.constants);
+        self.list("constants", consts_a, consts_b, pos, |d, x, y| {
+            d.identifier(&x.id, &y.id)?;
+            d.expression(&x.value, &y.value)
+        })?;
+
+        let types_a = a.1.as_ref().map_or(&[][..], |s| &s.types);
+        let types_b = b.1.as_ref().map_or(&[][..], |s| &s.types);
+        self.list("type declarations", types_a, types_b, pos, |d, x, y| {
+            d.identifier(&x.id, &y.id)?;
+            d.type_spec(&x.parent, &y.parent)
+        })?;
+
+        let vars_a = a.2.as_ref().map_or(&[][..], |s| &s.declarations);
+        let vars_b = b.2.as_ref().map_or(&[][..], |s| &s.declarations);
+        self.list("var declarations", vars_a, vars_b, pos, |d, x, y| {
+            d.identifier(&x.id, &y.id)?;
+            d.type_spec(&x.type_name, &y.type_name)
+        })
+    }
+
+    fn type_spec(&mut self, a: &TypeSpec, b: &TypeSpec) -> DiffResult {
+        match (a, b) {
+            (TypeSpec::Named(x), TypeSpec::Named(y)) => self.identifier(x, y),
+            (TypeSpec::Array(x), TypeSpec::Array(y)) => {
+                self.type_spec(&x.index, &y.index)?;
+                self.type_spec(&x.element, &y.element)
+            }
+            (TypeSpec::Record(x), TypeSpec::Record(y)) => {
+                self.fields(&x.fields, &y.fields, x.pos)?;
+                self.variant_part(x.variant.as_ref(), y.variant.as_ref(), x.pos)
+            }
+            (TypeSpec::Set(x), TypeSpec::Set(y)) => self.type_spec(&x.element, &y.element),
+            (TypeSpec::String(x), TypeSpec::String(y)) => {
+                self.identifier(&x.name, &y.name)?;
+                self.simple_expression(&x.length, &y.length)
+            }
+            (TypeSpec::Subrange(x), TypeSpec::Subrange(y)) => {
+                self.simple_expression(&x.low, &y.low)?;
+                self.simple_expression(&x.high, &y.high)
+            }
+            (TypeSpec::Pointer(x), TypeSpec::Pointer(y)) => self.identifier(&x.target, &y.target),
+            (TypeSpec::Enum(x), TypeSpec::Enum(y)) => {
+                self.list("enumerators", &x.values, &y.values, x.pos, |d, v1, v2| {
+                    d.identifier(v1, v2)
+                })
+            }
+            _ => differ(a.pos(), b.pos(), "different kinds of type".into()),
+        }
+    }
+
+    fn procedure(&mut self, a: &ProcedureDeclaration, b: &ProcedureDeclaration) -> DiffResult {
+        self.identifier(&a.id, &b.id)?;
+        let pos = a.id.id.pos;
+
+        match (&a.result_type, &b.result_type) {
+            (Some(x), Some(y)) => self.identifier(x, y)?,
+            (None, None) => (),
+            _ => return differ(pos, b.id.id.pos, "function vs procedure".into()),
+        }
+
+        if a.forward != b.forward {
+            return differ(pos, b.id.id.pos, "forward declaration vs body".into());
+        }
+        if a.short_heading != b.short_heading {
+            return differ(pos, b.id.id.pos, "heading left out vs repeated".into());
+        }
+
+        self.list("parameters", &a.params, &b.params, pos, |d, x, y| {
+            d.identifier(&x.id, &y.id)?;
+            d.identifier(&x.type_name, &y.type_name)?;
+
+            if x.by_reference != y.by_reference {
+                return differ(x.id.id.pos, y.id.id.pos, "VAR vs value parameter".into());
+            }
+            Ok(())
+        })?;
+
+        self.sections(
+            (&a.const_section, &a.type_section, &a.var_section),
+            (&b.const_section, &b.type_section, &b.var_section),
+            pos,
+        )?;
+        self.list(
+            "procedures",
+            &a.procedures,
+            &b.procedures,
+            pos,
+            |d, x, y| d.procedure(x, y),
+        )?;
+        self.compound(&a.compound, &b.compound, pos)
+    }
+
+    /// Same number of items, pairwise equal. `pos` is used if one list is empty.
+    fn list<T>(
+        &mut self,
+        what: &str,
+        a: &[T],
+        b: &[T],
+        pos: (usize, usize),
+        mut compare: impl FnMut(&mut Self, &T, &T) -> DiffResult,
+    ) -> DiffResult {
+        for (x, y) in a.iter().zip(b.iter()) {
+            compare(self, x, y)?;
+        }
+
+        if a.len() != b.len() {
+            return differ(pos, pos, format!("{} {} vs {}", what, a.len(), b.len()));
+        }
+
+        Ok(())
+    }
+
+    fn compound(&mut self, a: &Compound, b: &Compound, pos: (usize, usize)) -> DiffResult {
+        self.list(
+            "statements",
+            &a.statements,
+            &b.statements,
+            pos,
+            |d, x, y| d.statement(x, y),
+        )
+    }
+
+    fn statement(&mut self, a: &Statement, b: &Statement) -> DiffResult {
+        match (a, b) {
+            (Statement::Simple(x), Statement::Simple(y)) => {
+                self.identifier(&x.name, &y.name)?;
+                self.selectors(&x.selectors, &y.selectors, x.name.id.pos)?;
+                self.expression(&x.value, &y.value)
+            }
+            (Statement::Cond(x), Statement::Cond(y)) => {
+                self.expression(&x.condition, &y.condition)?;
+                self.statement(&x.statement, &y.statement)?;
+
+                match (&x.else_statement, &y.else_statement) {
+                    (Some(e1), Some(e2)) => self.statement(e1, e2),
+                    (None, None) => Ok(()),
+                    _ => differ(
+                        statement_pos(a),
+                        statement_pos(b),
+                        "else branch present in only one program".into(),
+                    ),
+                }
+            }
+            (Statement::While(x), Statement::While(y)) => {
+                self.expression(&x.condition, &y.condition)?;
+                self.statement(&x.statement, &y.statement)
+            }
+            (Statement::Repeat(x), Statement::Repeat(y)) => {
+                let pos = statement_pos(a);
+                self.list(
+                    "statements",
+                    &x.statements,
+                    &y.statements,
+                    pos,
+                    |d, s1, s2| d.statement(s1, s2),
+                )?;
+                self.expression(&x.condition, &y.condition)
+            }
+            (Statement::For(x), Statement::For(y)) => {
+                self.identifier(&x.variable, &y.variable)?;
+                self.expression(&x.start, &y.start)?;
+
+                if x.downto != y.downto {
+                    return differ(x.end.pos(), y.end.pos(), "'to' vs 'downto' loop".into());
+                }
+
+                self.expression(&x.end, &y.end)?;
+                self.statement(&x.statement, &y.statement)
+            }
+            (Statement::Case(x), Statement::Case(y)) => {
+                let pos = statement_pos(a);
+                self.expression(&x.selector, &y.selector)?;
+                self.list("branches", &x.branches, &y.branches, pos, |d, b1, b2| {
+                    d.list(
+                        "labels",
+                        &b1.labels,
+                        &b2.labels,
+                        b1.labels[0].low.pos(),
+                        |d, l1, l2| d.case_label(l1, l2),
+                    )?;
+                    d.statement(&b1.statement, &b2.statement)
+                })?;
+
+                match (&x.else_statements, &y.else_statements) {
+                    (Some(e1), Some(e2)) => {
+                        self.list("else statements", e1, e2, pos, |d, s1, s2| {
+                            d.statement(s1, s2)
+                        })
+                    }
+                    (None, None) => Ok(()),
+                    _ => differ(
+                        statement_pos(a),
+                        statement_pos(b),
+                        "else branch present in only one program".into(),
+                    ),
+                }
+            }
+            (Statement::Call(x), Statement::Call(y)) => self.call(x, y),
+            (Statement::Compound(x), Statement::Compound(y)) => {
+                self.compound(x, y, statement_pos(a))
+            }
+            (Statement::Empty, Statement::Empty) => Ok(()),
+            _ => differ(
+                statement_pos(a),
+                statement_pos(b),
+                format!("{} vs {}", statement_kind(a), statement_kind(b)),
+            ),
+        }
+    }
+
+    fn call(&mut self, a: &Call, b: &Call) -> DiffResult {
+        self.identifier(&a.name, &b.name)?;
+        self.list(
+            "arguments",
+            &a.arguments,
+            &b.arguments,
+            a.name.id.pos,
+            |d, x, y| d.expression(x, y),
+        )?;
+        self.list(
+            "field widths",
+            &a.formats,
+            &b.formats,
+            a.name.id.pos,
+            |d, (i, x), (j, y)| match i == j {
+                true => d.write_format(x, y),
+                false => differ(
+                    x.width.pos(),
+                    y.width.pos(),
+                    format!("width of argument {} vs {}", i + 1, j + 1),
+                ),
+            },
+        )
+    }
+
+    fn write_format(&mut self, a: &WriteFormat, b: &WriteFormat) -> DiffResult {
+        self.expression(&a.width, &b.width)?;
+        match (&a.decimals, &b.decimals) {
+            (Some(x), Some(y)) => self.expression(x, y),
+            (None, None) => Ok(()),
+            (x, y) => differ(
+                x.as_ref().map_or(a.width.pos(), |e| e.pos()),
+                y.as_ref().map_or(b.width.pos(), |e| e.pos()),
+                format!(
+                    "{} vs {}",
+                    if x.is_some() {
+                        "decimals"
+                    } else {
+                        "no decimals"
+                    },
+                    if y.is_some() {
+                        "decimals"
+                    } else {
+                        "no decimals"
+                    }
+                ),
+            ),
+        }
+    }
+
+    fn selectors(&mut self, a: &[Selector], b: &[Selector], pos: (usize, usize)) -> DiffResult {
+        self.list("selectors", a, b, pos, |d, x, y| match (x, y) {
+            (Selector::Index(i1), Selector::Index(i2)) => d.expression(i1, i2),
+            // Field names aren't symbols, so they are compared even when ignoring names
+            (Selector::Field(f1), Selector::Field(f2)) if f1.get_id() == f2.get_id() => Ok(()),
+            (Selector::Field(f1), Selector::Field(f2)) => differ(
+                f1.id.pos,
+                f2.id.pos,
+                format!("field {:?} vs {:?}", f1.get_id(), f2.get_id()),
+            ),
+            (Selector::Deref(_), Selector::Deref(_)) => Ok(()),
+            _ => differ(pos, pos, "different kinds of selector".into()),
+        })
+    }
+
+    fn fields(
+        &mut self,
+        a: &[FieldDeclaration],
+        b: &[FieldDeclaration],
+        pos: (usize, usize),
+    ) -> DiffResult {
+        self.list("fields", a, b, pos, |d, f1, f2| {
+            if f1.id.get_id() != f2.id.get_id() {
+                return differ(
+                    f1.id.id.pos,
+                    f2.id.id.pos,
+                    format!("field {:?} vs {:?}", f1.id.get_id(), f2.id.get_id()),
+                );
+            }
+            d.type_spec(&f1.type_name, &f2.type_name)
+        })
+    }
+
+    fn variant_part(
+        &mut self,
+        a: Option<&VariantPart>,
+        b: Option<&VariantPart>,
+        pos: (usize, usize),
+    ) -> DiffResult {
+        let (x, y) = match (a, b) {
+            (Some(x), Some(y)) => (x, y),
+            (None, None) => return Ok(()),
+            _ => return differ(pos, pos, "variant part vs none".into()),
+        };
+
+        match (&x.tag, &y.tag) {
+            (Some(t1), Some(t2)) if t1.get_id() != t2.get_id() => {
+                return differ(
+                    t1.id.pos,
+                    t2.id.pos,
+                    format!("tag {:?} vs {:?}", t1.get_id(), t2.get_id()),
+                )
+            }
+            (Some(_), Some(_)) | (None, None) => (),
+            _ => return differ(x.pos, y.pos, "named vs anonymous tag".into()),
+        }
+        self.identifier(&x.tag_type, &y.tag_type)?;
+
+        self.list("variants", &x.variants, &y.variants, x.pos, |d, v1, v2| {
+            d.list(
+                "labels",
+                &v1.labels,
+                &v2.labels,
+                v1.labels[0].low.pos(),
+                |d, l1, l2| d.case_label(l1, l2),
+            )?;
+            d.fields(&v1.fields, &v2.fields, v1.labels[0].low.pos())?;
+            d.variant_part(
+                v1.variant.as_deref(),
+                v2.variant.as_deref(),
+                v1.labels[0].low.pos(),
+            )
+        })
+    }
+
+    fn case_label(&mut self, a: &CaseLabel, b: &CaseLabel) -> DiffResult {
+        self.simple_expression(&a.low, &b.low)?;
+
+        match (&a.high, &b.high) {
+            (Some(x), Some(y)) => self.simple_expression(x, y),
+            (None, None) => Ok(()),
+            _ => differ(
+                a.low.pos(),
+                b.low.pos(),
+                "range vs single case label".into(),
+            ),
+        }
+    }
+
+    fn set_element(&mut self, a: &SetElement, b: &SetElement) -> DiffResult {
+        self.expression(&a.low, &b.low)?;
+
+        match (&a.high, &b.high) {
+            (Some(x), Some(y)) => self.expression(x, y),
+            (None, None) => Ok(()),
+            _ => differ(
+                a.low.pos(),
+                b.low.pos(),
+                "range vs single set element".into(),
+            ),
+        }
+    }
+
+    fn expression(&mut self, a: &Expression, b: &Expression) -> DiffResult {
+        match (a, b) {
+            (Expression::Simple(x), Expression::Simple(y)) => self.simple_expression(x, y),
+            (Expression::Relational(x), Expression::Relational(y)) => {
+                self.simple_expression(&x.first, &y.first)?;
+
+                let (op_a, op_b) = (format!("{:?}", x.op), format!("{:?}", y.op));
+                if op_a != op_b {
+                    return differ(
+                        x.second.pos(),
+                        y.second.pos(),
+                        format!("operator {} vs {}", op_a, op_b),
+                    );
+                }
+
+                self.simple_expression(&x.second, &y.second)
+            }
+            _ => differ(a.pos(), b.pos(), "simple vs relational expression".into()),
+        }
+    }
+
+    fn simple_expression(&mut self, a: &SimpleExpression, b: &SimpleExpression) -> DiffResult {
+        let (sign_a, sign_b) = (format!("{:?}", a.sign), format!("{:?}", b.sign));
+        if sign_a != sign_b {
+            return differ(a.pos(), b.pos(), format!("sign {} vs {}", sign_a, sign_b));
+        }
+        self.term(&a.term, &b.term)?;
+
+        let mut pair = (a.sub_expr.as_ref(), b.sub_expr.as_ref());
+        loop {
+            match pair {
+                (Some(x), Some(y)) => {
+                    let (op_a, op_b) = (format!("{:?}", x.op), format!("{:?}", y.op));
+                    if op_a != op_b {
+                        return differ(
+                            x.term.factor.pos(),
+                            y.term.factor.pos(),
+                            format!("operator {} vs {}", op_a, op_b),
+                        );
+                    }
+
+                    self.term(&x.term, &y.term)?;
+                    pair = (x.sub_expr.as_deref(), y.sub_expr.as_deref());
+                }
+                (None, None) => return Ok(()),
+                _ => {
+                    return differ(
+                        a.pos(),
+                        b.pos(),
+                        "expressions have different number of terms".into(),
+                    )
+                }
+            }
+        }
+    }
+
+    fn term(&mut self, a: &Term, b: &Term) -> DiffResult {
+        self.factor(&a.factor, &b.factor)?;
+
+        let mut pair = (a.sub_term.as_deref(), b.sub_term.as_deref());
+        loop {
+            match pair {
+                (Some(x), Some(y)) => {
+                    let (op_a, op_b) = (format!("{:?}", x.op), format!("{:?}", y.op));
+                    if op_a != op_b {
+                        return differ(
+                            x.factor.pos(),
+                            y.factor.pos(),
+                            format!("operator {} vs {}", op_a, op_b),
+                        );
+                    }
+
+                    self.factor(&x.factor, &y.factor)?;
+                    pair = (x.sub_term.as_deref(), y.sub_term.as_deref());
+                }
+                (None, None) => return Ok(()),
+                _ => {
+                    return differ(
+                        a.factor.pos(),
+                        b.factor.pos(),
+                        "terms have different number of factors".into(),
+                    )
+                }
+            }
+        }
+    }
+
+    fn factor(&mut self, a: &Factor, b: &Factor) -> DiffResult {
+        match (a, b) {
+            (Factor::Integer(_), Factor::Integer(_))
+            | (Factor::Real(_), Factor::Real(_))
+            | (Factor::Char(_), Factor::Char(_))
+            | (Factor::String(_), Factor::String(_))
+                if factor_text(a) == factor_text(b) =>
+            {
+                Ok(())
+            }
+            (Factor::Identifier(x), Factor::Identifier(y)) => self.identifier(x, y),
+            (Factor::Call(x), Factor::Call(y)) => self.call(x, y),
+            (Factor::Selected(x), Factor::Selected(y)) => {
+                self.identifier(&x.name, &y.name)?;
+                self.selectors(&x.selectors, &y.selectors, x.name.id.pos)
+            }
+            (Factor::Address(x), Factor::Address(y)) => {
+                self.identifier(&x.name, &y.name)?;
+                self.selectors(&x.selectors, &y.selectors, x.name.id.pos)
+            }
+            (Factor::Nil(_), Factor::Nil(_)) => Ok(()),
+            (Factor::Not(x), Factor::Not(y)) => self.factor(x, y),
+            (Factor::Set(x), Factor::Set(y)) => self.list(
+                "set elements",
+                &x.elements,
+                &y.elements,
+                x.pos,
+                |d, e1, e2| d.set_element(e1, e2),
+            ),
+            (Factor::Expression(x), Factor::Expression(y)) => self.expression(x, y),
+            _ => differ(
+                a.pos(),
+                b.pos(),
+                format!("{} vs {}", factor_text(a), factor_text(b)),
+            ),
+        }
+    }
+
+    fn identifier(&mut self, a: &Identifier, b: &Identifier) -> DiffResult {
+        let (name_a, name_b) = (a.get_id(), b.get_id());
+        let mismatch = || {
+            differ(
+                a.id.pos,
+                b.id.pos,
+                format!("identifier {:?} vs {:?}", name_a, name_b),
+            )
+        };
+
+        let user_symbols = (self.user_symbol(self.a, a), self.user_symbol(self.b, b));
+
+        match user_symbols {
+            (Some(x), Some(y)) if self.options.ignore_names => {
+                let forward = *self.renamed.entry(x).or_insert(y);
+                let backward = *self.renamed_back.entry(y).or_insert(x);
+
+                if forward == y && backward == x {
+                    Ok(())
+                } else {
+                    mismatch()
+                }
+            }
+            _ if name_a.to_lowercase() == name_b.to_lowercase() => Ok(()),
+            _ => mismatch(),
+        }
+    }
+
+    /// Symbol of an identifier declared in the program itself (not a builtin)
+    fn user_symbol(&self, program: &Program, id: &Identifier) -> Option<SymbolId> {
+        id.symbol
+            .filter(|s| program.scope_tree().symbol(*s).pos != (0, 0))
+    }
+}
+
+fn statement_kind(s: &Statement) -> &'static str {
+    match s {
+        Statement::Simple(_) => "assignment",
+        Statement::Cond(_) => "if statement",
+        Statement::While(_) => "while loop",
+        Statement::Repeat(_) => "repeat loop",
+        Statement::For(_) => "for loop",
+        Statement::Case(_) => "case statement",
+        Statement::Call(_) => "procedure call",
+        Statement::Compound(_) => "compound statement",
+        Statement::Empty => "empty statement",
+    }
+}
+
+fn statement_pos(s: &Statement) -> (usize, usize) {
+    match s {
+        Statement::Simple(a) => a.name.id.pos,
+        Statement::Cond(c) => c.condition.pos(),
+        Statement::While(w) => w.condition.pos(),
+        Statement::For(l) => l.variable.id.pos,
+        Statement::Case(c) => c.selector.pos(),
+        Statement::Call(c) => c.name.id.pos,
+        Statement::Repeat(r) => r
+            .statements
+            .first()
+            .map_or(r.condition.pos(), statement_pos),
+        // Neither BEGIN nor an empty statement is kept in the tree
+        Statement::Compound(c) => c.statements.first().map_or((0, 0), statement_pos),
+        Statement::Empty => (0, 0),
+    }
+}
+
+fn factor_text(f: &Factor) -> String {
+    match f {
+        Factor::Integer(t) | Factor::Real(t) | Factor::Char(t) | Factor::String(t) => {
+            match &t.token {
+                TokenType::Integer(i) | TokenType::HexInteger(i) => i.to_string(),
+                TokenType::Real(r) => r.to_string(),
+                TokenType::StringLiteral(s) => format!("'{}'", s.replace('\'', "''")),
+                TokenType::CharCode(c) => format!("#{}", *c as u32),
+                _ => "literal".into(),
+            }
+        }
+        Factor::Identifier(id) => format!("{:?}", id.get_id()),
+        Factor::Call(c) => format!("call of {:?}", c.name.get_id()),
+        Factor::Selected(v) => format!("component of {:?}", v.name.get_id()),
+        Factor::Set(_) => "set constructor".into(),
+        Factor::Address(v) => format!("address of {:?}", v.name.get_id()),
+        Factor::Nil(_) => "nil".into(),
+        Factor::Not(_) => "not".into(),
+        Factor::Expression(_) => "parenthesized expression".into(),
+    }
+}
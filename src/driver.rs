@@ -0,0 +1,317 @@
+use crate::analyzer::Analyzer;
+use crate::error::{CompilerError, Diagnostic, ErrorType};
+use crate::io::CharReader;
+use crate::json;
+use crate::lexer::{Directive, Lexer};
+use crate::options::Options;
+use crate::parser::Parser;
+use crate::pass::Pass;
+use crate::scope::{ScopeId, ScopeTree};
+use crate::syntax::{Expression, Module, Program, Statement, Unit};
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+/// Everything a compilation produced: the AST (absent only if parsing
+/// could not recover) and all diagnostics in the order they were found
+pub struct CompilationResult {
+    pub ast: Option<Program>,
+    // Set instead of `ast` when the source is a unit. Passes only run on programs.
+    pub unit: Option<Unit>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub token_count: usize,
+    // `{$...}` directives in source order, for phases which react to them
+    pub directives: Vec<Directive>,
+}
+
+impl CompilationResult {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.is_error())
+    }
+
+    /// AST for phases which need a complete program (code generation, execution).
+    /// Dumping or analyzing a partial tree should use `ast` directly.
+    pub fn complete_ast(&self) -> Result<&Program, CompilerError> {
+        match &self.ast {
+            Some(program) if !program.has_errors() => Ok(program),
+            _ => Err(CompilerError::error(
+                ErrorType::Compilation,
+                format!(
+                    "Program contains {} error(s), refusing to continue",
+                    self.diagnostics.iter().filter(|d| d.is_error()).count()
+                ),
+            )),
+        }
+    }
+}
+
+/// Compiler with extra passes registered by library users
+pub struct Compiler {
+    options: Options,
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl Compiler {
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            passes: Vec::new(),
+        }
+    }
+
+    /// Passes run in registration order
+    pub fn register_pass(&mut self, pass: Box<dyn Pass>) -> &mut Self {
+        self.passes.push(pass);
+        self
+    }
+
+    pub fn compile_file(&mut self, filename: &str) -> Result<CompilationResult, CompilerError> {
+        let reader = CharReader::new(String::from(filename))
+            .map_err(|e| CompilerError::io(format!("Failed to open file {}", filename), e))?;
+
+        Ok(self.compile(reader, &mut |_| ()))
+    }
+
+    /// Compiles source text held in memory, doesn't touch the file system
+    pub fn compile_source(&mut self, source: &str) -> CompilationResult {
+        self.compile(CharReader::from_source(source), &mut |_| ())
+    }
+
+    /// Like `compile_source`, but hands each diagnostic to `sink` as soon as it is
+    /// found (e.g. for live feedback in an editor). The result still has all of them.
+    pub fn compile_source_streaming(
+        &mut self,
+        source: &str,
+        sink: &mut (dyn FnMut(Diagnostic) + Send),
+    ) -> CompilationResult {
+        self.compile(CharReader::from_source(source), sink)
+    }
+
+    fn compile(
+        &mut self,
+        reader: CharReader,
+        sink: &mut (dyn FnMut(Diagnostic) + Send),
+    ) -> CompilationResult {
+        let lexer = Lexer::new(reader);
+        let mut parser = Parser::new(lexer, &self.options);
+        parser.set_sink(&mut *sink);
+
+        let res = catch_panic(|| parser.parse_module());
+        let mut diagnostics = std::mem::take(&mut parser.errors);
+        // The parser has streamed its own errors, the rest go out from here
+        let mut streamed = diagnostics.len();
+
+        let (ast, unit) = match res {
+            Ok(Ok(Module::Program(program))) => (Some(program), None),
+            Ok(Ok(Module::Unit(unit))) => (None, Some(unit)),
+            Ok(Err(e)) => {
+                let e = match (parser.current_file(), e.file()) {
+                    (Some(file), None) => e.with_file(file),
+                    _ => e,
+                };
+                diagnostics.push(e);
+                (None, None)
+            }
+            Err(panic) => {
+                diagnostics.push(internal_error("parsing", panic, parser.position()));
+                (None, None)
+            }
+        };
+        let token_count = parser.token_count;
+        let directives = std::mem::take(&mut parser.directives);
+
+        if let Some(program) = &ast {
+            for pass in self.passes.iter_mut() {
+                let res = catch_panic(|| pass.run(program, &mut diagnostics));
+
+                if let Err(panic) = res {
+                    let phase = format!("running pass '{}'", pass.name());
+                    diagnostics.push(internal_error(&phase, panic, (0, 0)));
+                }
+
+                streamed = stream(&diagnostics, streamed, sink);
+            }
+        }
+        stream(&diagnostics, streamed, sink);
+
+        CompilationResult {
+            ast,
+            unit,
+            diagnostics,
+            token_count,
+            directives,
+        }
+    }
+}
+
+pub fn compile_file(filename: &str, options: &Options) -> Result<CompilationResult, CompilerError> {
+    Compiler::new(options.clone()).compile_file(filename)
+}
+
+pub fn compile_source(source: &str, options: &Options) -> CompilationResult {
+    Compiler::new(options.clone()).compile_source(source)
+}
+
+/// Names a lone expression or statement can use: builtins only, or whatever
+/// is visible at some position of an already compiled program
+pub struct ScopeContext {
+    tree: ScopeTree,
+    scope: ScopeId,
+}
+
+impl ScopeContext {
+    /// Builtins depend on the options, e.g. no `string` in ISO 7185 mode
+    pub fn builtins(options: &Options) -> Self {
+        Self {
+            tree: Analyzer::with_options(options).take_scope_tree(),
+            scope: ScopeId(0),
+        }
+    }
+
+    /// Scope of the innermost routine (or the program) around `pos`
+    pub fn at(program: &Program, pos: (usize, usize)) -> Self {
+        let tree = program.scope_tree().clone();
+        let scope = tree.scope_at(pos);
+
+        Self { tree, scope }
+    }
+}
+
+/// Parses and checks source which is a single expression, e.g. a watch
+/// expression of the debugger. Positions are relative to `source`.
+pub fn parse_expression(
+    source: &str,
+    context: &ScopeContext,
+    options: &Options,
+) -> Result<Expression, Vec<Diagnostic>> {
+    parse_fragment(source, context, options, |parser| {
+        parser.parse_expression_only()
+    })
+}
+
+/// Parses and checks source which is a single statement, e.g. a REPL line
+pub fn parse_statement(
+    source: &str,
+    context: &ScopeContext,
+    options: &Options,
+) -> Result<Statement, Vec<Diagnostic>> {
+    parse_fragment(source, context, options, |parser| {
+        parser.parse_statement_only()
+    })
+}
+
+fn parse_fragment<T>(
+    source: &str,
+    context: &ScopeContext,
+    options: &Options,
+    parse: impl FnOnce(&mut Parser) -> Result<T, CompilerError>,
+) -> Result<T, Vec<Diagnostic>> {
+    let lexer = Lexer::new(CharReader::from_source(source));
+    let mut parser = Parser::new(lexer, options);
+    parser.resume_scope(context.tree.clone(), context.scope);
+
+    let res = catch_panic(|| parse(&mut parser));
+    let mut diagnostics = std::mem::take(&mut parser.errors);
+
+    match res {
+        Ok(Ok(fragment)) if diagnostics.is_empty() => return Ok(fragment),
+        Ok(Ok(_)) => (),
+        Ok(Err(e)) => diagnostics.push(e),
+        Err(panic) => diagnostics.push(internal_error("parsing", panic, parser.position())),
+    }
+
+    Err(diagnostics)
+}
+
+/// Diagnostics for the source as a JSON document, meant for embedding
+/// (e.g. a browser playground built for wasm32-unknown-unknown)
+pub fn compile_to_diagnostics_json(source: &str) -> String {
+    let result = compile_source(source, &Options::default());
+    let diagnostics = result
+        .diagnostics
+        .iter()
+        .enumerate()
+        .map(|(i, d)| d.to_json(i));
+
+    format!(
+        "{{\"parsed\":{},\"diagnostics\":{}}}",
+        result.ast.is_some(),
+        json::array(diagnostics)
+    )
+}
+
+thread_local! {
+    // Phases being run by catch_panic on this thread, and where the last panic happened
+    static CATCHING: Cell<usize> = const { Cell::new(0) };
+    static PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static PANIC_HOOK: Once = Once::new();
+
+/// Runs a compiler phase, returning the panic message (with its location) if it panics.
+/// Panics of the phase don't reach the default hook, so users don't get a raw backtrace.
+fn catch_panic<T>(phase: impl FnOnce() -> T) -> Result<T, String> {
+    // The hook is process-wide, so it is set once for all threads. Panics outside of
+    // a phase still go to the hook which was there before.
+    PANIC_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| match CATCHING.with(Cell::get) {
+            0 => previous(info),
+            _ => PANIC_LOCATION.with(|l| *l.borrow_mut() = info.location().map(|l| l.to_string())),
+        }));
+    });
+
+    PANIC_LOCATION.with(|l| l.borrow_mut().take());
+    CATCHING.with(|c| c.set(c.get() + 1));
+    let res = panic::catch_unwind(AssertUnwindSafe(phase));
+    CATCHING.with(|c| c.set(c.get() - 1));
+
+    res.map_err(|payload| {
+        let message = if let Some(s) = payload.downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "unknown panic".into()
+        };
+
+        match PANIC_LOCATION.with(|l| l.borrow_mut().take()) {
+            Some(l) => format!("{} at {}", message, l),
+            None => message,
+        }
+    })
+}
+
+// Sends diagnostics added since `streamed` to the sink, returns how many have been sent
+fn stream(
+    diagnostics: &[Diagnostic],
+    streamed: usize,
+    sink: &mut (dyn FnMut(Diagnostic) + Send),
+) -> usize {
+    for d in diagnostics[streamed..].iter() {
+        sink(d.clone());
+    }
+    diagnostics.len()
+}
+
+fn internal_error(phase: &str, panic: String, pos: (usize, usize)) -> CompilerError {
+    CompilerError::error(
+        ErrorType::Internal,
+        format!("Compiler crashed while {}: {}", phase, panic),
+    )
+    .with_span(pos)
+    .with_note("this is a bug in the compiler, please report it along with the source file")
+}
+
+// Units may be compiled on worker threads, keep everything a compilation
+// owns free of Rc/RefCell and other thread-bound types
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send::<Parser>();
+    assert_send::<crate::analyzer::Analyzer>();
+    assert_send::<CompilationResult>();
+    assert_send_sync::<Diagnostic>();
+    assert_send_sync::<Program>();
+};
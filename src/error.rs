@@ -1,18 +1,33 @@
+use crate::json;
+use std::error::Error;
 use std::fmt;
+use std::io;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub enum ErrorType {
+    Io,
+    // Problems with the compilation as a whole rather than a place in the source
+    Compilation,
+    // Bug in the compiler itself
+    Internal,
     Lexical,
     Syntax,
     Semantic,
+    // Style warnings, they don't stop the compilation
+    Lint,
 }
 
 impl fmt::Display for ErrorType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            ErrorType::Io => write!(f, "IO"),
+            ErrorType::Compilation => write!(f, "Compilation"),
+            ErrorType::Internal => write!(f, "Internal Compiler"),
             ErrorType::Lexical => write!(f, "Lexical"),
             ErrorType::Syntax => write!(f, "Syntax"),
             ErrorType::Semantic => write!(f, "Semantic"),
+            ErrorType::Lint => write!(f, "Lint"),
         }
     }
 }
@@ -25,37 +40,208 @@ pub struct CompilerError {
     description: String,
     pos: (usize, usize),
     err_type: ErrorType,
+    // Included file the position is in, None for the main source
+    file: Option<Arc<str>>,
+    // Secondary positions related to the error, e.g. a previous declaration
+    labels: Vec<((usize, usize), String)>,
+    notes: Vec<String>,
+    // io::Error is not Clone, so it is shared
+    source: Option<Arc<io::Error>>,
 }
 
+// Name used by the public API for errors reported to the user
+pub type Diagnostic = CompilerError;
+
+/// Errors are built step by step:
+/// `CompilerError::semantic("Type mismatch").with_span(pos).with_note("...")`
 impl CompilerError {
-    pub fn new(description: String, pos: (usize, usize), err_type: ErrorType) -> Self {
+    pub fn error(err_type: ErrorType, description: impl Into<String>) -> Self {
         Self {
-            description,
-            pos,
+            description: description.into(),
+            pos: (0, 0),
             err_type,
+            file: None,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            source: None,
+        }
+    }
+
+    pub fn lexical(description: impl Into<String>) -> Self {
+        CompilerError::error(ErrorType::Lexical, description)
+    }
+
+    pub fn syntax(description: impl Into<String>) -> Self {
+        CompilerError::error(ErrorType::Syntax, description)
+    }
+
+    pub fn semantic(description: impl Into<String>) -> Self {
+        CompilerError::error(ErrorType::Semantic, description)
+    }
+
+    pub fn lint(description: impl Into<String>) -> Self {
+        CompilerError::error(ErrorType::Lint, description)
+    }
+
+    /// Failure to read the source itself, keeps the underlying error as its source
+    pub fn io(description: impl Into<String>, err: io::Error) -> Self {
+        Self {
+            source: Some(Arc::new(err)),
+            ..CompilerError::error(ErrorType::Io, description)
+        }
+    }
+
+    pub fn with_span(mut self, pos: (usize, usize)) -> Self {
+        self.pos = pos;
+        self
+    }
+
+    pub fn with_file(mut self, file: impl Into<Arc<str>>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    pub fn with_label(mut self, pos: (usize, usize), label: impl Into<String>) -> Self {
+        self.labels.push((pos, label.into()));
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn pos(&self) -> (usize, usize) {
+        self.pos
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    pub fn err_type(&self) -> &ErrorType {
+        &self.err_type
+    }
+
+    pub fn message(&self) -> &str {
+        &self.description
+    }
+
+    pub fn labels(&self) -> &[((usize, usize), String)] {
+        &self.labels
+    }
+
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+
+    /// False for errors about the compilation as a whole, their position means nothing
+    pub fn has_position(&self) -> bool {
+        !matches!(self.err_type, ErrorType::Io | ErrorType::Compilation)
+    }
+
+    /// First line of the printed diagnostic, without labels and notes
+    pub fn headline(&self) -> String {
+        let severity = if self.is_error() { "Error" } else { "Warning" };
+
+        let file = match &self.file {
+            Some(file) => format!("{}:", file),
+            None => String::new(),
+        };
+
+        match self.has_position() {
+            true => format!(
+                "{} {} [{}{}:{}] {}",
+                self.err_type, severity, file, self.pos.0, self.pos.1, self.description
+            ),
+            false => format!("{} {}: {}", self.err_type, severity, self.description),
         }
     }
 
-    pub fn lexical(description: String, pos: (usize, usize)) -> Self {
-        CompilerError::new(description, pos, ErrorType::Lexical)
+    /// Everything but lint warnings counts as an error
+    pub fn is_error(&self) -> bool {
+        !matches!(self.err_type, ErrorType::Lint)
     }
 
-    pub fn syntax(description: String, pos: (usize, usize)) -> Self {
-        CompilerError::new(description, pos, ErrorType::Syntax)
+    /// Phase which produced the diagnostic. Lexing, parsing and analysis run
+    /// together, so it follows from the kind of error.
+    pub fn phase(&self) -> &'static str {
+        match self.err_type {
+            ErrorType::Lexical => "lex",
+            ErrorType::Syntax => "parse",
+            ErrorType::Semantic => "sem",
+            ErrorType::Lint => "lint",
+            ErrorType::Io | ErrorType::Compilation | ErrorType::Internal => "driver",
+        }
     }
+}
+
+impl CompilerError {
+    /// `index` is the diagnostic's place among all of a compilation's, in the order found
+    pub fn to_json(&self, index: usize) -> String {
+        let labels = self.labels.iter().map(|(pos, label)| {
+            format!(
+                "{{\"line\":{},\"column\":{},\"message\":{}}}",
+                pos.0,
+                pos.1,
+                json::string(label)
+            )
+        });
+        let notes = self.notes.iter().map(|n| json::string(n));
+        // Only diagnostics in included files have one
+        let file = match &self.file {
+            Some(file) => format!("\"file\":{},", json::string(file)),
+            None => String::new(),
+        };
 
-    #[allow(dead_code)]
-    pub fn semantic(description: String, pos: (usize, usize)) -> Self {
-        CompilerError::new(description, pos, ErrorType::Semantic)
+        format!(
+            "{{\"index\":{},\"phase\":{},\"type\":{},\"severity\":{},{}\"line\":{},\"column\":{},\"message\":{},\"labels\":{},\"notes\":{}}}",
+            index,
+            json::string(self.phase()),
+            json::string(&self.err_type.to_string()),
+            json::string(if self.is_error() { "error" } else { "warning" }),
+            file,
+            self.pos.0,
+            self.pos.1,
+            json::string(&self.description),
+            json::array(labels),
+            json::array(notes)
+        )
     }
 }
 
 impl fmt::Display for CompilerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{} Error [{}:{}] {}",
-            self.err_type, self.pos.0, self.pos.1, self.description
-        )
+        write!(f, "{}", self.headline())?;
+
+        for (pos, label) in self.labels.iter() {
+            write!(f, "\n    [{}:{}] {}", pos.0, pos.1, label)?;
+        }
+
+        for note in self.notes.iter() {
+            write!(f, "\n    note: {}", note)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CompilerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompilerError")
+            .field("type", &self.err_type.to_string())
+            .field("description", &self.description)
+            .field("file", &self.file)
+            .field("position", &self.pos)
+            .field("labels", &self.labels)
+            .field("notes", &self.notes)
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl Error for CompilerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|e| e as &(dyn Error + 'static))
     }
 }
@@ -0,0 +1,35 @@
+use std::fmt::Write;
+
+/// Synthetic but valid program with `n` type declarations, `n` variables
+/// and roughly `n` statements, used to measure the front end on big inputs
+pub fn synthetic_program(n: usize) -> String {
+    let n = n.max(1);
+    let mut src = String::from("program Generated;\ntype\n");
+
+    for i in 0..n {
+        let _ = writeln!(src, "    t{} : integer;", i);
+    }
+
+    src.push_str("var\n");
+    for i in 0..n {
+        let _ = writeln!(src, "    v{}, w{} : integer;", i, i);
+    }
+
+    src.push_str("begin\n");
+    for i in 0..n {
+        let j = (i * 7 + 3) % n;
+
+        let _ = match i % 3 {
+            0 => writeln!(src, "    v{} := (w{} + {}) * 2 - v{} div 3;", i, j, i, j),
+            1 => writeln!(
+                src,
+                "    while v{} > {} do\n        v{} := v{} - 1;",
+                i, i, i, i
+            ),
+            _ => writeln!(src, "    if v{} = w{} then\n        w{} := {};", i, j, i, i),
+        };
+    }
+    src.push_str("end.\n");
+
+    src
+}
@@ -0,0 +1,193 @@
+use crate::driver::compile_source;
+use crate::options::Options;
+use crate::scope::{ScopeId, ScopeTree, TypeDef, Usage};
+use crate::syntax::*;
+use crate::visitor::*;
+use std::fmt;
+
+/// What an identifier under the cursor is, for editor hovers
+pub struct TypeInfo {
+    pub name: String,
+    // variable, constant, type, field, procedure, function or program
+    pub kind: &'static str,
+    // Type as written in the declaration, signature for procedures and functions
+    pub type_name: String,
+    // Aliases followed down to the builtin or spelled out type
+    pub resolved_type: String,
+    // Known value of constants
+    pub value: Option<String>,
+    // First and last character of the identifier
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+impl fmt::Display for TypeInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.name)?;
+
+        if !self.type_name.is_empty() {
+            write!(f, " : {}", self.type_name)?;
+        }
+        if self.resolved_type != self.type_name {
+            write!(f, " ({})", self.resolved_type)?;
+        }
+        if let Some(value) = &self.value {
+            write!(f, " = {}", value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compiles the source with `options`, as a build would, and describes the identifier
+/// at `pos` (line and column, both from 1). Parts of the program which failed to
+/// parse have no types.
+pub fn type_at(source: &str, pos: (usize, usize), options: &Options) -> Option<TypeInfo> {
+    let result = compile_source(source, options);
+    let program = result.ast.as_ref()?;
+    let tree = program.scope_tree();
+
+    let mut finder = Finder {
+        tree,
+        scope: tree.scope_at(pos),
+        pos,
+        found: None,
+    };
+
+    finder.visit_program(program);
+    finder.found
+}
+
+/// Kind of the symbol and its type, the signature for procedures and functions
+pub(crate) fn describe(name: &str, usage: &Usage) -> (&'static str, String) {
+    match usage {
+        Usage::Variable(t) => ("variable", t.clone()),
+        Usage::Constant(t) => ("constant", t.clone()),
+        Usage::Type(_) => ("type", name.to_string()),
+        Usage::Procedure(s) => ("procedure", format!("{:?}", s)),
+        Usage::Function(s) => ("function", format!("{:?}", s)),
+        Usage::Program => ("program", String::new()),
+        Usage::Unit => ("unit", String::new()),
+    }
+}
+
+struct Finder<'a> {
+    tree: &'a ScopeTree,
+    // Aliases are looked up where the cursor is
+    scope: ScopeId,
+    pos: (usize, usize),
+    found: Option<TypeInfo>,
+}
+
+impl Finder<'_> {
+    // Token positions point at the first character of the identifier
+    fn span(id: &Identifier) -> ((usize, usize), (usize, usize)) {
+        let (line, start) = id.id.pos;
+        let len = id.get_id().chars().count();
+
+        ((line, start), (line, start + len - 1))
+    }
+
+    fn covers(&self, id: &Identifier) -> bool {
+        let (start, end) = Self::span(id);
+        start <= self.pos && self.pos <= end
+    }
+
+    fn info(&self, id: &Identifier, kind: &'static str, type_name: String) -> TypeInfo {
+        let (start, end) = Self::span(id);
+
+        TypeInfo {
+            name: id.get_id(),
+            kind,
+            resolved_type: self.tree.resolve_type(self.scope, &type_name),
+            type_name,
+            value: None,
+            start,
+            end,
+        }
+    }
+
+    /// Walks the component types of `base_type`, looking for the field under the cursor
+    fn selectors(&mut self, base_type: Option<String>, selectors: &[Selector]) {
+        let mut current = base_type;
+
+        for s in selectors.iter() {
+            let resolved = current
+                .as_ref()
+                .map(|t| self.tree.resolve_type(self.scope, t));
+            let type_def = resolved.as_ref().and_then(|t| self.tree.type_def(t));
+
+            current = match (s, type_def) {
+                // Pointer types made by `@` aren't declared, so go by the name
+                (Selector::Deref(_), _) => resolved
+                    .as_ref()
+                    .and_then(|t| t.strip_prefix('^'))
+                    .map(String::from),
+                (Selector::Index(_), Some(TypeDef::Array { element, .. })) => Some(element.clone()),
+                (Selector::Field(field), Some(TypeDef::Record { fields, .. })) => fields
+                    .iter()
+                    .find(|(name, _)| *name == field.get_id())
+                    .map(|(_, field_type)| field_type.clone()),
+                _ => None,
+            };
+
+            if let (Selector::Field(field), Some(field_type)) = (s, &current) {
+                if self.covers(field) {
+                    self.found = Some(self.info(field, "field", field_type.clone()));
+                }
+            }
+        }
+    }
+
+    fn variable_type(&self, id: &Identifier) -> Option<String> {
+        match &self.tree.symbol(id.symbol?).usage {
+            Usage::Variable(t) => Some(t.clone()),
+            Usage::Function(s) => s.result.clone(),
+            _ => None,
+        }
+    }
+}
+
+impl Visitor for Finder<'_> {
+    fn visit_field_declaration(&mut self, f: &FieldDeclaration) {
+        if self.covers(&f.id) {
+            self.found = Some(self.info(&f.id, "field", f.type_name.type_name()));
+        }
+        self.visit_type_spec(&f.type_name);
+    }
+
+    fn visit_variant_part(&mut self, p: &VariantPart) {
+        if let Some(tag) = p.tag.as_ref().filter(|tag| self.covers(tag)) {
+            self.found = Some(self.info(tag, "field", p.tag_type.get_id()));
+        }
+        walk_variant_part(self, p);
+    }
+
+    fn visit_statement(&mut self, s: &Statement) {
+        if let Statement::Simple(a) = s {
+            self.selectors(self.variable_type(&a.name), &a.selectors);
+        }
+        walk_statement(self, s);
+    }
+
+    fn visit_factor(&mut self, f: &Factor) {
+        if let Factor::Selected(v) = f {
+            self.selectors(self.variable_type(&v.name), &v.selectors);
+        }
+        walk_factor(self, f);
+    }
+
+    fn visit_identifier(&mut self, id: &Identifier) {
+        let Some(symbol) = id.symbol else { return };
+        if !self.covers(id) {
+            return;
+        }
+
+        let symbol = self.tree.symbol(symbol);
+        let (kind, type_name) = describe(&id.get_id(), &symbol.usage);
+
+        let mut info = self.info(id, kind, type_name);
+        info.value = symbol.value.as_ref().map(|v| format!("{:?}", v));
+        self.found = Some(info);
+    }
+}
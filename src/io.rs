@@ -1,28 +1,168 @@
 use std::fs::File;
-use std::io::{BufRead, BufReader, Error, Lines};
+use std::io::{BufRead, BufReader, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+// Source lines either come from a file or from a string in memory
+// (the latter works on targets without a file system, e.g. WASM)
+type Lines = Box<dyn Iterator<Item = Result<String, Error>> + Send>;
 
 pub struct CharReader {
+    source: Source,
+    // Sources which `{$I ...}` left, reading goes on with the last one when
+    // the current source ends
+    including: Vec<Source>,
+    read_errors: Vec<ReadError>,
+}
+
+/// Line which couldn't be read, e.g. invalid UTF-8. Reading its source stops
+/// there, so the lines after it are never numbered wrong.
+pub struct ReadError {
+    pub error: Error,
+    pub pos: (usize, usize),
+    // Included file the line is in, None for the main source
+    pub file: Option<PathBuf>,
+}
+
+// One file or string being read, positions are its own
+struct Source {
+    // None for source text from memory
+    file: Option<PathBuf>,
     current_char: Option<char>,
     chars: Option<Vec<char>>,
-    lines: Lines<BufReader<File>>,
+    lines: Lines,
     line_num: usize,
     col_num: usize,
+    // Why reading stopped before the end
+    error: Option<Error>,
 }
 
 impl CharReader {
     pub fn new(filename: String) -> Result<Self, Error> {
-        let file = File::open(filename)?;
+        Ok(Self::from_source_state(Source::open(PathBuf::from(
+            filename,
+        ))?))
+    }
+
+    pub fn from_source(source: &str) -> Self {
+        let lines: Vec<Result<String, Error>> =
+            source.lines().map(|l| Ok(String::from(l))).collect();
+
+        Self::from_source_state(Source::from_lines(None, Box::new(lines.into_iter())))
+    }
+
+    fn from_source_state(source: Source) -> Self {
+        Self {
+            source,
+            including: Vec::new(),
+            read_errors: Vec::new(),
+        }
+    }
+
+    /// Lines which failed to read since the last call
+    pub fn take_read_errors(&mut self) -> Vec<ReadError> {
+        self.keep_read_error();
+        std::mem::take(&mut self.read_errors)
+    }
+
+    fn keep_read_error(&mut self) {
+        if let Some(error) = self.source.error.take() {
+            self.read_errors.push(ReadError {
+                error,
+                pos: (self.source.line_num, 1),
+                file: self.included_file().map(Path::to_path_buf),
+            });
+        }
+    }
+
+    pub fn current_char(&self) -> Option<char> {
+        self.source.current_char
+    }
 
+    pub fn peek(&mut self) -> Option<&char> {
+        match self.source.chars.as_ref() {
+            Some(v) if v.len() == self.source.col_num + 1 => None,
+            Some(v) => Some(&v[self.source.col_num + 1]),
+            _ => None,
+        }
+    }
+
+    /// Line and column in the file being read, which may be an included one
+    pub fn position(&self) -> (usize, usize) {
+        (self.source.line_num, self.source.col_num + 1)
+    }
+
+    /// File being read when it was included by another, None in the main source
+    pub fn included_file(&self) -> Option<&Path> {
+        match self.including.is_empty() {
+            true => None,
+            false => self.source.file.as_deref(),
+        }
+    }
+
+    /// Reads `path` up to its end, then goes on with the current character.
+    /// A relative path is taken from the directory of the file being read.
+    /// Including a file which is being read already is an error, it would never end.
+    /// Source text from memory includes nothing, reading it stays off the file system.
+    pub fn include(&mut self, path: &Path) -> Result<(), Error> {
+        let path = match &self.source.file {
+            Some(file) if path.is_relative() => file.parent().unwrap_or(Path::new("")).join(path),
+            Some(_) => path.to_path_buf(),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "include files need a source file",
+                ))
+            }
+        };
+
+        let canonical = path.canonicalize()?;
+        let open = std::iter::once(&self.source).chain(self.including.iter());
+        if open
+            .filter_map(|s| s.file.as_ref()?.canonicalize().ok())
+            .any(|f| f == canonical)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "it is already being read, so it would include itself",
+            ));
+        }
+
+        let mut source = Source::open(path)?;
+        if let Some(e) = source.error.take() {
+            return Err(e);
+        }
+        // Nothing to read, e.g. an empty file
+        if source.current_char.is_none() {
+            return Ok(());
+        }
+
+        let outer = std::mem::replace(&mut self.source, source);
+        self.including.push(outer);
+        Ok(())
+    }
+}
+
+impl Source {
+    fn open(path: PathBuf) -> Result<Self, Error> {
+        let file = File::open(&path)?;
         let reader = BufReader::new(file);
-        let mut lines = reader.lines();
 
+        Ok(Self::from_lines(Some(path), Box::new(reader.lines())))
+    }
+
+    fn from_lines(file: Option<PathBuf>, mut lines: Lines) -> Self {
+        let mut error = None;
         let chars: Option<Vec<char>> = match lines.by_ref().next() {
             Some(Ok(s)) => {
                 let mut c: Vec<char> = s.chars().collect();
                 c.push('\n');
                 Some(c)
             }
-            _ => None,
+            Some(Err(e)) => {
+                error = Some(e);
+                None
+            }
+            None => None,
         };
 
         let line_num = 1;
@@ -30,37 +170,18 @@ impl CharReader {
 
         let current_char = chars.as_ref().map(|v| v[0]);
 
-        let reader = Self {
+        Self {
+            file,
             current_char,
             chars,
             lines,
             line_num,
             col_num,
-        };
-        Ok(reader)
-    }
-
-    pub fn current_char(&self) -> Option<char> {
-        self.current_char
-    }
-
-    pub fn peek(&mut self) -> Option<&char> {
-        match self.chars.as_ref() {
-            Some(v) if v.len() == self.col_num + 1 => None,
-            Some(v) => Some(&v[self.col_num + 1]),
-            _ => None,
+            error,
         }
     }
 
-    pub fn position(&self) -> (usize, usize) {
-        (self.line_num, self.col_num + 1)
-    }
-}
-
-impl Iterator for CharReader {
-    type Item = char;
-
-    fn next(&mut self) -> Option<Self::Item> {
+    fn next(&mut self) -> Option<char> {
         match self.chars.as_ref() {
             // End of current line => we need to pass \n and read next line
             Some(v) if self.col_num + 1 == v.len() => {
@@ -83,12 +204,17 @@ impl Iterator for CharReader {
                                 self.col_num = 0;
                             }
                         }
+                        Some(Err(e)) => {
+                            self.error = Some(e);
+                            self.chars = None;
+                            self.current_char = None;
+                            break;
+                        }
                         None => {
                             self.chars = None;
                             self.current_char = None;
                             break;
                         }
-                        _ => (),
                     };
                 }
             }
@@ -104,3 +230,20 @@ impl Iterator for CharReader {
         self.current_char
     }
 }
+
+impl Iterator for CharReader {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.source.next().is_none() {
+            self.keep_read_error();
+
+            // An included file ended, the one which included it goes on
+            if let Some(outer) = self.including.pop() {
+                self.source = outer;
+            }
+        }
+
+        self.source.current_char
+    }
+}
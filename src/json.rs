@@ -0,0 +1,233 @@
+// Minimal JSON helpers, enough to write diagnostics and read them back as a baseline
+
+/// Quoted and escaped JSON string
+pub fn string(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+
+    res.push('"');
+    res
+}
+
+pub fn array(items: impl IntoIterator<Item = String>) -> String {
+    format!("[{}]", items.into_iter().collect::<Vec<_>>().join(","))
+}
+
+/// Parsed JSON document, numbers are kept as `f64`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    // Members in the order they were written
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Member of an object, None for anything else
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Reads a whole document, errors tell what was expected at which byte offset
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut reader = Reader { text, pos: 0 };
+    let value = reader.value()?;
+
+    reader.skip_ws();
+    match reader.pos == text.len() {
+        true => Ok(value),
+        false => Err(reader.expected("end of document")),
+    }
+}
+
+struct Reader<'a> {
+    text: &'a str,
+    // Byte offset of the next character
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_ws(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn expected(&self, what: &str) -> String {
+        format!("expected {} at byte {}", what, self.pos)
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        match self.text[self.pos..].starts_with(word) {
+            true => {
+                self.pos += word.len();
+                Ok(value)
+            }
+            false => Err(self.expected(word)),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_ws();
+
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('n') => self.keyword("null", Value::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.expected("a value")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.bump();
+        let mut members = Vec::new();
+
+        self.skip_ws();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(Value::Object(members));
+        }
+
+        loop {
+            self.skip_ws();
+            if self.peek() != Some('"') {
+                return Err(self.expected("a member name"));
+            }
+            let key = self.string()?;
+
+            self.skip_ws();
+            if self.bump() != Some(':') {
+                return Err(self.expected("':'"));
+            }
+            members.push((key, self.value()?));
+
+            self.skip_ws();
+            match self.bump() {
+                Some(',') => (),
+                Some('}') => return Ok(Value::Object(members)),
+                _ => return Err(self.expected("',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.bump();
+        let mut items = Vec::new();
+
+        self.skip_ws();
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(Value::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+
+            self.skip_ws();
+            match self.bump() {
+                Some(',') => (),
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return Err(self.expected("',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        // Past the opening quote
+        self.bump();
+        let mut res = String::new();
+
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(res),
+                Some('\\') => match self.bump() {
+                    Some('"') => res.push('"'),
+                    Some('\\') => res.push('\\'),
+                    Some('/') => res.push('/'),
+                    Some('b') => res.push('\u{8}'),
+                    Some('f') => res.push('\u{c}'),
+                    Some('n') => res.push('\n'),
+                    Some('r') => res.push('\r'),
+                    Some('t') => res.push('\t'),
+                    Some('u') => {
+                        let hex = self.text.get(self.pos..self.pos + 4);
+                        let c = hex
+                            .and_then(|h| u32::from_str_radix(h, 16).ok())
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| self.expected("4 hex digits"))?;
+                        self.pos += 4;
+                        res.push(c);
+                    }
+                    _ => return Err(self.expected("an escape sequence")),
+                },
+                Some(c) => res.push(c),
+                None => return Err(self.expected("'\"'")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.pos += 1;
+        }
+
+        self.text[start..self.pos]
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| format!("invalid number at byte {}", start))
+    }
+}
@@ -1,44 +1,280 @@
 use crate::error::CompilerError;
 use crate::io::CharReader;
+use crate::options::Conformance;
 use crate::token::{Token, TokenType};
 use std::iter::Iterator;
+use std::path::Path;
+
+// Keywords by their lowercase spelling. Each one has its own slot in
+// `KEYWORD_SLOTS`, so a lookup folds case into a stack buffer, hashes once
+// and compares with at most one keyword.
+static KEYWORDS: [(&str, TokenType); 34] = [
+    ("div", TokenType::DivOp),
+    ("mod", TokenType::ModOp),
+    ("if", TokenType::IfKeyword),
+    ("else", TokenType::ElseKeyword),
+    ("then", TokenType::ThenKeyword),
+    ("or", TokenType::OrOp),
+    ("and", TokenType::AndOp),
+    ("not", TokenType::NotOp),
+    ("in", TokenType::InOp),
+    ("while", TokenType::WhileKeyword),
+    ("do", TokenType::DoKeyword),
+    ("repeat", TokenType::RepeatKeyword),
+    ("until", TokenType::UntilKeyword),
+    ("for", TokenType::ForKeyword),
+    ("to", TokenType::ToKeyword),
+    ("downto", TokenType::DowntoKeyword),
+    ("case", TokenType::CaseKeyword),
+    ("of", TokenType::OfKeyword),
+    ("procedure", TokenType::ProcedureKeyword),
+    ("function", TokenType::FunctionKeyword),
+    ("type", TokenType::TypeKeyword),
+    ("const", TokenType::ConstKeyword),
+    ("array", TokenType::ArrayKeyword),
+    ("record", TokenType::RecordKeyword),
+    ("set", TokenType::SetKeyword),
+    ("nil", TokenType::NilKeyword),
+    ("program", TokenType::ProgramKeyword),
+    ("uses", TokenType::UsesKeyword),
+    ("unit", TokenType::UnitKeyword),
+    ("interface", TokenType::InterfaceKeyword),
+    ("implementation", TokenType::ImplementationKeyword),
+    ("begin", TokenType::BeginKeyword),
+    ("end", TokenType::EndKeyword),
+    ("var", TokenType::VarKeyword),
+];
+
+// Longest keyword is `implementation`
+const MAX_KEYWORD_LEN: usize = 14;
+const SLOT_COUNT: usize = 64;
+const EMPTY_SLOT: u8 = u8::MAX;
+
+// Index into `KEYWORDS` by hash slot. Built at compile time, which fails if
+// two keywords hash to the same slot.
+static KEYWORD_SLOTS: [u8; SLOT_COUNT] = keyword_slots();
+
+// Length, first and last letter tell all keywords apart with these factors
+const fn keyword_slot(word: &[u8]) -> usize {
+    (word.len() + word[0] as usize * 31 + word[word.len() - 1] as usize * 49) % SLOT_COUNT
+}
+
+const fn keyword_slots() -> [u8; SLOT_COUNT] {
+    let mut slots = [EMPTY_SLOT; SLOT_COUNT];
+    let mut i = 0;
+
+    while i < KEYWORDS.len() {
+        let slot = keyword_slot(KEYWORDS[i].0.as_bytes());
+        assert!(slots[slot] == EMPTY_SLOT, "two keywords share a slot");
+        slots[slot] = i as u8;
+        i += 1;
+    }
+
+    slots
+}
+
+/// Token of a keyword, matched case-insensitively
+fn keyword(word: &str) -> Option<TokenType> {
+    let bytes = word.as_bytes();
+    if bytes.is_empty() || bytes.len() > MAX_KEYWORD_LEN {
+        return None;
+    }
+
+    let mut buffer = [0u8; MAX_KEYWORD_LEN];
+    let folded = &mut buffer[..bytes.len()];
+    for (f, b) in folded.iter_mut().zip(bytes) {
+        *f = b.to_ascii_lowercase();
+    }
+
+    let (name, token) = KEYWORDS.get(KEYWORD_SLOTS[keyword_slot(folded)] as usize)?;
+    (name.as_bytes() == folded).then(|| token.clone())
+}
 
 pub struct Lexer {
     chars: CharReader,
+    conformance: Conformance,
+    // Dialect errors which don't interrupt the token stream
+    errors: Vec<CompilerError>,
+    directives: Vec<Directive>,
+}
+
+/// Compiler directive from a `{$...}` comment: `{$R+}` has name `R` and argument `+`,
+/// `{$I defs.inc}` has name `I` and argument `defs.inc`. Names are uppercase.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Directive {
+    pub name: String,
+    pub argument: String,
+    pub pos: (usize, usize),
+}
+
+impl Directive {
+    /// File named by `{$I file}`, quotes taken off. `{$I+}` and `{$I-}` switch
+    /// input and output checks instead.
+    pub fn include_file(&self) -> Option<&str> {
+        match (self.name.as_str(), self.argument.as_str()) {
+            ("I", "+" | "-" | "") => None,
+            ("I", file) => Some(file.trim_matches('\'')),
+            _ => None,
+        }
+    }
+
+    fn parse(text: &str, pos: (usize, usize)) -> Self {
+        let text = text.trim();
+        let end = text
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(text.len());
+
+        Self {
+            name: text[..end].to_ascii_uppercase(),
+            argument: text[end..].trim().to_string(),
+            pos,
+        }
+    }
 }
 
 impl Lexer {
     pub fn new(chars: CharReader) -> Self {
-        Self { chars }
+        Self {
+            chars,
+            conformance: Conformance::Extended,
+            errors: Vec::new(),
+            directives: Vec::new(),
+        }
+    }
+
+    /// Dialect for lexical extensions, `//` comments are rejected by ISO 7185
+    pub fn with_conformance(mut self, conformance: Conformance) -> Self {
+        self.conformance = conformance;
+        self
+    }
+
+    /// Recoverable errors found since the last call, the tokens around them are still valid
+    pub fn take_errors(&mut self) -> Vec<CompilerError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// File the last token came from when it is an included one
+    pub fn included_file(&self) -> Option<&Path> {
+        self.chars.included_file()
+    }
+
+    /// Directives read since the last call, in source order. ISO 7185 has none, there
+    /// they are plain comments.
+    pub fn take_directives(&mut self) -> Vec<Directive> {
+        std::mem::take(&mut self.directives)
+    }
+
+    // Recoverable error at the current position, the file it is in may end
+    // before the parser gets the next token
+    fn error(&mut self, e: CompilerError) {
+        let e = match self.chars.included_file() {
+            Some(file) => e.with_file(file.display().to_string()),
+            None => e,
+        };
+        self.errors.push(e);
     }
 
+    // Whitespace and comments, which may span lines
     fn skip_ws(&mut self) {
         loop {
-            let cur = self.chars.current_char();
-
-            if cur.is_none() {
-                break;
+            match self.chars.current_char() {
+                Some(c) if c.is_whitespace() => {
+                    self.chars.by_ref().next();
+                }
+                Some('{') => self.skip_comment(false),
+                Some('(') if self.chars.peek() == Some(&'*') => self.skip_comment(true),
+                Some('/') if self.chars.peek() == Some(&'/') => self.skip_line_comment(),
+                _ => return,
             }
+        }
+    }
+
+    // `{ ... }`, or `(* ... *)` when `starred`, each closed by its own kind. One left
+    // open takes the rest of the source, which then ends.
+    fn skip_comment(&mut self, starred: bool) {
+        let start = self.chars.position();
+        let close = if starred { "*)" } else { "}" };
+
+        // Past the opening, so `(*)` doesn't close itself
+        self.chars.by_ref().next();
+        if starred {
+            self.chars.by_ref().next();
+        }
 
-            match cur.unwrap() {
-                c if c.is_whitespace() => {
+        let directive =
+            self.chars.current_char() == Some('$') && self.conformance != Conformance::Iso7185;
+        let mut text = String::new();
+
+        loop {
+            match self.chars.current_char() {
+                Some('}') if !starred => break,
+                Some('*') if starred && self.chars.peek() == Some(&')') => {
                     self.chars.by_ref().next();
-                    continue;
-                }
-                _ => {
                     break;
                 }
+                Some(c) => {
+                    if directive {
+                        text.push(c);
+                    }
+                    self.chars.by_ref().next();
+                }
+                None => {
+                    self.error(
+                        CompilerError::lexical(format!(
+                            "Unterminated comment, expected '{}'",
+                            close
+                        ))
+                        .with_span(start),
+                    );
+                    return;
+                }
+            }
+        }
+
+        self.chars.by_ref().next();
+
+        if directive {
+            // Past the `$`
+            let directive = Directive::parse(&text[1..], start);
+            if let Some(file) = directive.include_file() {
+                if let Err(e) = self.chars.include(Path::new(file)) {
+                    self.error(
+                        CompilerError::lexical(format!("Can't include {}: {}", file, e))
+                            .with_span(start),
+                    );
+                }
+            }
+            self.directives.push(directive);
+        }
+    }
+
+    // `// ...` up to the end of the line, also skipped in ISO mode once reported
+    fn skip_line_comment(&mut self) {
+        if self.conformance == Conformance::Iso7185 {
+            self.error(
+                CompilerError::syntax("'//' comments are not part of ISO 7185")
+                    .with_span(self.chars.position()),
+            );
+        }
+
+        while let Some(c) = self.chars.current_char() {
+            if c == '\n' {
+                break;
             }
+            self.chars.by_ref().next();
         }
     }
 
     fn number(&mut self) -> Result<Token, CompilerError> {
+        let pos = self.chars.position();
         let mut num = String::new();
         let mut is_real = false;
 
         loop {
             match self.chars.by_ref().current_char() {
-                Some(ch) if ch.is_digit(10) || ch.is_alphanumeric() => num.push(ch),
+                Some(ch) if ch.is_ascii_digit() || ch.is_alphanumeric() => num.push(ch),
+                // Range like `1..5`, not a real
+                Some('.') if self.chars.peek() == Some(&'.') => break,
                 Some(ch) if ch == '.' => {
                     num.push(ch);
                     is_real = true;
@@ -54,36 +290,52 @@ impl Lexer {
             let parsed = num.parse::<f32>();
 
             match parsed {
-                Ok(f) => Ok(Token::new(TokenType::Real(f), self.chars.position())),
-                _ => {
-                    let pos = self.chars.position();
-                    Err(CompilerError::lexical(
-                        format!("Invalid real literal {}", num),
-                        pos,
-                    ))
-                }
+                Ok(f) => Ok(Token::new(TokenType::Real(f), pos)),
+                _ => Err(
+                    CompilerError::lexical(format!("Invalid real literal {}", num)).with_span(pos),
+                ),
             }
         } else {
             let parsed = num.parse::<i32>();
 
             match parsed {
-                Ok(i) => Ok(Token::new(TokenType::Integer(i), self.chars.position())),
-                _ => {
-                    let pos = self.chars.position();
-
-                    Err(CompilerError::lexical(
-                        format!("Invalid int literal {}", num),
-                        pos,
-                    ))
-                }
+                Ok(i) => Ok(Token::new(TokenType::Integer(i), pos)),
+                _ => Err(
+                    CompilerError::lexical(format!("Invalid int literal {}", num)).with_span(pos),
+                ),
             }
         }
     }
 
+    fn hex_number(&mut self) -> Result<Token, CompilerError> {
+        let start = self.chars.position();
+        let mut digits = String::new();
+
+        // Skip the '$'
+        self.chars.by_ref().next();
+
+        while let Some(ch) = self.chars.by_ref().current_char() {
+            if !ch.is_ascii_hexdigit() {
+                break;
+            }
+            digits.push(ch);
+            self.chars.by_ref().next();
+        }
+
+        // Like decimal literals, $FFFFFFFF is out of range
+        match i32::from_str_radix(&digits, 16) {
+            Ok(i) => Ok(Token::new(TokenType::HexInteger(i), start)),
+            _ => Err(
+                CompilerError::lexical(format!("Invalid hex literal ${}", digits)).with_span(start),
+            ),
+        }
+    }
+
     fn maybe_keyword(&mut self) -> Result<Token, CompilerError> {
         if self.chars.by_ref().current_char().is_none() {
             Ok(Token::new(TokenType::Eof, self.chars.position()))
         } else {
+            let pos = self.chars.position();
             let mut s = String::new();
             s.push(self.chars.by_ref().current_char().unwrap());
 
@@ -94,24 +346,9 @@ impl Lexer {
                 }
             }
 
-            let pos = self.chars.position();
-
-            match s.to_lowercase().as_str() {
-                "div" => Ok(Token::new(TokenType::DivOp, pos)),
-                "mod" => Ok(Token::new(TokenType::ModOp, pos)),
-                "if" => Ok(Token::new(TokenType::IfKeyword, pos)),
-                "else" => Ok(Token::new(TokenType::ElseKeyword, pos)),
-                "then" => Ok(Token::new(TokenType::ThenKeyword, pos)),
-                "or" => Ok(Token::new(TokenType::OrOp, pos)),
-                "and" => Ok(Token::new(TokenType::AndOp, pos)),
-                "while" => Ok(Token::new(TokenType::WhileKeyword, pos)),
-                "do" => Ok(Token::new(TokenType::DoKeyword, pos)),
-                "type" => Ok(Token::new(TokenType::TypeKeyword, pos)),
-                "program" => Ok(Token::new(TokenType::ProgramKeyword, pos)),
-                "begin" => Ok(Token::new(TokenType::BeginKeyword, pos)),
-                "end" => Ok(Token::new(TokenType::EndKeyword, pos)),
-                "var" => Ok(Token::new(TokenType::VarKeyword, pos)),
-                _ => Ok(Token::new(TokenType::Identifier(s), pos)),
+            match keyword(&s) {
+                Some(token) => Ok(Token::new(token, pos)),
+                None => Ok(Token::new(TokenType::Identifier(s), pos)),
             }
         }
     }
@@ -133,7 +370,7 @@ impl Lexer {
                     }
                     _ => Ok(Token::new(TokenType::Colon, pos)),
                 },
-                _ => Err(CompilerError::lexical("Invalid operator".into(), pos)),
+                _ => Err(CompilerError::lexical("Invalid operator").with_span(pos)),
             }
         };
 
@@ -149,9 +386,36 @@ impl Lexer {
         } else {
             match self.chars.current_char().unwrap() {
                 ';' => Ok(Token::new(TokenType::Semicolon, pos)),
-                '.' => Ok(Token::new(TokenType::Period, pos)),
+                '.' => match self.chars.peek() {
+                    Some('.') => {
+                        self.chars.next();
+                        Ok(Token::new(TokenType::DotDot, pos))
+                    }
+                    _ => Ok(Token::new(TokenType::Period, pos)),
+                },
                 '(' => Ok(Token::new(TokenType::LBrace, pos)),
+                '#' => {
+                    // The last digit is passed below like any other symbol
+                    let mut digits = String::new();
+                    while let Some(d) = self.chars.peek().copied().filter(char::is_ascii_digit) {
+                        digits.push(d);
+                        self.chars.next();
+                    }
+
+                    match digits.parse::<u32>().ok().and_then(char::from_u32) {
+                        Some(c) => Ok(Token::new(TokenType::CharCode(c), pos)),
+                        None => Err(CompilerError::lexical(format!(
+                            "Invalid character code #{}",
+                            digits
+                        ))
+                        .with_span(pos)),
+                    }
+                }
                 ')' => Ok(Token::new(TokenType::RBrace, pos)),
+                '[' => Ok(Token::new(TokenType::LBracket, pos)),
+                ']' => Ok(Token::new(TokenType::RBracket, pos)),
+                '^' => Ok(Token::new(TokenType::Caret, pos)),
+                '@' => Ok(Token::new(TokenType::At, pos)),
                 ',' => Ok(Token::new(TokenType::Comma, pos)),
                 '=' => Ok(Token::new(TokenType::Eq, pos)),
                 '>' => {
@@ -181,22 +445,32 @@ impl Lexer {
                     }
                 }
                 '\'' => {
-                    // Read chars until string literal is closed
-                    let literal: String = self
-                        .chars
-                        .by_ref()
-                        .take_while(|x: &char| x != &'\'')
-                        .collect();
+                    // Read chars until string literal is closed, `''` inside it is one quote
+                    let mut literal = String::new();
 
-                    match self.chars.current_char() {
-                        Some('\'') => Ok(Token::new(TokenType::StringLiteral(literal), pos)),
-                        _ => Err(CompilerError::lexical("Invalid string literal".into(), pos)),
+                    loop {
+                        match self.chars.next() {
+                            Some('\'') if self.chars.peek() == Some(&'\'') => {
+                                self.chars.next();
+                                literal.push('\'');
+                            }
+                            Some('\'') => {
+                                break Ok(Token::new(TokenType::StringLiteral(literal), pos))
+                            }
+                            Some(c) => literal.push(c),
+                            None => {
+                                break Err(
+                                    CompilerError::lexical("Invalid string literal").with_span(pos)
+                                )
+                            }
+                        }
                     }
                 }
-                _ => Err(CompilerError::lexical(
-                    format!("Unsupported symbol {}", self.chars.current_char().unwrap()),
-                    pos,
-                )),
+                _ => Err(CompilerError::lexical(format!(
+                    "Unsupported symbol {}",
+                    self.chars.current_char().unwrap()
+                ))
+                .with_span(pos)),
             }
         };
 
@@ -214,6 +488,7 @@ impl Iterator for Lexer {
         let token = match self.chars.by_ref().current_char() {
             Some(ch) => match ch {
                 '0'..='9' => self.number(),
+                '$' => self.hex_number(),
                 '+' | '-' | '*' | ':' => self.operator(),
                 _ if ch.is_alphanumeric() => self.maybe_keyword(),
                 _ => self.symbol(),
@@ -221,6 +496,15 @@ impl Iterator for Lexer {
             None => Ok(Token::new(TokenType::Eof, self.chars.position())),
         };
 
+        for e in self.chars.take_read_errors() {
+            let message = format!("Can't read line {}: {}", e.pos.0, e.error);
+            let error = CompilerError::io(message, e.error).with_span(e.pos);
+            self.errors.push(match e.file {
+                Some(file) => error.with_file(file.display().to_string()),
+                None => error,
+            });
+        }
+
         match token {
             Ok(Token {
                 token: TokenType::Eof,
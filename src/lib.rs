@@ -0,0 +1,28 @@
+pub mod analyzer;
+pub mod baseline;
+pub mod batch;
+pub mod completion;
+pub mod const_eval;
+pub mod diff;
+pub mod driver;
+pub mod error;
+pub mod generator;
+pub mod hover;
+pub mod io;
+pub mod json;
+pub mod lexer;
+pub mod lint;
+pub mod metrics;
+pub mod options;
+pub mod parser;
+pub mod pass;
+pub mod printer;
+pub mod scope;
+pub mod snippet;
+pub mod stats;
+pub mod syntax;
+pub mod token;
+pub mod transform;
+mod unit_files;
+mod units;
+pub mod visitor;
@@ -0,0 +1,278 @@
+use crate::error::{CompilerError, Diagnostic};
+use crate::pass::Pass;
+use crate::scope::{SymbolId, Usage};
+use crate::syntax::*;
+use crate::token::TokenType;
+use crate::visitor::{self, Visitor};
+use std::collections::{BTreeSet, HashSet};
+
+/// Style rules checked by the lint pass, selected by name on the command line
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Rule {
+    // Variable named with a single letter which isn't a loop counter (for variable or tested by a loop condition)
+    ShortName,
+    // Numeric literal other than 0 and 1 outside of const declarations, case labels and array bounds
+    MagicNumber,
+    // `if a then else ...`, `if a then;` or `if a then begin end`
+    EmptyThen,
+    // `x = value` in a condition where x is never assigned, probably meant `x := value`
+    EqInCondition,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 4] = [
+        Rule::ShortName,
+        Rule::MagicNumber,
+        Rule::EmptyThen,
+        Rule::EqInCondition,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::ShortName => "short-name",
+            Rule::MagicNumber => "magic-number",
+            Rule::EmptyThen => "empty-then",
+            Rule::EqInCondition => "eq-in-condition",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|r| r.name() == name)
+    }
+}
+
+pub struct Lint {
+    rules: BTreeSet<Rule>,
+}
+
+impl Lint {
+    pub fn new(rules: impl IntoIterator<Item = Rule>) -> Self {
+        Self {
+            rules: rules.into_iter().collect(),
+        }
+    }
+
+    pub fn all() -> Self {
+        Self::new(Rule::ALL)
+    }
+}
+
+impl Pass for Lint {
+    fn name(&self) -> &str {
+        "lint"
+    }
+
+    fn run(&mut self, program: &Program, diagnostics: &mut Vec<Diagnostic>) {
+        let mut collector = Collector::default();
+        collector.visit_program(program);
+
+        let mut warnings = Vec::new();
+
+        if self.rules.contains(&Rule::ShortName) {
+            for id in collector.variables.iter() {
+                let loop_counter = id
+                    .symbol
+                    .is_some_and(|s| collector.loop_variables.contains(&s));
+
+                if id.get_id().chars().count() == 1 && !loop_counter {
+                    warnings.push(
+                        warning(
+                            Rule::ShortName,
+                            format!("Variable {:?} has a single-letter name", id.get_id()),
+                        )
+                        .with_span(id.id.pos),
+                    );
+                }
+            }
+        }
+
+        if self.rules.contains(&Rule::MagicNumber) {
+            for (literal, pos) in collector.literals.iter() {
+                warnings.push(
+                    warning(Rule::MagicNumber, format!("Magic number {}", literal))
+                        .with_span(*pos)
+                        .with_note("consider declaring a named constant"),
+                );
+            }
+        }
+
+        if self.rules.contains(&Rule::EmptyThen) {
+            for (pos, has_else) in collector.empty_then.iter() {
+                let mut w = warning(Rule::EmptyThen, "Then-branch is empty".into()).with_span(*pos);
+                if *has_else {
+                    w = w.with_note("negate the condition and move the else-branch here");
+                }
+                warnings.push(w);
+            }
+        }
+
+        if self.rules.contains(&Rule::EqInCondition) {
+            for id in collector.compared.iter() {
+                let unassigned_variable = id.symbol.is_some_and(|s| {
+                    let usage = &program.scope_tree().symbol(s).usage;
+                    matches!(usage, Usage::Variable(_)) && !collector.assigned.contains(&s)
+                });
+
+                if unassigned_variable {
+                    warnings.push(
+                        warning(
+                            Rule::EqInCondition,
+                            format!("{:?} is compared with '=' but never assigned", id.get_id()),
+                        )
+                        .with_span(id.id.pos)
+                        .with_note("did you mean ':='?"),
+                    );
+                }
+            }
+        }
+
+        warnings.sort_by_key(|w| w.pos());
+        diagnostics.extend(warnings);
+    }
+}
+
+fn warning(rule: Rule, description: String) -> CompilerError {
+    CompilerError::lint(format!("{} [{}]", description, rule.name()))
+}
+
+/// Gathers everything the rules need in one walk over the AST
+#[derive(Default)]
+struct Collector {
+    variables: Vec<Identifier>,
+    // Variables tested by loop conditions
+    loop_variables: HashSet<SymbolId>,
+    assigned: HashSet<SymbolId>,
+    // Variables on the left side of `=` in conditions
+    compared: Vec<Identifier>,
+    literals: Vec<(String, (usize, usize))>,
+    // Conditions of if statements with an empty then-branch
+    empty_then: Vec<((usize, usize), bool)>,
+}
+
+impl Collector {
+    fn check_condition(&mut self, e: &Expression) {
+        if let Expression::Relational(r) = e {
+            if let (RelationalOp::Eq, Some(id)) = (&*r.op, r.first.as_identifier()) {
+                self.compared.push(id.clone());
+            }
+        }
+    }
+}
+
+impl Visitor for Collector {
+    fn visit_var_declaration(&mut self, d: &VarDeclaration) {
+        self.variables.push(d.id.clone());
+    }
+
+    // Parameters get their values from the caller
+    fn visit_formal_parameter(&mut self, p: &FormalParameter) {
+        if let Some(symbol) = p.id.symbol {
+            self.assigned.insert(symbol);
+        }
+    }
+
+    fn visit_statement(&mut self, s: &Statement) {
+        match s {
+            Statement::Simple(a) => {
+                if let Some(symbol) = a.name.symbol {
+                    self.assigned.insert(symbol);
+                }
+            }
+            Statement::Cond(c) => {
+                self.check_condition(&c.condition);
+
+                let empty = match &*c.statement {
+                    Statement::Empty => true,
+                    Statement::Compound(inner) => inner.statements.is_empty(),
+                    _ => false,
+                };
+
+                if empty {
+                    let has_else = c.else_statement.is_some();
+                    self.empty_then.push((c.condition.pos(), has_else));
+                }
+            }
+            Statement::While(w) => {
+                self.check_condition(&w.condition);
+
+                let mut loop_variables = LoopVariables::default();
+                loop_variables.visit_expression(&w.condition);
+                self.loop_variables.extend(loop_variables.0);
+            }
+            Statement::Repeat(r) => {
+                self.check_condition(&r.condition);
+
+                let mut loop_variables = LoopVariables::default();
+                loop_variables.visit_expression(&r.condition);
+                self.loop_variables.extend(loop_variables.0);
+            }
+            Statement::For(l) => {
+                if let Some(symbol) = l.variable.symbol {
+                    self.assigned.insert(symbol);
+                    self.loop_variables.insert(symbol);
+                }
+            }
+            // A VAR parameter may assign the variable passed to it
+            Statement::Call(c) => {
+                for arg in c.arguments.iter() {
+                    if let Some(symbol) = expression_identifier(arg).and_then(|id| id.symbol) {
+                        self.assigned.insert(symbol);
+                    }
+                }
+            }
+            Statement::Case(_) | Statement::Compound(_) | Statement::Empty => (),
+        }
+
+        visitor::walk_statement(self, s);
+    }
+
+    // Naming a value is the fix for a magic number
+    fn visit_const_declaration(&mut self, _d: &ConstDeclaration) {}
+
+    // Labels are the values being named, so they are never magic numbers
+    fn visit_case_label(&mut self, _l: &CaseLabel) {}
+
+    // Array bounds are part of the type, like the names of other types
+    fn visit_type_spec(&mut self, _t: &TypeSpec) {}
+
+    // Field widths lay out the output, naming them wouldn't tell more
+    fn visit_write_format(&mut self, _f: &WriteFormat) {}
+
+    fn visit_factor(&mut self, f: &Factor) {
+        match f {
+            Factor::Integer(t) => {
+                if let TokenType::Integer(i) | TokenType::HexInteger(i) = t.token {
+                    if i != 0 && i != 1 {
+                        self.literals.push((i.to_string(), t.pos));
+                    }
+                }
+            }
+            Factor::Real(t) => {
+                if let TokenType::Real(r) = t.token {
+                    if r != 0.0 && r != 1.0 {
+                        self.literals.push((r.to_string(), t.pos));
+                    }
+                }
+            }
+            _ => visitor::walk_factor(self, f),
+        }
+    }
+}
+
+#[derive(Default)]
+struct LoopVariables(HashSet<SymbolId>);
+
+impl Visitor for LoopVariables {
+    fn visit_identifier(&mut self, id: &Identifier) {
+        if let Some(symbol) = id.symbol {
+            self.0.insert(symbol);
+        }
+    }
+}
+
+fn expression_identifier(e: &Expression) -> Option<&Identifier> {
+    match e {
+        Expression::Simple(s) => s.as_variable(),
+        Expression::Relational(_) => None,
+    }
+}
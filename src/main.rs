@@ -1,51 +1,383 @@
-mod analyzer;
-mod error;
-mod io;
-mod lexer;
-mod parser;
-mod scope;
-mod syntax;
-mod token;
-
-use io::CharReader;
-use lexer::Lexer;
-use parser::Parser;
+use pascal_compiler::baseline::Baseline;
+use pascal_compiler::batch::{pascal_files, BatchReport};
+use pascal_compiler::diff::{diff_programs, DiffOptions};
+use pascal_compiler::driver::{compile_file, CompilationResult, Compiler};
+use pascal_compiler::error::Diagnostic;
+use pascal_compiler::generator::synthetic_program;
+use pascal_compiler::lint::{Lint, Rule};
+use pascal_compiler::metrics::Metrics;
+use pascal_compiler::options::{Conformance, DivMod, KeywordCase, Newline, Options, PrintOptions};
+use pascal_compiler::snippet::render_diagnostic;
+use pascal_compiler::stats::Stats;
+use pascal_compiler::transform::{obfuscate, strip_unused};
 use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 
+const USAGE: &str = "\
+Usage: pascal-compiler [--verbose] [--stats] [--lint[=RULE,...]] [--metrics[=text|json]] [--obfuscate] [--strip-unused] [--newline=lf|crlf] [--keyword-case=lower|upper|capitalized] [--div-mod=iso|truncated] [--bitwise-not] [--pedantic] [--conformance=iso7185|tp7] [--max-scope-depth N] [--max-expr-depth N] [--baseline FILE] [--write-baseline FILE] [--snippets[=color]] [--unit-path DIR]... source.pas
+       pascal-compiler --bench-input N    print a generated program of size N
+       pascal-compiler diff-ast [--ignore-names] a.pas b.pas
+                                          compare structure of two programs
+       pascal-compiler check [--recursive] [--format=text|csv|json] path...
+                                          summarize errors of many programs
+
+Lint rules: {lint_rules} (all by default)
+div/mod: iso (default) makes mod non-negative, truncated gives mod the sign of the dividend
+Newlines and keyword case apply to printed source (--obfuscate)
+Exit status is 1 if the source has errors or can't be read, 0 otherwise
+--baseline hides the diagnostics recorded by --write-baseline, any others make the exit status 1
+--snippets shows the source lines under each diagnostic
+Units in `uses` are read from <name>.pas in the program's directory, then in each --unit-path";
+
+/// Report of every `.pas` file in the paths (directories are searched), exits
+/// with 1 if any of them has errors and 2 if a directory can't be listed
+fn check(args: impl Iterator<Item = String>) -> ! {
+    let mut recursive = false;
+    let mut format = "text";
+    let mut paths = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "--recursive" => recursive = true,
+            "--format=text" => format = "text",
+            "--format=csv" => format = "csv",
+            "--format=json" => format = "json",
+            _ if !arg.starts_with("--") => paths.push(arg),
+            _ => usage(),
+        }
+    }
+
+    if paths.is_empty() {
+        usage();
+    }
+
+    let mut files: Vec<PathBuf> = Vec::new();
+    for path in paths.iter().map(Path::new) {
+        if !path.is_dir() {
+            files.push(path.to_path_buf());
+            continue;
+        }
+
+        match pascal_files(path, recursive) {
+            Ok(found) => files.extend(found),
+            Err(e) => {
+                eprintln!("Can't list {}: {}", path.display(), e);
+                exit(2);
+            }
+        }
+    }
+
+    let report = BatchReport::check(&files, &Options::default());
+    match format {
+        "csv" => print!("{}", report.to_csv()),
+        "json" => println!("{}", report.to_json()),
+        _ => println!("{}", report),
+    }
+
+    exit(if report.has_errors() { 1 } else { 0 });
+}
+
+/// Exits with 2 if the file can't be read or doesn't hold a baseline
+fn read_baseline(file: &str) -> Baseline {
+    let res = fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|text| Baseline::from_json(&text));
+
+    match res {
+        Ok(baseline) => baseline,
+        Err(e) => {
+            eprintln!("Can't read baseline {}: {}", file, e);
+            exit(2);
+        }
+    }
+}
+
+fn usage() -> ! {
+    let rules: Vec<&str> = Rule::ALL.iter().map(Rule::name).collect();
+    println!("{}", USAGE.replace("{lint_rules}", &rules.join(", ")));
+    exit(1);
+}
+
+/// Exits with 0 if programs are structurally equal, 1 if they differ
+/// and 2 if any of them can't be compiled
+fn diff_ast(args: impl Iterator<Item = String>) -> ! {
+    let mut diff_options = DiffOptions::default();
+    let mut files = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "--ignore-names" => diff_options.ignore_names = true,
+            _ if !arg.starts_with("--") => files.push(arg),
+            _ => usage(),
+        }
+    }
+
+    if files.len() != 2 {
+        usage();
+    }
+
+    let mut programs = Vec::new();
+    for file in files.iter() {
+        let result = match compile_file(file, &Options::default()) {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(2);
+            }
+        };
+
+        for e in result.diagnostics.iter() {
+            eprintln!("{}: {}", file, e);
+        }
+
+        match result.complete_ast() {
+            Ok(_) => programs.extend(result.ast),
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                exit(2);
+            }
+        }
+    }
+
+    match diff_programs(&programs[0], &programs[1], &diff_options) {
+        None => {
+            println!("Programs are structurally equal");
+            exit(0);
+        }
+        Some(d) => {
+            println!("First difference: {}", d);
+            exit(1);
+        }
+    }
+}
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args = env::args().skip(1).peekable();
 
-    if args.len() != 2 {
-        println!("Usage: pascal-compiler source.pas");
-        exit(1);
+    match args.peek().map(String::as_str) {
+        Some("diff-ast") => {
+            args.next();
+            diff_ast(args);
+        }
+        Some("check") => {
+            args.next();
+            check(args);
+        }
+        _ => (),
     }
 
-    let filename = &args[1];
+    let mut options = Options::default();
+    let mut verbose = false;
+    let mut stats = false;
+    let mut lint = None;
+    let mut metrics = None;
+    let mut obfuscated = false;
+    let mut print_options = PrintOptions::default();
+    let mut strip = false;
+    let mut baseline = None;
+    let mut write_baseline = None;
+    // Source lines under diagnostics, and whether they are colored
+    let mut snippets = None;
+    let mut filename = None;
 
-    let char_reader = CharReader::new(String::from(filename));
-    match char_reader {
-        Ok(reader) => {
-            let lexer = Lexer::new(reader);
-            let mut parser = Parser::new(lexer);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--verbose" => verbose = true,
+            "--stats" => stats = true,
+            "--lint" => lint = Some(Lint::all()),
+            "--obfuscate" => obfuscated = true,
+            "--strip-unused" => strip = true,
+            "--newline=lf" => print_options.newline = Newline::Lf,
+            "--newline=crlf" => print_options.newline = Newline::CrLf,
+            "--keyword-case=lower" => print_options.keyword_case = KeywordCase::Lower,
+            "--keyword-case=upper" => print_options.keyword_case = KeywordCase::Upper,
+            "--keyword-case=capitalized" => print_options.keyword_case = KeywordCase::Capitalized,
+            "--bitwise-not" => options.bitwise_not = true,
+            "--pedantic" => options.pedantic = true,
+            "--conformance=iso7185" => options.conformance = Conformance::Iso7185,
+            // Turbo Pascal's own arithmetic comes with it
+            "--conformance=tp7" => {
+                options.conformance = Conformance::TurboPascal7;
+                options.div_mod = DivMod::Truncated;
+                options.bitwise_not = true;
+            }
+            "--div-mod=iso" => options.div_mod = DivMod::Iso,
+            "--div-mod=truncated" => options.div_mod = DivMod::Truncated,
+            "--metrics" | "--metrics=text" => metrics = Some(false),
+            "--metrics=json" => metrics = Some(true),
+            "--snippets" => snippets = Some(false),
+            "--snippets=color" => snippets = Some(true),
+            _ if arg.starts_with("--lint=") => {
+                let rules: Option<Vec<Rule>> = arg["--lint=".len()..]
+                    .split(',')
+                    .map(Rule::from_name)
+                    .collect();
 
-            let res = parser.parse();
-            match res {
-                Ok(r) => {
-                    println!("Parsed program!");
+                match rules {
+                    Some(rules) => lint = Some(Lint::new(rules)),
+                    None => usage(),
+                }
+            }
+            "--bench-input" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => {
+                    print!("{}", synthetic_program(n));
+                    return;
+                }
+                None => usage(),
+            },
+            "--max-scope-depth" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => options.max_scope_depth = n,
+                None => usage(),
+            },
+            "--max-expr-depth" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => options.max_expression_depth = n,
+                None => usage(),
+            },
+            "--baseline" => match args.next() {
+                Some(file) => baseline = Some(read_baseline(&file)),
+                None => usage(),
+            },
+            "--write-baseline" => match args.next() {
+                Some(file) => write_baseline = Some(file),
+                None => usage(),
+            },
+            "--unit-path" => match args.next() {
+                Some(dir) => options.unit_path.push(PathBuf::from(dir)),
+                None => usage(),
+            },
+            _ if filename.is_none() && !arg.starts_with("--") => filename = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    let filename = match filename {
+        Some(f) => f,
+        None => usage(),
+    };
+
+    // Units next to the program come first
+    let program_dir = Path::new(&filename).parent().unwrap_or(Path::new(""));
+    options.unit_path.insert(0, program_dir.to_path_buf());
+
+    let mut compiler = Compiler::new(options);
+    if let Some(lint) = lint {
+        compiler.register_pass(Box::new(lint));
+    }
+
+    let mut res = compiler.compile_file(&filename);
+    let mut failed = match &res {
+        Ok(result) => result.has_errors(),
+        Err(_) => true,
+    };
+    // The compiler streams the file, so it is read again for the snippets
+    let source = snippets.and_then(|_| fs::read_to_string(&filename).ok());
+    let show = |e: &Diagnostic| match (&source, snippets) {
+        (Some(source), Some(color)) => render_diagnostic(source, e, color),
+        _ => e.to_string(),
+    };
+
+    if let Ok(result) = &mut res {
+        if let Some(file) = &write_baseline {
+            if let Err(e) = fs::write(file, Baseline::record(&result.diagnostics)) {
+                eprintln!("Can't write baseline {}: {}", file, e);
+                exit(2);
+            }
+        }
+
+        if let Some(baseline) = &baseline {
+            result.diagnostics = baseline.new_diagnostics(std::mem::take(&mut result.diagnostics));
+            failed = !result.diagnostics.is_empty();
+        }
+    }
+
+    if strip {
+        if let Ok(CompilationResult { ast: Some(r), .. }) = &mut res {
+            for removed in strip_unused(r) {
+                eprintln!("{}", removed);
+            }
+        }
+    }
+
+    match res {
+        // Only the transformed source goes to stdout, and only if it is complete
+        Ok(result) if obfuscated => {
+            for e in result.diagnostics.iter() {
+                eprintln!("{}", show(e));
+            }
+
+            match result.complete_ast() {
+                Ok(r) => print!("{}", obfuscate(r, &print_options)),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        Ok(result) => match result.ast {
+            // Only the report goes to stdout, so it can be processed by other tools
+            Some(r) if metrics.is_some() => {
+                for e in result.diagnostics {
+                    eprintln!("{}", show(&e));
+                }
+
+                let report = Metrics::collect(&r);
+                match metrics {
+                    Some(true) => println!("{}", report.to_json()),
+                    _ => println!("{}", report),
+                }
+            }
+            Some(r) => {
+                println!("Parsed program!");
+                println!("Errors:");
+
+                for e in result.diagnostics {
+                    println!("{}", show(&e));
+                }
+
+                println!("{:#?}", r);
+
+                if verbose {
+                    println!("{}", r.scope_tree().stats());
+                }
+
+                if stats {
+                    println!("{}", Stats::collect(&r, result.token_count));
+                }
+            }
+            None => match result.unit {
+                Some(u) => {
+                    println!("Parsed unit!");
                     println!("Errors:");
 
-                    for e in parser.errors {
-                        println!("{}", e);
+                    for e in result.diagnostics {
+                        println!("{}", show(&e));
                     }
 
-                    println!("{:#?}", r)
+                    println!("{:#?}", u);
+
+                    if verbose {
+                        println!("{}", u.scope_tree().stats());
+                    }
                 }
-                Err(e) => println!("{}", e),
-            }
-        }
+                None => {
+                    for e in result.diagnostics {
+                        println!("{}", show(&e));
+                    }
+                }
+            },
+        },
         Err(e) => {
-            eprintln!("Failed to open file {}: {}", filename, e);
+            eprintln!("{}", e);
+
+            let mut source = e.source();
+            while let Some(cause) = source {
+                eprintln!("Caused by: {}", cause);
+                source = cause.source();
+            }
         }
     }
+
+    if failed {
+        exit(1);
+    }
 }
@@ -0,0 +1,143 @@
+use crate::json;
+use crate::syntax::*;
+use crate::visitor::*;
+use std::fmt;
+
+/// Complexity of a single routine's body
+pub struct RoutineMetrics {
+    pub name: String,
+    pub statements: usize,
+    // Decision points (if, loops, case branches, and, or) plus one
+    pub cyclomatic_complexity: usize,
+    pub max_nesting: usize,
+}
+
+/// Per-routine complexity report. The program body comes first, then
+/// procedures in declaration order, nested ones right after their parent.
+pub struct Metrics {
+    pub routines: Vec<RoutineMetrics>,
+}
+
+impl Metrics {
+    pub fn collect(program: &Program) -> Self {
+        let mut routines = vec![routine(program.identifier.get_id(), &program.compound)];
+        collect_procedures(&program.procedures, &mut routines);
+
+        Self { routines }
+    }
+
+    pub fn to_json(&self) -> String {
+        let routines = self.routines.iter().map(|r| {
+            format!(
+                "{{\"name\":{},\"statements\":{},\"cyclomatic_complexity\":{},\"max_nesting\":{}}}",
+                json::string(&r.name),
+                r.statements,
+                r.cyclomatic_complexity,
+                r.max_nesting
+            )
+        });
+
+        format!("{{\"routines\":{}}}", json::array(routines))
+    }
+}
+
+fn collect_procedures(procedures: &[ProcedureDeclaration], routines: &mut Vec<RoutineMetrics>) {
+    // A forward declaration has no statements, its body comes later
+    for p in procedures.iter().filter(|p| !p.forward) {
+        routines.push(routine(p.id.get_id(), &p.compound));
+        collect_procedures(&p.procedures, routines);
+    }
+}
+
+fn routine(name: String, body: &Compound) -> RoutineMetrics {
+    let mut collector = MetricsCollector {
+        metrics: RoutineMetrics {
+            name,
+            statements: 0,
+            cyclomatic_complexity: 1,
+            max_nesting: 0,
+        },
+        nesting: 0,
+    };
+
+    collector.visit_compound(body);
+    collector.metrics
+}
+
+struct MetricsCollector {
+    metrics: RoutineMetrics,
+    nesting: usize,
+}
+
+impl Visitor for MetricsCollector {
+    fn visit_statement(&mut self, s: &Statement) {
+        // BEGIN..END only groups statements and empty ones do nothing
+        if let Statement::Compound(_) | Statement::Empty = s {
+            walk_statement(self, s);
+            return;
+        }
+
+        self.metrics.statements += 1;
+
+        if let Statement::Cond(_) | Statement::While(_) | Statement::Repeat(_) | Statement::For(_) =
+            s
+        {
+            self.metrics.cyclomatic_complexity += 1;
+        }
+
+        // Every branch of a case is a separate path
+        if let Statement::Case(c) = s {
+            self.metrics.cyclomatic_complexity += c.branches.len();
+        }
+
+        self.nesting += 1;
+        self.metrics.max_nesting = self.metrics.max_nesting.max(self.nesting);
+        walk_statement(self, s);
+        self.nesting -= 1;
+    }
+
+    fn visit_simple_expression(&mut self, e: &SimpleExpression) {
+        // Short-circuit operators add paths as well
+        let mut sub_expr = e.sub_expr.as_ref();
+        while let Some(s) = sub_expr {
+            if let AdditiveOp::Or = *s.op {
+                self.metrics.cyclomatic_complexity += 1;
+            }
+            sub_expr = s.sub_expr.as_deref();
+        }
+
+        walk_simple_expression(self, e);
+    }
+
+    fn visit_term(&mut self, t: &Term) {
+        let mut sub_term = t.sub_term.as_deref();
+        while let Some(s) = sub_term {
+            if let MultiplicativeOp::And = s.op {
+                self.metrics.cyclomatic_complexity += 1;
+            }
+            sub_term = s.sub_term.as_deref();
+        }
+
+        walk_term(self, t);
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<20} {:>10} {:>10} {:>10}",
+            "Routine", "Statements", "Complexity", "Nesting"
+        )?;
+
+        for r in self.routines.iter() {
+            write!(
+                f,
+                "\n{:<20} {:>10} {:>10} {:>10}",
+                r.name, r.statements, r.cyclomatic_complexity, r.max_nesting
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+/// Settings which affect how a program is compiled
+#[derive(Clone)]
+pub struct Options {
+    // Nested scopes allowed before the analyzer gives up
+    pub max_scope_depth: usize,
+    // Nested expressions (parentheses, arguments, indexes) and statements allowed
+    pub max_expression_depth: usize,
+    pub div_mod: DivMod,
+    // Turbo Pascal extension, `not` on an integer flips all of its bits
+    pub bitwise_not: bool,
+    pub conformance: Conformance,
+    // Also warn about what is allowed but easy to overlook, like integer to real conversion
+    pub pedantic: bool,
+    // Constant array indexes must be in range, `{$R-}` turns this off
+    pub range_checks: bool,
+    // Directories searched in order for `<name>.pas` of units which aren't builtin
+    pub unit_path: Vec<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            max_scope_depth: 256,
+            max_expression_depth: 256,
+            div_mod: DivMod::Iso,
+            bitwise_not: false,
+            conformance: Conformance::Extended,
+            pedantic: false,
+            range_checks: true,
+            unit_path: Vec::new(),
+        }
+    }
+}
+
+/// How `div` and `mod` treat negative operands. ISO is the default.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DivMod {
+    // ISO 7185: div truncates, mod is never negative and needs a positive divisor
+    Iso,
+    // Both truncate towards zero, mod has the sign of the dividend (Turbo Pascal, most CPUs)
+    Truncated,
+}
+
+/// Which Pascal the compiler accepts
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Conformance {
+    // ISO 7185 with extensions (`string`, `#65`, `@`…) and a forgiving parser
+    Extended,
+    // Only ISO 7185: extensions are errors, the program heading needs its
+    // parameters, sections come in order and nothing follows the final period
+    Iso7185,
+    // Turbo Pascal 7: the extensions plus the `Crt` and `Dos` units, and
+    // identifiers which ignore case like keywords do
+    TurboPascal7,
+}
+
+/// Layout of source printed back from the AST, so it can match an existing codebase
+#[derive(Clone, Copy, Default)]
+pub struct PrintOptions {
+    pub newline: Newline,
+    pub keyword_case: KeywordCase,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Newline {
+    #[default]
+    Lf,
+    // Windows line endings
+    CrLf,
+}
+
+/// Keywords are case-insensitive, so any of these reads back the same
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum KeywordCase {
+    // `begin`
+    #[default]
+    Lower,
+    // `BEGIN`
+    Upper,
+    // `Begin`
+    Capitalized,
+}
@@ -1,84 +1,352 @@
-use crate::analyzer::Analyzer;
-use crate::error::CompilerError;
-use crate::lexer::Lexer;
-use crate::scope::Usage;
+use crate::analyzer::{Analyzer, MAX_STRING_LENGTH};
+use crate::error::{CompilerError, Diagnostic};
+use crate::lexer::{Directive, Lexer};
+use crate::options::{Conformance, Options};
+use crate::scope::{ScopeId, ScopeTree, Usage};
 use crate::syntax::*;
 use crate::token::{Token, TokenType};
-use std::iter::Peekable;
+use std::mem;
 
-pub struct Parser {
-    lexer: Peekable<Lexer>,
+// Token from the lexer and the included file it comes from
+type Lexed = (Option<Result<Token, CompilerError>>, Option<String>);
+// Operator and operand of a term or simple expression after its first one
+type MulOperand = (MultiplicativeOp, Box<Factor>);
+type AddOperand = (Box<AdditiveOp>, Box<Term>);
+
+pub struct Parser<'s> {
+    lexer: Lexer,
     current_token: Option<Result<Token, CompilerError>>,
+    // The token after the current one once looked at, and its included file
+    peeked: Option<Lexed>,
     current_pos: (usize, usize),
+    // Included file the current token comes from, None in the main source
+    current_file: Option<String>,
     pub(crate) errors: Vec<CompilerError>,
+    // Compiler directives in the order they were read
+    pub(crate) directives: Vec<Directive>,
+    // {$R+} or {$R-} read with the current token, it takes effect once that token is
+    // passed as the code before it may still be checked
+    range_checks_due: Option<bool>,
+    // Gets a copy of every error as soon as it is reported
+    sink: Option<&'s mut (dyn FnMut(Diagnostic) + Send)>,
     analyzer: Analyzer,
+    // Tokens read from the lexer so far
+    pub(crate) token_count: usize,
+    // Parenthesized expressions currently being parsed
+    expr_depth: usize,
+    max_expr_depth: usize,
+    // The nesting limit was hit, the statement (or type) around it is skipped as a whole
+    too_deep: bool,
+    // Records and variants of the type being parsed whose END or `)` hasn't come yet
+    open_types: usize,
+    // Closing keywords (END, UNTIL) of statement lists currently being parsed
+    open_lists: Vec<mem::Discriminant<TokenType>>,
+    // Input is a lone expression or statement, so EOF may end an expression
+    fragment: bool,
+    // Parsing a condition or bound which THEN or DO follows. A statement starting
+    // after it ends the expression, the keyword was left out.
+    keyword_due: bool,
+    // Parsing the condition of IF, WHILE or UNTIL, where ':=' is a mistaken '='
+    condition: bool,
+    // Parsing the interface of a unit, where routines are headings only
+    interface: bool,
+    conformance: Conformance,
+    pedantic: bool,
 }
 
-impl Parser {
-    pub fn new(lexer: Lexer) -> Self {
+impl<'s> Parser<'s> {
+    pub fn new(lexer: Lexer, options: &Options) -> Self {
+        let analyzer = Analyzer::with_options(options);
+
         let mut parser = Self {
-            lexer: lexer.peekable(),
+            lexer: lexer.with_conformance(options.conformance),
             current_token: None,
+            peeked: None,
+            current_file: None,
             errors: Vec::new(),
+            directives: Vec::new(),
+            range_checks_due: None,
+            sink: None,
             current_pos: (0, 0),
-            analyzer: Analyzer::new(),
+            analyzer,
+            token_count: 0,
+            expr_depth: 0,
+            max_expr_depth: options.max_expression_depth,
+            too_deep: false,
+            open_types: 0,
+            open_lists: Vec::new(),
+            fragment: false,
+            keyword_due: false,
+            condition: false,
+            interface: false,
+            conformance: options.conformance,
+            pedantic: options.pedantic,
         };
 
         parser.next_token();
         parser
     }
 
+    /// Units whose compilation led to this one, using any of them again is circular
+    pub(crate) fn with_loading(mut self, units: Vec<String>) -> Self {
+        self.analyzer.loading = units;
+        self
+    }
+
+    /// Delivers recoverable errors to `sink` while parsing, they are still kept in `errors`
+    pub fn set_sink(&mut self, sink: &'s mut (dyn FnMut(Diagnostic) + Send)) {
+        self.sink = Some(sink);
+    }
+
+    /// Reports use of something ISO 7185 doesn't have when only ISO 7185 is accepted
+    fn extension(&mut self, what: &str, pos: (usize, usize)) {
+        if self.conformance == Conformance::Iso7185 {
+            self.report(
+                CompilerError::syntax(format!("{} is not part of ISO 7185", what)).with_span(pos),
+            );
+        }
+    }
+
+    fn report(&mut self, e: CompilerError) {
+        let e = match (&self.current_file, e.file()) {
+            (Some(file), None) => e.with_file(file.as_str()),
+            _ => e,
+        };
+
+        if let Some(sink) = self.sink.as_mut() {
+            sink(e.clone());
+        }
+        self.errors.push(e);
+    }
+
+    /// Position of the token being processed
+    pub fn position(&self) -> (usize, usize) {
+        self.current_pos
+    }
+
     fn next_token(&mut self) {
-        let res = self.lexer.next();
+        if let Some(on) = self.range_checks_due.take() {
+            self.analyzer.options.range_checks = on;
+        }
+
+        let (res, file) = self.peeked.take().unwrap_or_else(|| self.lex());
         self.current_token = res;
+        self.current_file = file;
 
-        if let Some(Ok(t)) = &self.current_token {
-            self.current_pos = t.pos
+        for e in self.lexer.take_errors() {
+            self.report(e);
+        }
+        for d in self.lexer.take_directives() {
+            self.directive(&d);
+            self.directives.push(d);
+        }
+
+        if self.current_token.is_some() {
+            self.token_count += 1;
+        }
+
+        if let Some(Ok(t)) = &mut self.current_token {
+            self.current_pos = t.pos;
+
+            // Turbo Pascal matches identifiers case-insensitively, builtins are lowercase
+            if let TokenType::Identifier(id) = &mut t.token {
+                if self.conformance == Conformance::TurboPascal7 {
+                    id.make_ascii_lowercase();
+                }
+            }
+        }
+    }
+
+    // Switches the front end honours, the rest get a warning so they aren't silently lost
+    fn directive(&mut self, d: &Directive) {
+        let warning = match (d.name.as_str(), d.argument.as_str()) {
+            ("R", "+" | "-") => {
+                self.range_checks_due = Some(d.argument == "+");
+                return;
+            }
+            // Input and output checks, there is nothing to check before the
+            // program runs. Files are included by the lexer.
+            ("I", _) => return,
+            ("MODE", _) => format!(
+                "{{$MODE {}}} has no effect, the dialect is chosen with --conformance",
+                d.argument
+            ),
+            _ => format!("Directive {{${}}} is not supported and is ignored", d.name),
+        };
+
+        self.report(CompilerError::lint(warning).with_span(d.pos));
+    }
+
+    fn peek_token(&mut self) -> Option<&Result<Token, CompilerError>> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lex());
         }
+        self.peeked.as_ref().and_then(|(token, _)| token.as_ref())
+    }
+
+    // Reading stays in the file of a token until the next one is lexed, as every
+    // line ends with a newline
+    fn lex(&mut self) -> Lexed {
+        let token = self.lexer.next();
+        let file = self.lexer.included_file().map(|f| f.display().to_string());
+        (token, file)
+    }
+
+    /// Included file of the current token, for errors the parser doesn't report itself
+    pub(crate) fn current_file(&self) -> Option<&str> {
+        self.current_file.as_deref()
     }
 
     fn parse_factor(&mut self) -> Result<Factor, CompilerError> {
+        // Function calls and selected variables read their own arguments and selectors
+        if let Some(Ok(
+            token @ Token {
+                token: TokenType::Identifier(_),
+                ..
+            },
+        )) = &self.current_token
+        {
+            let id = Identifier::new(token.clone());
+
+            if let Ok(Usage::Function(_) | Usage::Procedure(_)) = self.analyzer.find_identifier(&id)
+            {
+                let mut call = self.parse_call()?;
+
+                return match self.analyzer.check_call(&mut call)? {
+                    Some(_) => Ok(Factor::Call(call)),
+                    None => Err(CompilerError::semantic(format!(
+                        "Procedure {:?} has no value",
+                        call.name.get_id()
+                    ))
+                    .with_span(call.name.id.pos)),
+                };
+            }
+
+            if let Some(Ok(Token {
+                token: TokenType::LBracket | TokenType::Period | TokenType::Caret,
+                ..
+            })) = self.peek_token()
+            {
+                let mut name = self.parse_identifier()?;
+                self.analyzer.resolve_identifier(&mut name)?;
+                let selectors = self.parse_selectors()?;
+
+                return Ok(Factor::Selected(SelectedVariable { name, selectors }));
+            }
+        }
+
         let factor = match &self.current_token {
             Some(Ok(token)) => match token {
                 Token {
                     token: TokenType::Integer(_),
                     ..
                 } => Ok(Factor::Integer(token.clone())),
+                Token {
+                    token: TokenType::HexInteger(_),
+                    pos,
+                } => {
+                    let pos = *pos;
+                    let factor = Factor::Integer(token.clone());
+                    self.extension("A hex literal", pos);
+                    Ok(factor)
+                }
                 Token {
                     token: TokenType::Real(_),
                     ..
                 } => Ok(Factor::Real(token.clone())),
+                Token {
+                    token: TokenType::StringLiteral(s),
+                    ..
+                } => match s.chars().count() {
+                    1 => Ok(Factor::Char(token.clone())),
+                    n if n > MAX_STRING_LENGTH as usize
+                        && self.conformance == Conformance::TurboPascal7 =>
+                    {
+                        Err(CompilerError::semantic(format!(
+                            "String literal has {} characters, at most {} fit in a string",
+                            n, MAX_STRING_LENGTH
+                        ))
+                        .with_span(token.pos))
+                    }
+                    _ => Ok(Factor::String(token.clone())),
+                },
+                Token {
+                    token: TokenType::CharCode(_),
+                    ..
+                } => Ok(Factor::Char(token.clone())),
                 Token {
                     token: TokenType::Identifier(_),
                     ..
                 } => {
-                    let id = Identifier { id: token.clone() };
-                    let usage = self.analyzer.find_identifier(&id)?;
+                    let mut id = Identifier::new(token.clone());
+                    let usage = self.analyzer.resolve_identifier(&mut id)?;
                     match usage {
-                        Usage::Variable(_) | Usage::Constant(_) => {
-                            Ok(Factor::Identifier(Identifier { id: token.clone() }))
-                        }
-                        _ => Err(CompilerError::semantic(
-                            "Identifier is not a variable".into(),
-                            token.pos,
-                        )),
+                        Usage::Variable(_) | Usage::Constant(_) => Ok(Factor::Identifier(id)),
+                        _ => Err(CompilerError::semantic("Identifier is not a variable")
+                            .with_span(token.pos)),
                     }
                 }
                 Token {
                     token: TokenType::LBrace,
                     ..
-                } => Ok(Factor::Expression(Box::new(self.parse_simple_expr()?))),
-                tok => Err(CompilerError::syntax(
-                    format!("Expected literal or identifier, found {:?}", tok),
-                    tok.pos,
-                )),
+                } => Ok(Factor::Expression(Box::new(self.parse_braced_expr()?))),
+                Token {
+                    token: TokenType::At,
+                    pos,
+                } => {
+                    let pos = *pos;
+                    self.next_token();
+                    self.extension("Address-of '@'", pos);
+                    let mut name = self.parse_identifier()?;
+                    self.analyzer.resolve_identifier(&mut name)?;
+                    let selectors = self.parse_selectors()?;
+
+                    // The trailing next_token below would skip whatever follows the variable
+                    return Ok(Factor::Address(SelectedVariable { name, selectors }));
+                }
+                Token {
+                    token: TokenType::NilKeyword,
+                    ..
+                } => Ok(Factor::Nil(token.clone())),
+                Token {
+                    token: TokenType::NotOp,
+                    ..
+                } => {
+                    self.next_token();
+                    // `not not not ...` nests like braces do
+                    let operand = self.nested("Expression", |p| p.parse_factor())?;
+
+                    // The operand has moved past its last token already
+                    return Ok(Factor::Not(Box::new(operand)));
+                }
+                Token {
+                    token: TokenType::LBracket,
+                    ..
+                } => {
+                    let mut set = self.parse_set_constructor()?;
+                    self.analyzer.check_set_constructor(&mut set)?;
+
+                    Ok(Factor::Set(set))
+                }
+                tok => Err(CompilerError::syntax(format!(
+                    "Expected literal or identifier, found {:?}",
+                    tok
+                ))
+                .with_span(tok.pos)),
             },
             Some(Err(e)) => Err((*e).clone()),
-            _ => Err(CompilerError::syntax(
-                "Expected int or real, found EOF".into(),
-                self.current_pos,
-            )),
+            _ => Err(CompilerError::syntax("Expected int or real, found EOF")
+                .with_span(self.current_pos)),
         };
+
+        if let Ok(Factor::Char(Token {
+            token: TokenType::CharCode(_),
+            pos,
+        })) = &factor
+        {
+            self.extension("A character code", *pos);
+        }
+
         self.next_token();
         factor
     }
@@ -92,14 +360,10 @@ impl Parser {
             Usage::Variable(s) | Usage::Constant(s) => s,
             _ => todo!(),
         };
-        let sub_term_type;
-        if sub_term.is_some() {
-            sub_term_type = self
-                .analyzer
-                .get_sub_term_type(&sub_term.as_ref().unwrap())?;
-        } else {
-            sub_term_type = String::new();
-        }
+        let sub_term_type = match &sub_term {
+            Some(s) => s.sub_term_type.clone(),
+            None => String::new(),
+        };
 
         let term_type =
             self.analyzer
@@ -115,64 +379,83 @@ impl Parser {
     }
 
     fn parse_sub_term(&mut self) -> Result<Option<Box<SubTerm>>, CompilerError> {
-        // TODO: this check probably should not be here
+        // `a * b * c` is read in a loop, so a long chain doesn't nest calls. The
+        // chain is then built from its end, where each link's type is merged.
+        let mut operands = Vec::new();
+        while let Some(operand) = self.parse_mul_operand()? {
+            operands.push(operand);
+        }
+
+        let mut sub_term: Option<Box<SubTerm>> = None;
+        for (op, factor) in operands.into_iter().rev() {
+            let factor_type = self.analyzer.get_factor_type(&factor)?;
+            let fact_type_str = match factor_type {
+                Usage::Variable(s) | Usage::Constant(s) => s,
+                _ => todo!(),
+            };
+            let op_text = match op {
+                MultiplicativeOp::Mul => "*",
+                MultiplicativeOp::Div => "div",
+                MultiplicativeOp::Mod => "mod",
+                MultiplicativeOp::And => "and",
+            };
+            self.analyzer
+                .check_operator(op_text, &fact_type_str, factor.pos())?;
+            let sub_term_type = match &sub_term {
+                Some(s) => s.sub_term_type.clone(),
+                None => String::new(),
+            };
+
+            let res = self.analyzer.merge_types(
+                &fact_type_str,
+                &sub_term_type,
+                self.current_pos,
+                false,
+            )?;
+
+            sub_term = Some(Box::new(SubTerm {
+                op,
+                factor,
+                sub_term,
+                sub_term_type: res,
+            }));
+        }
+
+        Ok(sub_term)
+    }
+
+    // Next `* factor` of a term, None where the term ends
+    fn parse_mul_operand(&mut self) -> Result<Option<MulOperand>, CompilerError> {
         match &self.current_token {
             Some(Ok(Token {
                 token: TokenType::RBrace,
                 ..
             })) => Ok(None),
-            Some(Ok(tok)) if tok.is_rel_op() => Ok(None),
-            _ => {
-                match &self.current_token {
-                    Some(Ok(t)) if t.is_mul_op() => {
-                        let op = self.parse_multiplicative_op()?;
-                        let factor = Box::new(self.parse_factor()?);
-                        let sub_term_res = self.parse_sub_term()?;
-                        let sub_term = sub_term_res;
-
-                        let factor_type = self.analyzer.get_factor_type(&factor)?;
-                        let fact_type_str = match factor_type {
-                            Usage::Variable(s) | Usage::Constant(s) => s,
-                            _ => todo!(),
-                        };
-                        let sub_term_type;
-                        if sub_term.is_some() {
-                            sub_term_type = self
-                                .analyzer
-                                .get_sub_term_type(sub_term.as_ref().unwrap())?;
-                        } else {
-                            sub_term_type = String::new();
-                        }
+            Some(Ok(t)) if t.is_mul_op() => {
+                let op_pos = t.pos;
+                let op = self.parse_multiplicative_op()?;
+                let factor = Box::new(self.parse_factor()?);
+                self.analyzer.check_divisor(&op, op_pos, &factor)?;
 
-                        let res = self.analyzer.merge_types(
-                            &fact_type_str,
-                            &sub_term_type,
-                            self.current_pos,
-                            false,
-                        )?;
-                        // let sub_term_type = self.analyzer.get_subterm_type(&sub_term)?;
-
-                        Ok(Some(Box::new(SubTerm {
-                            op,
-                            factor,
-                            sub_term,
-                            sub_term_type: res,
-                        })))
-                    }
-                    Some(Ok(t)) if t.is_add_op() || t.is_expression_end() || t.is_rel_op() => {
-                        Ok(None)
-                    }
-                    Some(Ok(t)) => Err(CompilerError::syntax(
-                        format!("Expected *, div or mod, found {:?}", t),
-                        t.pos,
-                    )),
-                    Some(Err(e)) => Err(e.clone()),
-                    None => Err(CompilerError::syntax(
-                        "Unexpected EOF".into(),
-                        self.current_pos,
-                    )),
-                }
+                Ok(Some((op, factor)))
+            }
+            Some(Ok(t))
+                if t.is_add_op()
+                    || t.is_expression_end()
+                    || t.is_rel_op()
+                    || self.compares_by_mistake(t)
+                    || (self.keyword_due && t.starts_statement()) =>
+            {
+                Ok(None)
             }
+            Some(Ok(t)) => Err(CompilerError::syntax(format!(
+                "Expected *, div or mod, found {:?}",
+                t
+            ))
+            .with_span(t.pos)),
+            Some(Err(e)) => Err(e.clone()),
+            None if self.fragment => Ok(None),
+            None => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
         }
     }
 
@@ -186,21 +469,17 @@ impl Parser {
                     token: TokenType::Comma,
                     ..
                 } => Ok(()),
-                _ => Err(CompilerError::syntax(
-                    format!("Expected ',', found {:?}", t),
-                    t.pos,
-                )),
+                _ => Err(
+                    CompilerError::syntax(format!("Expected ',', found {:?}", t)).with_span(t.pos),
+                ),
             },
             Some(Err(e)) => Err(e),
-            _ => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
-            )),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
         }
     }
 
     fn parse_type_declaration(&mut self) -> Result<Vec<TypeDeclaration>, CompilerError> {
-        // id {,id} : type_id
+        // id {,id} = type
         let mut types = Vec::new();
 
         loop {
@@ -215,58 +494,49 @@ impl Parser {
 
                         match &self.current_token {
                             Some(Ok(Token {
-                                token: TokenType::Colon,
+                                token: TokenType::Eq,
                                 ..
                             })) => break,
                             _ => self.parse_comma()?,
                         }
                     }
                     _ => {
-                        types.push(Err(CompilerError::syntax(
-                            format!("Expected identifier, found {:?}", token),
-                            token.pos,
-                        )));
+                        types.push(Err(CompilerError::syntax(format!(
+                            "Expected identifier, found {:?}",
+                            token
+                        ))
+                        .with_span(token.pos)));
                     }
                 },
                 Some(Err(e)) => types.push(Err(e)),
-                None => types.push(Err(CompilerError::syntax(
-                    "Unexpected EOF".into(),
-                    self.current_pos,
-                ))),
+                None => types.push(Err(
+                    CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)
+                )),
             }
         }
 
         let parent_type = match &self.current_token {
             Some(Ok(Token {
-                token: TokenType::Colon,
+                token: TokenType::Eq,
                 ..
             })) => {
                 self.next_token();
-                match self.current_token.take() {
-                    Some(Ok(token)) => {
-                        let type_id = Identifier { id: token.clone() };
-                        self.next_token();
-                        self.parse_semicolon()?;
-                        let usage = self.analyzer.find_identifier(&type_id)?;
-
-                        match usage {
-                            Usage::Type(_) => Ok(type_id),
-                            _ => Err(CompilerError::semantic(
-                                "Identifier is not a type".into(),
-                                token.pos,
-                            )),
-                        }
+                let mut spec = self.parse_type_spec()?;
+                self.parse_semicolon()?;
+
+                // The declaration is complete, so keep the names declared. Only an alias
+                // of an unknown name is dropped, it could name itself: `type A = A`.
+                match self.analyzer.check_type_spec(&mut spec) {
+                    Err(e) if matches!(spec, TypeSpec::Named(_)) => {
+                        self.report(e);
+                        return Ok(Vec::new());
                     }
-                    _ => Err(CompilerError::syntax(
-                        "Expected identifier".into(),
-                        self.current_pos,
-                    )),
+                    Err(e) => self.report(e),
+                    Ok(_) => (),
                 }
+                Ok(spec)
             }
-            _ => Err(CompilerError::syntax(
-                "Expected ','".into(),
-                self.current_pos,
-            )),
+            _ => Err(CompilerError::syntax("Expected '='").with_span(self.current_pos)),
         };
 
         match parent_type {
@@ -276,11 +546,11 @@ impl Parser {
                 for id in types {
                     if let Ok(token) = id {
                         declarations.push(TypeDeclaration {
-                            id: Identifier { id: token },
+                            id: Identifier::new(token),
                             parent: type_id.clone(),
                         });
                     } else if let Err(e) = id {
-                        self.errors.push(e);
+                        self.report(e);
                     }
                 }
 
@@ -290,6 +560,84 @@ impl Parser {
         }
     }
 
+    fn parse_const_declaration(&mut self) -> Result<ConstDeclaration, CompilerError> {
+        // id = const_expr;
+        let id = self.parse_identifier()?;
+
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::Eq,
+                ..
+            })) => self.next_token(),
+            // Common mistake: `N := 10`, declared as meant
+            Some(Ok(Token {
+                token: TokenType::AssignOp,
+                pos,
+            })) => {
+                self.report(
+                    CompilerError::syntax("Expected '=' in a constant declaration, found ':='")
+                        .with_span(*pos)
+                        .with_note("constants are declared with '=', ':=' assigns variables"),
+                );
+                self.next_token();
+            }
+            Some(Ok(t)) => {
+                return Err(
+                    CompilerError::syntax(format!("Expected '=', found {:?}", t)).with_span(t.pos),
+                )
+            }
+            Some(Err(e)) => return Err(e.clone()),
+            None => {
+                return Err(
+                    CompilerError::syntax("Expected '=', found EOF").with_span(self.current_pos)
+                )
+            }
+        }
+
+        let value = self.parse_expr()?;
+        self.parse_semicolon()?;
+
+        Ok(ConstDeclaration {
+            id,
+            value: Box::new(value),
+        })
+    }
+
+    fn parse_const_section(&mut self) -> Result<ConstSection, CompilerError> {
+        // [const
+        //      <const_declaration>
+        //      {<const_declaration>}]
+        let mut constants = Vec::new();
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::ConstKeyword,
+                ..
+            })) => {
+                self.next_token();
+
+                while self.at_declaration() || self.at_mistyped_const() {
+                    match self.parse_const_declaration() {
+                        Ok(decl) => match self.analyzer.check_const_declaration(decl) {
+                            Ok(decl) => constants.push(decl),
+                            Err(e) => self.report(e),
+                        },
+                        Err(e) => {
+                            self.report(e);
+                            self.skip_until_starters();
+                        }
+                    }
+                }
+
+                Ok(ConstSection { constants })
+            }
+            Some(Ok(t)) => Err(
+                CompilerError::syntax(format!("Expected CONST, found {:?}", t)).with_span(t.pos),
+            ),
+            Some(Err(e)) => Err(e.clone()),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        }
+    }
+
     fn parse_type_section(&mut self) -> Result<TypeSection, CompilerError> {
         // [type
         //      <type_declaration>
@@ -302,34 +650,29 @@ impl Parser {
             })) => {
                 self.next_token();
 
-                loop {
-                    match &self.current_token {
-                        Some(Ok(Token {
-                            token: TokenType::BeginKeyword,
-                            ..
-                        }))
-                        | Some(Ok(Token {
-                            token: TokenType::VarKeyword,
-                            ..
-                        })) => break,
-                        _ => {
-                            let decl = self.parse_type_declaration();
-                            match decl {
-                                Ok(v) => {
-                                    for i in v {
-                                        let check_res = self.analyzer.check_type_declaration(i);
-                                        match check_res {
-                                            Ok(decl) => declarations.push(decl),
-                                            Err(e) => self.errors.push(e),
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    self.errors.push(e);
-                                    self.skip_until_starters();
+                while self.at_declaration() {
+                    let decl = self.parse_type_declaration();
+                    match decl {
+                        Ok(v) => {
+                            for i in v {
+                                let check_res = self.analyzer.check_type_declaration(i);
+                                match check_res {
+                                    Ok(decl) => declarations.push(decl),
+                                    Err(e) => self.report(e),
                                 }
                             }
                         }
+                        Err(e) => {
+                            self.report(e);
+                            self.skip_until_starters();
+                        }
+                    }
+                }
+
+                // Pointers may point to types declared later in the section
+                for decl in declarations.iter_mut() {
+                    if let Err(e) = self.analyzer.check_pointer_targets(&mut decl.parent) {
+                        self.report(e);
                     }
                 }
 
@@ -337,20 +680,16 @@ impl Parser {
                     types: declarations,
                 })
             }
-            Some(Ok(t)) => Err(CompilerError::syntax(
-                format!("Expected TYPE, found {:?}", t),
-                t.pos,
-            )),
+            Some(Ok(t)) => {
+                Err(CompilerError::syntax(format!("Expected TYPE, found {:?}", t)).with_span(t.pos))
+            }
             Some(Err(e)) => Err(e.clone()),
-            _ => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
-            )),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
         }
     }
 
     fn parse_var_declaration(&mut self) -> Result<Vec<VarDeclaration>, CompilerError> {
-        // id {,id} : type_id
+        // id {,id} : type
         let mut identifiers = Vec::new();
 
         loop {
@@ -372,17 +711,17 @@ impl Parser {
                         }
                     }
                     _ => {
-                        identifiers.push(Err(CompilerError::syntax(
-                            format!("Expected identifier, found {:?}", token),
-                            token.pos,
-                        )));
+                        identifiers.push(Err(CompilerError::syntax(format!(
+                            "Expected identifier, found {:?}",
+                            token
+                        ))
+                        .with_span(token.pos)));
                     }
                 },
                 Some(Err(e)) => identifiers.push(Err(e)),
-                None => identifiers.push(Err(CompilerError::syntax(
-                    "Unexpected EOF".into(),
-                    self.current_pos,
-                ))),
+                None => identifiers.push(Err(
+                    CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)
+                )),
             }
         }
 
@@ -392,30 +731,20 @@ impl Parser {
                 ..
             })) => {
                 self.next_token();
-                match self.current_token.take() {
-                    Some(Ok(token)) => {
-                        let type_id = Identifier { id: token.clone() };
-                        self.next_token();
-                        self.parse_semicolon()?;
-                        let usage = self.analyzer.find_identifier(&type_id)?;
-                        match usage {
-                            Usage::Type(_) => Ok(type_id),
-                            _ => Err(CompilerError::semantic(
-                                "Identifier is not a type".into(),
-                                token.pos,
-                            )),
-                        }
-                    }
-                    _ => Err(CompilerError::syntax(
-                        "Expected identifier".into(),
-                        self.current_pos,
-                    )),
+                let mut spec = self.parse_type_spec()?;
+                self.parse_semicolon()?;
+
+                // The declaration is complete, so keep the names declared
+                if let Err(e) = self
+                    .analyzer
+                    .check_type_spec(&mut spec)
+                    .and_then(|_| self.analyzer.check_pointer_targets(&mut spec))
+                {
+                    self.report(e);
                 }
+                Ok(spec)
             }
-            _ => Err(CompilerError::syntax(
-                "Expected ','".into(),
-                self.current_pos,
-            )),
+            _ => Err(CompilerError::syntax("Expected ','").with_span(self.current_pos)),
         };
 
         match var_type {
@@ -425,11 +754,11 @@ impl Parser {
                 for id in identifiers {
                     if let Ok(token) = id {
                         declarations.push(VarDeclaration {
-                            id: Identifier { id: token },
+                            id: Identifier::new(token),
                             type_name: type_id.clone(),
                         });
                     } else if let Err(e) = id {
-                        self.errors.push(e);
+                        self.report(e);
                     }
                 }
 
@@ -452,51 +781,46 @@ impl Parser {
                 self.next_token();
                 Ok(())
             }
-            Some(Ok(t)) => Err(CompilerError::syntax(
-                format!("Expected VAR, found {:?}", t),
-                t.pos,
-            )),
-            _ => Err(CompilerError::syntax(
-                "Expected VAR, found EOF".into(),
-                self.current_pos,
-            )),
+            Some(Ok(t)) => {
+                Err(CompilerError::syntax(format!("Expected VAR, found {:?}", t)).with_span(t.pos))
+            }
+            _ => Err(CompilerError::syntax("Expected VAR, found EOF").with_span(self.current_pos)),
         };
 
         match check_section {
-            Ok(_) => loop {
-                let decl = self.parse_var_declaration();
-                match decl {
-                    Ok(v) => {
-                        for i in v {
-                            let check_res = self.analyzer.check_var_declaration(i);
-                            match check_res {
-                                Ok(decl) => declarations.push(decl),
-                                Err(e) => self.errors.push(e),
+            Ok(_) => {
+                while self.at_declaration() {
+                    let decl = self.parse_var_declaration();
+                    match decl {
+                        Ok(v) => {
+                            for i in v {
+                                let check_res = self.analyzer.check_var_declaration(i);
+                                match check_res {
+                                    Ok(decl) => declarations.push(decl),
+                                    Err(e) => self.report(e),
+                                }
                             }
                         }
-                    }
-                    Err(e) => {
-                        self.errors.push(e);
-                        self.skip_until_starters();
+                        Err(e) => {
+                            self.report(e);
+                            self.skip_until_starters();
+                        }
                     }
                 }
 
-                match self.current_token {
-                    Some(Ok(Token {
-                        token: TokenType::BeginKeyword,
-                        pos,
-                    })) => {
-                        if declarations.is_empty() {
-                            self.errors.push(CompilerError::syntax(
-                                "Expected identifier, found BEGIN".into(),
-                                pos,
-                            ));
-                        };
-                        return Ok(VarSection { declarations });
-                    }
-                    _ => continue,
-                }
-            },
+                if declarations.is_empty() {
+                    self.report(match &self.current_token {
+                        Some(Ok(t)) => {
+                            CompilerError::syntax(format!("Expected identifier, found {:?}", t))
+                                .with_span(t.pos)
+                        }
+                        _ => CompilerError::syntax("Expected identifier, found EOF")
+                            .with_span(self.current_pos),
+                    });
+                };
+
+                Ok(VarSection { declarations })
+            }
             Err(e) => Err(e),
         }
     }
@@ -510,20 +834,17 @@ impl Parser {
                 } = token
                 {
                     self.next_token();
-                    Ok(Identifier { id: token.clone() })
+                    Ok(Identifier::new(token.clone()))
                 } else {
                     self.next_token();
-                    Err(CompilerError::syntax(
-                        format!("Expected identifier, found {:?}", token),
-                        token.pos,
-                    ))
+                    Err(
+                        CompilerError::syntax(format!("Expected identifier, found {:?}", token))
+                            .with_span(token.pos),
+                    )
                 }
             }
             Some(Err(e)) => Err(e.clone()),
-            _ => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
-            )),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
         }
     }
 
@@ -543,20 +864,17 @@ impl Parser {
                 } = t
                 {
                     Ok(())
-                } else if t.is_rel_op() {
+                } else if t.is_rel_op() && self.conformance != Conformance::Iso7185 {
                     Ok(())
                 } else {
-                    Err(CompilerError::syntax(
-                        format!("Expected ';', found {:?}", t),
-                        t.pos,
-                    ))
+                    Err(
+                        CompilerError::syntax(format!("Expected ';', found {:?}", t))
+                            .with_span(t.pos),
+                    )
                 }
             }
             Some(Err(e)) => Err(e),
-            _ => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
-            )),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
         }
     }
 
@@ -570,334 +888,2088 @@ impl Parser {
                     token: TokenType::Period,
                     ..
                 } => Ok(()),
-                _ => Err(CompilerError::syntax(
-                    format!("Expected '.', found {:?}", t),
-                    t.pos,
-                )),
+                _ => Err(
+                    CompilerError::syntax(format!("Expected '.', found {:?}", t)).with_span(t.pos),
+                ),
             },
             Some(Err(e)) => Err(e),
-            None => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
-            )),
+            None => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
         }
     }
 
-    fn parse_program(&mut self) -> Result<Program, CompilerError> {
-        // program <identifier>;
-        // <vars>
-        // <types>
-        // <procedures>
-        // <compound>
-        // end.
-        self.analyzer.enter_scope();
-        match &self.current_token {
-            Some(Ok(Token {
-                token: TokenType::ProgramKeyword,
-                ..
-            })) => {
-                self.next_token();
-                let id = self.parse_identifier()?;
-                // Semicolon check
-                self.parse_semicolon()?;
-
-                let type_section = match self.current_token {
-                    Some(Ok(Token {
-                        token: TokenType::TypeKeyword,
-                        ..
-                    })) => Some(Box::new(self.parse_type_section()?)),
-                    _ => None,
-                };
-                let var_section = match self.current_token {
-                    Some(Ok(Token {
-                        token: TokenType::VarKeyword,
-                        ..
-                    })) => Some(Box::new(self.parse_var_section()?)),
-                    _ => None,
-                };
-                let compound = Box::new(self.parse_compound()?);
+    #[allow(clippy::type_complexity)]
+    fn parse_sections(
+        &mut self,
+    ) -> Result<
+        (
+            Option<Box<ConstSection>>,
+            Option<Box<TypeSection>>,
+            Option<Box<VarSection>>,
+        ),
+        CompilerError,
+    > {
+        // Sections may come in any order and repeat, each kind is merged into one.
+        // Nothing declared in a later kind can be used by an earlier one, so
+        // const, type, var order keeps every declaration after what it uses.
+        let mut const_section: Option<Box<ConstSection>> = None;
+        let mut type_section: Option<Box<TypeSection>> = None;
+        let mut var_section: Option<Box<VarSection>> = None;
+        // Index of the kind of the last section in ISO order
+        let mut last_kind = None;
 
-                self.parse_period()?;
-                self.analyzer.leave_scope();
+        loop {
+            let kind = match self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::ConstKeyword,
+                    ..
+                })) => 0,
+                Some(Ok(Token {
+                    token: TokenType::TypeKeyword,
+                    ..
+                })) => 1,
+                Some(Ok(Token {
+                    token: TokenType::VarKeyword,
+                    ..
+                })) => 2,
+                _ => 3,
+            };
+            if kind < 3 && last_kind.is_some_and(|last| kind <= last) {
+                self.extension("Repeated or reordered section", self.current_pos);
+            }
+            last_kind = Some(kind);
 
-                Ok(Program {
-                    identifier: id,
+            match self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::ConstKeyword,
+                    ..
+                })) => {
+                    let section = self.parse_const_section()?;
+                    match &mut const_section {
+                        Some(s) => s.constants.extend(section.constants),
+                        None => const_section = Some(Box::new(section)),
+                    }
+                }
+                Some(Ok(Token {
+                    token: TokenType::TypeKeyword,
+                    ..
+                })) => {
+                    let section = self.parse_type_section()?;
+                    match &mut type_section {
+                        Some(s) => s.types.extend(section.types),
+                        None => type_section = Some(Box::new(section)),
+                    }
+                }
+                Some(Ok(Token {
+                    token: TokenType::VarKeyword,
+                    ..
+                })) => {
+                    let section = self.parse_var_section()?;
+                    match &mut var_section {
+                        Some(s) => s.declarations.extend(section.declarations),
+                        None => var_section = Some(Box::new(section)),
+                    }
+                }
+                _ => return Ok((const_section, type_section, var_section)),
+            }
+        }
+    }
+
+    // parse_compound accepts a body without BEGIN to keep going,
+    // but programs and procedures must have one
+    fn check_body_start(&mut self, owner: &str) {
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::BeginKeyword,
+                ..
+            })) => (),
+            Some(Err(_)) => (),
+            _ => self.report(
+                CompilerError::syntax(format!("Expected 'begin' to start the {} body", owner))
+                    .with_span(self.current_pos),
+            ),
+        }
+    }
+
+    fn parse_procedures(&mut self) -> Vec<ProcedureDeclaration> {
+        // Bodies of forward declarations belong to the same part
+        let forwards = self.analyzer.forward_count();
+        let procedures = self.parse_routines();
+        self.report_unresolved_forwards(forwards);
+        procedures
+    }
+
+    fn parse_routines(&mut self) -> Vec<ProcedureDeclaration> {
+        let mut procedures = Vec::new();
+
+        while let Some(Ok(Token {
+            token: TokenType::ProcedureKeyword | TokenType::FunctionKeyword,
+            ..
+        })) = &self.current_token
+        {
+            match self.parse_procedure_declaration() {
+                Ok(p) => procedures.push(p),
+                Err(e) => {
+                    self.report(e);
+                    self.skip_until_block();
+                }
+            }
+        }
+
+        procedures
+    }
+
+    fn report_unresolved_forwards(&mut self, first: usize) {
+        for e in self.analyzer.unresolved_forwards(first) {
+            self.report(e);
+        }
+    }
+
+    fn parse_procedure_declaration(&mut self) -> Result<ProcedureDeclaration, CompilerError> {
+        // procedure <identifier> [(<parameters>)];
+        // function <identifier> [(<parameters>)] : <type>;
+        // forward; | <block>, where <block> is
+        // <consts>
+        // <types>
+        // <vars>
+        // <procedures>
+        // <compound>;
+        let is_function = matches!(
+            self.current_token,
+            Some(Ok(Token {
+                token: TokenType::FunctionKeyword,
+                ..
+            }))
+        );
+        self.next_token();
+        let mut id = self.parse_identifier()?;
+
+        // ISO 7185 leaves the heading out of the body of a forward declared routine
+        let forward_heading = self.analyzer.forward_heading(&id);
+        let short_heading = forward_heading.is_some()
+            && matches!(
+                self.current_token,
+                Some(Ok(Token {
+                    token: TokenType::Semicolon,
+                    ..
+                }))
+            );
+
+        let (mut params, result_type) = match (short_heading, forward_heading.as_ref()) {
+            (true, Some(heading)) => {
+                self.next_token();
+                heading.clone()
+            }
+            _ => match self.parse_procedure_header(is_function) {
+                Ok(header) => header,
+                Err(e) => {
+                    // The body still gets parsed, it may have errors of its own
+                    self.report(e);
+                    self.skip_until_block();
+                    (Vec::new(), None)
+                }
+            },
+        };
+
+        // A repeated heading names the same parameters as the forward declaration
+        if let Some((forward_params, _)) = &forward_heading {
+            for (p, f) in params.iter_mut().zip(forward_params.iter()) {
+                p.id.symbol = f.id.symbol;
+            }
+        }
+
+        let forward_directive = match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::Identifier(directive),
+                ..
+            })) if directive.eq_ignore_ascii_case("forward") => {
+                self.next_token();
+                true
+            }
+            _ => false,
+        };
+        // The interface of a unit declares its routines ahead of the implementation
+        let forward = forward_directive || self.interface;
+
+        // Declared before the body, so the procedure can call itself
+        if let Err(e) = self.analyzer.check_procedure_declaration(
+            &mut id,
+            &params,
+            result_type.as_ref(),
+            forward,
+        ) {
+            self.report(e);
+        }
+
+        self.analyzer.enter_scope(id.id.pos)?;
+        self.analyzer.enter_routine(id.symbol);
+        let procedure = match forward {
+            // Parameters still get symbols, for hover and renaming
+            true => {
+                let params = self.check_parameters(params);
+                self.analyzer
+                    .add_forward(&id, &params, result_type.as_ref(), self.interface);
+                Ok(ProcedureDeclaration::forward(id, params, result_type))
+            }
+            false => self
+                .parse_procedure_body(id, params, result_type)
+                .map(|p| ProcedureDeclaration { short_heading, ..p }),
+        };
+        self.analyzer.leave_routine();
+        self.analyzer.leave_scope(self.current_pos);
+        let procedure = procedure?;
+
+        // A bare interface heading ends with the semicolon of its parameters
+        if forward_directive || !self.interface {
+            if let Err(e) = self.parse_semicolon() {
+                self.report(e);
+            }
+        }
+
+        Ok(procedure)
+    }
+
+    fn parse_procedure_body(
+        &mut self,
+        id: Identifier,
+        params: Vec<FormalParameter>,
+        result_type: Option<Identifier>,
+    ) -> Result<ProcedureDeclaration, CompilerError> {
+        let checked = self.check_parameters(params);
+        let (const_section, type_section, var_section) = self.parse_sections()?;
+        let procedures = self.parse_procedures();
+        self.check_body_start("procedure");
+        let compound = self.parse_compound()?;
+
+        Ok(ProcedureDeclaration {
+            id,
+            params: checked,
+            result_type,
+            const_section,
+            type_section,
+            var_section,
+            procedures,
+            compound: Box::new(compound),
+            forward: false,
+            short_heading: false,
+        })
+    }
+
+    fn check_parameters(&mut self, params: Vec<FormalParameter>) -> Vec<FormalParameter> {
+        let mut checked = Vec::new();
+        for p in params {
+            match self.analyzer.check_parameter(p) {
+                Ok(p) => checked.push(p),
+                Err(e) => self.report(e),
+            }
+        }
+
+        checked
+    }
+
+    fn parse_procedure_header(
+        &mut self,
+        is_function: bool,
+    ) -> Result<(Vec<FormalParameter>, Option<Identifier>), CompilerError> {
+        let params = self.parse_formal_parameters()?;
+
+        let result_type = match is_function {
+            true => {
+                self.parse_colon()?;
+                let mut type_name = self.parse_identifier()?;
+
+                match self.analyzer.resolve_identifier(&mut type_name) {
+                    Ok(Usage::Type(_)) => (),
+                    Ok(_) => self.report(
+                        CompilerError::semantic("Identifier is not a type")
+                            .with_span(type_name.id.pos),
+                    ),
+                    Err(e) => self.report(e),
+                }
+
+                Some(type_name)
+            }
+            false => None,
+        };
+
+        self.parse_semicolon()?;
+        Ok((params, result_type))
+    }
+
+    fn parse_formal_parameters(&mut self) -> Result<Vec<FormalParameter>, CompilerError> {
+        // [( [var] id {, id} : type_id {; [var] id {, id} : type_id} )]
+        let mut params = Vec::new();
+
+        if let Some(Ok(Token {
+            token: TokenType::LBrace,
+            ..
+        })) = &self.current_token
+        {
+            self.next_token();
+
+            loop {
+                let by_reference = match &self.current_token {
+                    Some(Ok(Token {
+                        token: TokenType::VarKeyword,
+                        ..
+                    })) => {
+                        self.next_token();
+                        true
+                    }
+                    _ => false,
+                };
+
+                let mut ids = vec![self.parse_identifier()?];
+                while let Some(Ok(Token {
+                    token: TokenType::Comma,
+                    ..
+                })) = &self.current_token
+                {
+                    self.next_token();
+                    ids.push(self.parse_identifier()?);
+                }
+
+                self.parse_colon()?;
+                let mut type_name = self.parse_identifier()?;
+                // Parameters are still declared, so the body doesn't report them as unknown
+                match self.analyzer.resolve_identifier(&mut type_name) {
+                    Ok(Usage::Type(_)) => (),
+                    Ok(_) => self.report(
+                        CompilerError::semantic("Identifier is not a type")
+                            .with_span(type_name.id.pos),
+                    ),
+                    Err(e) => self.report(e),
+                }
+
+                for id in ids {
+                    params.push(FormalParameter {
+                        id,
+                        type_name: type_name.clone(),
+                        by_reference,
+                    });
+                }
+
+                match &self.current_token {
+                    Some(Ok(Token {
+                        token: TokenType::Semicolon,
+                        ..
+                    })) => self.next_token(),
+                    Some(Ok(Token {
+                        token: TokenType::RBrace,
+                        ..
+                    })) => {
+                        self.next_token();
+                        break;
+                    }
+                    Some(Ok(t)) => {
+                        return Err(CompilerError::syntax(format!(
+                            "Expected ';' or ')', found {:?}",
+                            t
+                        ))
+                        .with_span(t.pos))
+                    }
+                    Some(Err(e)) => return Err(e.clone()),
+                    None => {
+                        return Err(
+                            CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)
+                        )
+                    }
+                }
+            }
+        }
+
+        Ok(params)
+    }
+
+    /// Skip a broken declaration up to the next section of a block
+    fn skip_until_block(&mut self) {
+        loop {
+            match &self.current_token {
+                Some(Ok(Token {
+                    token:
+                        TokenType::ConstKeyword
+                        | TokenType::TypeKeyword
+                        | TokenType::VarKeyword
+                        | TokenType::ProcedureKeyword
+                        | TokenType::FunctionKeyword
+                        | TokenType::BeginKeyword,
+                    ..
+                }))
+                | None => return,
+                Some(Err(e)) => self.report(e.clone()),
+                Some(Ok(_)) => (),
+            }
+
+            self.next_token();
+        }
+    }
+
+    fn parse_call_statement(&mut self) -> Result<Call, CompilerError> {
+        let mut call = self.parse_call()?;
+
+        // Arguments are parsed already, so errors here don't need recovery
+        match self.analyzer.check_call(&mut call) {
+            Ok(Some(_)) => self.report(
+                CompilerError::semantic(format!(
+                    "Result of function {:?} is not used",
+                    call.name.get_id()
+                ))
+                .with_span(call.name.id.pos),
+            ),
+            Ok(None) => (),
+            Err(e) => self.report(e),
+        }
+
+        Ok(call)
+    }
+
+    fn parse_call(&mut self) -> Result<Call, CompilerError> {
+        // id [( expr {, expr} )]
+        let name = self.parse_identifier()?;
+        let mut arguments = Vec::new();
+        let mut formats = Vec::new();
+
+        if let Some(Ok(Token {
+            token: TokenType::LBrace,
+            ..
+        })) = &self.current_token
+        {
+            self.next_token();
+
+            loop {
+                arguments.push(self.parse_expr()?);
+
+                // expr:width[:decimals], the analyzer allows it for write and writeln
+                if let Some(Ok(Token {
+                    token: TokenType::Colon,
+                    ..
+                })) = &self.current_token
+                {
+                    formats.push((arguments.len() - 1, self.parse_write_format()?));
+                }
+
+                match &self.current_token {
+                    Some(Ok(Token {
+                        token: TokenType::Comma,
+                        ..
+                    })) => self.next_token(),
+                    Some(Ok(Token {
+                        token: TokenType::RBrace,
+                        ..
+                    })) => {
+                        self.next_token();
+                        break;
+                    }
+                    Some(Ok(t)) => {
+                        return Err(CompilerError::syntax(format!(
+                            "Expected ',' or ')', found {:?}",
+                            t
+                        ))
+                        .with_span(t.pos))
+                    }
+                    Some(Err(e)) => return Err(e.clone()),
+                    None => {
+                        return Err(
+                            CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)
+                        )
+                    }
+                }
+            }
+        }
+
+        Ok(Call {
+            name,
+            arguments,
+            formats,
+        })
+    }
+
+    fn parse_write_format(&mut self) -> Result<WriteFormat, CompilerError> {
+        self.parse_colon()?;
+        let width = self.parse_expr()?;
+
+        let decimals = match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::Colon,
+                ..
+            })) => {
+                self.next_token();
+                Some(self.parse_expr()?)
+            }
+            _ => None,
+        };
+
+        Ok(WriteFormat { width, decimals })
+    }
+
+    fn parse_program(&mut self) -> Result<Program, CompilerError> {
+        // program <identifier>;
+        // <consts>
+        // <types>
+        // <vars>
+        // <procedures>
+        // <compound>
+        // end.
+        self.analyzer.enter_scope(self.current_pos)?;
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::ProgramKeyword,
+                ..
+            })) => {
+                self.next_token();
+                let id = self.parse_identifier()?;
+                let id = self.analyzer.check_program_name(id)?;
+                let mut parameters = self.parse_program_parameters()?;
+                // Semicolon check
+                self.parse_semicolon()?;
+                let units = self.parse_uses()?;
+
+                let (const_section, type_section, var_section) = self.parse_sections()?;
+                self.check_program_parameters(&mut parameters);
+                let procedures = self.parse_procedures();
+                self.check_body_start("program");
+
+                let compound = Box::new(self.parse_compound()?);
+
+                let end = self.current_pos;
+                self.parse_period()?;
+                self.analyzer.leave_scope(end);
+
+                if let Some(Ok(t)) = &self.current_token {
+                    let pos = t.pos;
+                    self.extension("Text after the final '.'", pos);
+                }
+
+                Ok(Program {
+                    identifier: id,
+                    parameters,
+                    units,
+                    const_section,
                     var_section,
                     type_section,
+                    procedures,
                     compound,
+                    scope_tree: self.analyzer.take_scope_tree(),
+                    has_errors: self.errors.iter().any(|e| e.is_error()),
                 })
             }
-            Some(Ok(t)) => Err(CompilerError::syntax(
-                format!("Expected 'PROGRAM', found {:?}", t),
-                t.pos,
-            )),
-            Some(Err(e)) => Err(e.clone()),
-            _ => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
+            Some(Ok(t)) => Err(
+                CompilerError::syntax(format!("Expected 'PROGRAM', found {:?}", t))
+                    .with_span(t.pos),
+            ),
+            Some(Err(e)) => Err(e.clone()),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        }
+    }
+
+    fn parse_unit(&mut self) -> Result<Unit, CompilerError> {
+        // unit <identifier>;
+        // interface
+        // <uses> <consts> <types> <vars> <procedure headings>
+        // implementation
+        // <uses> <consts> <types> <vars> <procedures>
+        // [begin <statements>] end.
+        self.analyzer.enter_scope(self.current_pos)?;
+        self.extension("A unit", self.current_pos);
+        self.next_token();
+        let id = self.parse_identifier()?;
+        let id = self.analyzer.check_unit_name(id)?;
+        self.parse_semicolon()?;
+
+        self.parse_interface()?;
+        // Headings of the interface get their bodies in the implementation
+        let forwards = self.analyzer.forward_count();
+        self.interface = true;
+        let interface = self.parse_unit_part();
+        self.interface = false;
+        let interface = interface?;
+        let exports = self.analyzer.current_symbols();
+
+        self.parse_implementation()?;
+        let implementation = self.parse_unit_part()?;
+        self.report_unresolved_forwards(forwards);
+
+        let initialization = match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::BeginKeyword,
+                ..
+            })) => Some(Box::new(self.parse_compound()?)),
+            _ => {
+                self.parse_end()?;
+                None
+            }
+        };
+
+        let end = self.current_pos;
+        self.parse_period()?;
+        self.analyzer.leave_scope(end);
+
+        if let Some(Ok(t)) = &self.current_token {
+            let pos = t.pos;
+            self.extension("Text after the final '.'", pos);
+        }
+
+        Ok(Unit {
+            identifier: id,
+            interface,
+            implementation,
+            initialization,
+            exports,
+            scope_tree: self.analyzer.take_scope_tree(),
+            has_errors: self.errors.iter().any(|e| e.is_error()),
+        })
+    }
+
+    fn parse_unit_part(&mut self) -> Result<UnitPart, CompilerError> {
+        let units = self.parse_uses()?;
+        let (const_section, type_section, var_section) = self.parse_sections()?;
+        let procedures = self.parse_routines();
+
+        Ok(UnitPart {
+            units,
+            const_section,
+            type_section,
+            var_section,
+            procedures,
+        })
+    }
+
+    fn parse_interface(&mut self) -> Result<(), CompilerError> {
+        let tok = self.current_token.take();
+        self.next_token();
+
+        match tok {
+            Some(Ok(Token {
+                token: TokenType::InterfaceKeyword,
+                ..
+            })) => Ok(()),
+            Some(Ok(t)) => Err(CompilerError::syntax(format!(
+                "Expected 'interface', found {:?}",
+                t
+            ))
+            .with_span(t.pos)),
+            Some(Err(e)) => Err(e),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        }
+    }
+
+    fn parse_implementation(&mut self) -> Result<(), CompilerError> {
+        let tok = self.current_token.take();
+        self.next_token();
+
+        match tok {
+            Some(Ok(Token {
+                token: TokenType::ImplementationKeyword,
+                ..
+            })) => Ok(()),
+            Some(Ok(t)) => Err(CompilerError::syntax(format!(
+                "Expected 'implementation', found {:?}",
+                t
+            ))
+            .with_span(t.pos)),
+            Some(Err(e)) => Err(e),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        }
+    }
+
+    fn parse_end(&mut self) -> Result<(), CompilerError> {
+        let tok = self.current_token.take();
+        self.next_token();
+
+        match tok {
+            Some(Ok(Token {
+                token: TokenType::EndKeyword,
+                ..
+            })) => Ok(()),
+            Some(Ok(t)) => Err(
+                CompilerError::syntax(format!("Expected 'end', found {:?}", t)).with_span(t.pos),
+            ),
+            Some(Err(e)) => Err(e),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        }
+    }
+
+    fn parse_program_parameters(&mut self) -> Result<Vec<Identifier>, CompilerError> {
+        // [( id {, id} )]
+        let mut parameters = Vec::new();
+
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::LBrace,
+                ..
+            })) => self.next_token(),
+            _ => {
+                self.extension("A program heading without parameters", self.current_pos);
+                return Ok(parameters);
+            }
+        }
+
+        loop {
+            parameters.push(self.parse_identifier()?);
+
+            match &self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::RBrace,
+                    ..
+                })) => {
+                    self.next_token();
+                    return Ok(parameters);
+                }
+                _ => self.parse_comma()?,
+            }
+        }
+    }
+
+    fn parse_uses(&mut self) -> Result<Vec<Identifier>, CompilerError> {
+        // [uses id {, id} ;]
+        let mut units = Vec::new();
+
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::UsesKeyword,
+                pos,
+            })) => {
+                let pos = *pos;
+                self.next_token();
+                self.extension("A uses clause", pos);
+            }
+            _ => return Ok(units),
+        }
+
+        loop {
+            let unit = self.parse_identifier()?;
+            if let Err(e) = self.analyzer.use_unit(&unit) {
+                self.report(e);
+            }
+            units.push(unit);
+
+            match &self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::Comma,
+                    ..
+                })) => self.next_token(),
+                _ => {
+                    self.parse_semicolon()?;
+                    return Ok(units);
+                }
+            }
+        }
+    }
+
+    // Parameters other than the standard files name variables of the program
+    fn check_program_parameters(&mut self, parameters: &mut [Identifier]) {
+        for param in parameters.iter_mut() {
+            if matches!(param.get_id().as_str(), "input" | "output") {
+                continue;
+            }
+
+            let is_variable = matches!(
+                self.analyzer.resolve_identifier(param),
+                Ok(Usage::Variable(_))
+            );
+            if !is_variable {
+                self.report(
+                    CompilerError::semantic(format!(
+                        "Program parameter {:?} must be a variable of the program",
+                        param.get_id()
+                    ))
+                    .with_span(param.id.pos),
+                );
+            }
+        }
+    }
+
+    fn parse_additive_op(&mut self) -> Result<AdditiveOp, CompilerError> {
+        let op = match self.current_token.take() {
+            Some(Ok(t)) => match t {
+                Token {
+                    token: TokenType::PlusOp,
+                    ..
+                } => {
+                    self.next_token();
+                    Ok(AdditiveOp::Plus)
+                }
+                Token {
+                    token: TokenType::MinusOp,
+                    ..
+                } => {
+                    self.next_token();
+                    Ok(AdditiveOp::Minus)
+                }
+                Token {
+                    token: TokenType::OrOp,
+                    ..
+                } => {
+                    self.next_token();
+                    Ok(AdditiveOp::Or)
+                }
+                tok => Err(CompilerError::syntax(format!(
+                    "Expected additive operator, found {:?}",
+                    tok
+                ))
+                .with_span(tok.pos)),
+            },
+            Some(Err(e)) => Err(e),
+            None => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        };
+
+        op
+    }
+
+    fn parse_sub_expr(&mut self) -> Result<Option<SubExpression>, CompilerError> {
+        // Read in a loop and built from the end, like the factors of a term
+        let mut operands = Vec::new();
+        while let Some(operand) = self.parse_add_operand()? {
+            operands.push(operand);
+        }
+
+        let mut sub_expr: Option<SubExpression> = None;
+        for (op, term) in operands.into_iter().rev() {
+            let term_type = &term.term_type;
+            let op_text = match *op {
+                AdditiveOp::Plus => "+",
+                AdditiveOp::Minus => "-",
+                AdditiveOp::Or => "or",
+            };
+            self.analyzer
+                .check_operator(op_text, term_type, term.factor.pos())?;
+            let sub_expr_type = match &sub_expr {
+                Some(s) => s.sub_expr_type.clone(),
+                None => String::new(),
+            };
+
+            let sub_expr_type =
+                self.analyzer
+                    .merge_types(term_type, &sub_expr_type, self.current_pos, false)?;
+
+            sub_expr = Some(SubExpression {
+                op,
+                term,
+                sub_expr: sub_expr.map(Box::new),
+                sub_expr_type,
+            });
+        }
+
+        Ok(sub_expr)
+    }
+
+    // Next `+ term` of a simple expression, None where the expression ends
+    fn parse_add_operand(&mut self) -> Result<Option<AddOperand>, CompilerError> {
+        match &self.current_token {
+            Some(Ok(t)) if t.is_expression_end() || t.is_rel_op() => Ok(None),
+            Some(Ok(t)) if self.compares_by_mistake(t) => Ok(None),
+            Some(Ok(t)) if self.keyword_due && t.starts_statement() => Ok(None),
+            None if self.fragment => Ok(None),
+            Some(Ok(t)) if t.is_add_op() => {
+                let op = Box::new(self.parse_additive_op()?);
+                let term = Box::new(self.parse_term()?);
+
+                Ok(Some((op, term)))
+            }
+            Some(Ok(t)) => Err(CompilerError::syntax(format!(
+                "Expected +, - or statement end, found {:?}",
+                t
+            ))
+            .with_span(t.pos)),
+            Some(Err(e)) => Err(e.clone()),
+            None => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        }
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement, CompilerError> {
+        let statement = self.nested("Statement", |p| p.parse_statement_at_depth());
+
+        // Too deep to be parsed, the innermost statement which failed skips what is
+        // left of it, so the list around it goes on after its end
+        if statement.is_err() && mem::take(&mut self.too_deep) {
+            self.skip_statement();
+        }
+        statement
+    }
+
+    // Passes a statement up to the ';', END, UNTIL or ELSE which follows it, skipping
+    // whatever is nested inside
+    fn skip_statement(&mut self) {
+        let mut open = 0;
+
+        loop {
+            match &self.current_token {
+                Some(Ok(t)) => match t.token {
+                    TokenType::BeginKeyword | TokenType::CaseKeyword | TokenType::RepeatKeyword => {
+                        open += 1
+                    }
+                    TokenType::EndKeyword | TokenType::UntilKeyword if open > 0 => open -= 1,
+                    TokenType::Semicolon
+                    | TokenType::EndKeyword
+                    | TokenType::UntilKeyword
+                    | TokenType::ElseKeyword
+                        if open == 0 =>
+                    {
+                        return
+                    }
+                    _ => (),
+                },
+                Some(Err(e)) => self.report(e.clone()),
+                None => return,
+            }
+            self.next_token();
+        }
+    }
+
+    fn parse_statement_at_depth(&mut self) -> Result<Statement, CompilerError> {
+        match &self.current_token {
+            Some(Ok(
+                token @ Token {
+                    token: TokenType::Identifier(_),
+                    ..
+                },
+            )) => {
+                let id = Identifier::new(token.clone());
+                let next = self.peek_token().and_then(|t| t.as_ref().ok());
+                // `x = 5` is taken for the assignment it was meant to be
+                let assignment = matches!(
+                    next.map(|t| &t.token),
+                    Some(TokenType::AssignOp | TokenType::Eq)
+                );
+                let arguments = matches!(next.map(|t| &t.token), Some(TokenType::LBrace));
+
+                match self.analyzer.find_identifier(&id) {
+                    Ok(Usage::Procedure(_) | Usage::Function(_)) if !assignment => {
+                        Ok(Statement::Call(self.parse_call_statement()?))
+                    }
+                    // Reported as not being a procedure
+                    _ if arguments => Ok(Statement::Call(self.parse_call_statement()?)),
+                    _ => Ok(Statement::Simple(self.parse_assignment()?)),
+                }
+            }
+            Some(Ok(Token {
+                token: TokenType::IfKeyword,
+                ..
+            })) => Ok(Statement::Cond(self.parse_conditional()?)),
+            Some(Ok(Token {
+                token: TokenType::WhileKeyword,
+                ..
+            })) => Ok(Statement::While(self.parse_while_loop()?)),
+            Some(Ok(Token {
+                token: TokenType::RepeatKeyword,
+                ..
+            })) => Ok(Statement::Repeat(self.parse_repeat_loop()?)),
+            Some(Ok(Token {
+                token: TokenType::ForKeyword,
+                ..
+            })) => Ok(Statement::For(self.parse_for_loop()?)),
+            Some(Ok(Token {
+                token: TokenType::CaseKeyword,
+                ..
+            })) => Ok(Statement::Case(self.parse_case()?)),
+            Some(Ok(Token {
+                token: TokenType::BeginKeyword,
+                ..
+            })) => Ok(Statement::Compound(self.parse_compound()?)),
+            // Empty statement, e.g. `if a then else b := 1` or `while a do;`
+            Some(Ok(Token {
+                token:
+                    TokenType::Semicolon
+                    | TokenType::EndKeyword
+                    | TokenType::ElseKeyword
+                    | TokenType::UntilKeyword,
+                ..
+            })) => Ok(Statement::Empty),
+            _ => Err(CompilerError::syntax("Illegal statement").with_span(self.current_pos)),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expression, CompilerError> {
+        // Expr ::= <Simple Expr> | <Simple Expr> <Rel Op> <Simple Expr>
+        let first = Box::new(self.parse_simple_expr()?);
+
+        // Common mistake: `if x := 1 then`, compared as meant
+        if let Some(Ok(t)) = &self.current_token {
+            if self.compares_by_mistake(t) {
+                let pos = t.pos;
+                self.report(
+                    CompilerError::syntax("Expected '=' to compare, found ':='")
+                        .with_span(pos)
+                        .with_note("':=' assigns a value, '=' compares two"),
+                );
+                self.current_token = Some(Ok(Token::new(TokenType::Eq, pos)));
+            }
+        }
+
+        match &self.current_token {
+            Some(Ok(token)) if token.is_rel_op() => {
+                let pos = token.pos;
+                let op = Box::new(self.parse_relational_op()?);
+                let second = Box::new(self.parse_simple_expr()?);
+                self.analyzer
+                    .check_comparison(&first.expr_type, &op, &second.expr_type, pos)?;
+
+                Ok(Expression::Relational(Box::new(RelationalExpression {
+                    first,
+                    op,
+                    second,
+                })))
+            }
+            _ => Ok(Expression::Simple(first)),
+        }
+    }
+
+    fn parse_if(&mut self) -> Result<(), CompilerError> {
+        let tok = self.current_token.take();
+        self.next_token();
+
+        match tok {
+            Some(Ok(Token {
+                token: TokenType::IfKeyword,
+                ..
+            })) => Ok(()),
+            Some(Ok(t)) => {
+                Err(CompilerError::syntax(format!("Expected 'if', found {:?}", t)).with_span(t.pos))
+            }
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        }
+    }
+
+    /// Condition or bound which THEN or DO follows
+    fn parse_expr_before_keyword(&mut self) -> Result<Expression, CompilerError> {
+        let outer = mem::replace(&mut self.keyword_due, true);
+        let expr = self.parse_expr();
+        self.keyword_due = outer;
+        expr
+    }
+
+    /// Condition of IF or WHILE (`before_keyword`) or of UNTIL
+    fn parse_condition(&mut self, before_keyword: bool) -> Result<Expression, CompilerError> {
+        let outer = mem::replace(&mut self.condition, true);
+        let expr = match before_keyword {
+            true => self.parse_expr_before_keyword(),
+            false => self.parse_expr(),
+        };
+        self.condition = outer;
+        expr
+    }
+
+    // ':=' in a condition, which can only be meant as '='
+    fn compares_by_mistake(&self, t: &Token) -> bool {
+        self.condition && matches!(t.token, TokenType::AssignOp)
+    }
+
+    // A statement where THEN or DO should be means the keyword was left out. That is
+    // reported and parsing goes on as if it were there, so the statement still gets
+    // checked and the error doesn't cascade.
+    fn keyword_left_out(&mut self, keyword: &str) -> bool {
+        match &self.current_token {
+            Some(Ok(t)) if t.starts_statement() => {
+                let error = CompilerError::syntax(format!(
+                    "Expected '{}' before the statement, found {:?}",
+                    keyword, t
+                ))
+                .with_span(t.pos);
+                self.report(error);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_then(&mut self) -> Result<(), CompilerError> {
+        if self.keyword_left_out("then") {
+            return Ok(());
+        }
+
+        let tok = self.current_token.take();
+        self.next_token();
+
+        match tok {
+            Some(Ok(Token {
+                token: TokenType::ThenKeyword,
+                ..
+            })) => Ok(()),
+            Some(Ok(t)) => Err(
+                CompilerError::syntax(format!("Expected 'Then', found {:?}", t)).with_span(t.pos),
+            ),
+            Some(Err(e)) => Err(e),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        }
+    }
+
+    fn parse_else(&mut self) -> Result<Option<()>, CompilerError> {
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::ElseKeyword,
+                ..
+            })) => {
+                self.next_token();
+                Ok(Some(()))
+            }
+            Some(Err(e)) => Err(e.clone()),
+            _ => Ok(None),
+        }
+    }
+
+    fn parse_conditional(&mut self) -> Result<IfStatement, CompilerError> {
+        self.parse_if()?;
+        let condition = Box::new(self.parse_condition(true)?);
+        self.analyzer.check_condition(&condition, "if")?;
+        self.parse_then()?;
+        let statement = self.parse_statement()?;
+        let else_ = self.parse_else()?;
+
+        let else_statement = if else_.is_some() {
+            Some(Box::new(self.parse_statement()?))
+        } else {
+            None
+        };
+
+        Ok(IfStatement {
+            condition,
+            statement: Box::new(statement),
+            else_statement,
+        })
+    }
+
+    fn parse_while(&mut self) -> Result<(), CompilerError> {
+        let tok = self.current_token.take();
+        self.next_token();
+
+        match tok {
+            Some(Ok(Token {
+                token: TokenType::WhileKeyword,
+                ..
+            })) => Ok(()),
+            Some(Ok(t)) => Err(
+                CompilerError::syntax(format!("Expected 'While', found {:?}", t)).with_span(t.pos),
+            ),
+            Some(Err(e)) => Err(e),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        }
+    }
+
+    fn parse_do(&mut self) -> Result<(), CompilerError> {
+        if self.keyword_left_out("do") {
+            return Ok(());
+        }
+
+        let tok = self.current_token.take();
+        self.next_token();
+
+        match tok {
+            Some(Ok(Token {
+                token: TokenType::DoKeyword,
+                ..
+            })) => Ok(()),
+            Some(Ok(t)) => {
+                Err(CompilerError::syntax(format!("Expected 'Do', found {:?}", t)).with_span(t.pos))
+            }
+            Some(Err(e)) => Err(e),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        }
+    }
+
+    fn parse_while_loop(&mut self) -> Result<WhileLoop, CompilerError> {
+        self.parse_while()?;
+        let expr = self.parse_condition(true)?;
+        self.analyzer.check_condition(&expr, "while")?;
+        self.parse_do()?;
+        let statement = self.parse_statement()?;
+
+        Ok(WhileLoop {
+            condition: Box::new(expr),
+            statement: Box::new(statement),
+        })
+    }
+
+    fn parse_for_loop(&mut self) -> Result<ForStatement, CompilerError> {
+        // Consume FOR
+        self.next_token();
+        let mut variable = self.parse_identifier()?;
+
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::AssignOp,
+                ..
+            })) => self.next_token(),
+            Some(Ok(t)) => {
+                return Err(
+                    CompilerError::syntax(format!("Expected :=, found {:?}", t)).with_span(t.pos)
+                )
+            }
+            Some(Err(e)) => return Err(e.clone()),
+            None => {
+                return Err(
+                    CompilerError::syntax("Expected :=, found EOF").with_span(self.current_pos)
+                )
+            }
+        }
+
+        let start = self.parse_expr()?;
+
+        let downto = match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::ToKeyword,
+                ..
+            })) => false,
+            Some(Ok(Token {
+                token: TokenType::DowntoKeyword,
+                ..
+            })) => true,
+            Some(Ok(t)) => {
+                return Err(CompilerError::syntax(format!(
+                    "Expected 'to' or 'downto', found {:?}",
+                    t
+                ))
+                .with_span(t.pos))
+            }
+            Some(Err(e)) => return Err(e.clone()),
+            None => return Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        };
+        self.next_token();
+
+        let end = self.parse_expr_before_keyword()?;
+        self.analyzer
+            .check_for_header(&mut variable, &start, &end)?;
+        self.parse_do()?;
+        self.analyzer.enter_for_loop(&variable);
+        let statement = self.parse_statement();
+        self.analyzer.leave_for_loop();
+        let statement = statement?;
+
+        Ok(ForStatement {
+            variable,
+            start: Box::new(start),
+            end: Box::new(end),
+            downto,
+            statement: Box::new(statement),
+        })
+    }
+
+    // A subrange starts with a constant, a name is one if `..` follows
+    fn at_subrange(&mut self) -> bool {
+        match &self.current_token {
+            Some(Ok(Token {
+                token:
+                    TokenType::Integer(_)
+                    | TokenType::HexInteger(_)
+                    | TokenType::Real(_)
+                    | TokenType::StringLiteral(_)
+                    | TokenType::CharCode(_)
+                    | TokenType::PlusOp
+                    | TokenType::MinusOp,
+                ..
+            })) => true,
+            Some(Ok(Token {
+                token: TokenType::Identifier(_),
+                ..
+            })) => matches!(
+                self.peek_token(),
+                Some(Ok(Token {
+                    token: TokenType::DotDot,
+                    ..
+                }))
+            ),
+            _ => false,
+        }
+    }
+
+    fn parse_subrange_type(&mut self) -> Result<TypeSpec, CompilerError> {
+        let low = self.parse_simple_expr()?;
+
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::DotDot,
+                ..
+            })) => self.next_token(),
+            Some(Ok(t)) => {
+                return Err(
+                    CompilerError::syntax(format!("Expected '..', found {:?}", t)).with_span(t.pos),
+                )
+            }
+            Some(Err(e)) => return Err(e.clone()),
+            None => return Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        }
+
+        Ok(TypeSpec::Subrange(Box::new(SubrangeType {
+            low,
+            high: self.parse_simple_expr()?,
+            type_name: String::new(),
+        })))
+    }
+
+    fn parse_type_spec(&mut self) -> Result<TypeSpec, CompilerError> {
+        let outermost = self.expr_depth == 0;
+        let spec = self.nested("Type", |p| p.parse_type_spec_at_depth());
+
+        if outermost {
+            if spec.is_err() && mem::take(&mut self.too_deep) {
+                self.skip_type();
+            }
+            self.open_types = 0;
+        }
+        spec
+    }
+
+    // Passes the rest of a type up to the ';' which ends its declaration, the
+    // records and variants still open are closed on the way
+    fn skip_type(&mut self) {
+        let mut open = self.open_types;
+
+        loop {
+            match &self.current_token {
+                Some(Ok(t)) => match t.token {
+                    TokenType::RecordKeyword | TokenType::LBrace => open += 1,
+                    TokenType::EndKeyword | TokenType::RBrace if open > 0 => open -= 1,
+                    TokenType::Semicolon if open == 0 => return,
+                    // Anything else that closes is outside of the type
+                    TokenType::EndKeyword | TokenType::RBrace | TokenType::BeginKeyword => return,
+                    _ => (),
+                },
+                Some(Err(e)) => self.report(e.clone()),
+                None => return,
+            }
+            self.next_token();
+        }
+    }
+
+    fn parse_type_spec_at_depth(&mut self) -> Result<TypeSpec, CompilerError> {
+        // type_id | array [ const .. const {, const .. const} ] of type | record | set of type
+        // | ( id {, id} ) | ^ type_id | string [ const ] | const .. const
+        if self.at_subrange() {
+            return self.parse_subrange_type();
+        }
+
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::Caret,
+                ..
+            })) => {
+                self.next_token();
+
+                return Ok(TypeSpec::Pointer(PointerType {
+                    target: self.parse_identifier()?,
+                    type_name: String::new(),
+                }));
+            }
+            Some(Ok(Token {
+                token: TokenType::ArrayKeyword,
+                ..
+            })) => self.next_token(),
+            Some(Ok(Token {
+                token: TokenType::RecordKeyword,
+                ..
+            })) => return self.parse_record_type(),
+            Some(Ok(Token {
+                token: TokenType::LBrace,
+                ..
+            })) => return self.parse_enum_type(),
+            Some(Ok(Token {
+                token: TokenType::SetKeyword,
+                pos,
+            })) => {
+                let pos = *pos;
+                self.next_token();
+                self.parse_of()?;
+
+                return Ok(TypeSpec::Set(Box::new(SetType {
+                    element: self.parse_type_spec()?,
+                    type_name: String::new(),
+                    pos,
+                })));
+            }
+            _ => {
+                let name = self.parse_identifier()?;
+
+                // Only the string type takes a length, the analyzer checks the name
+                return match &self.current_token {
+                    Some(Ok(Token {
+                        token: TokenType::LBracket,
+                        ..
+                    })) => {
+                        self.next_token();
+                        let length = self.parse_simple_expr()?;
+
+                        match &self.current_token {
+                            Some(Ok(Token {
+                                token: TokenType::RBracket,
+                                ..
+                            })) => self.next_token(),
+                            Some(Ok(t)) => {
+                                return Err(CompilerError::syntax(format!(
+                                    "Expected ']', found {:?}",
+                                    t
+                                ))
+                                .with_span(t.pos))
+                            }
+                            Some(Err(e)) => return Err(e.clone()),
+                            None => {
+                                return Err(CompilerError::syntax("Unexpected EOF")
+                                    .with_span(self.current_pos))
+                            }
+                        }
+
+                        Ok(TypeSpec::String(Box::new(StringType {
+                            name,
+                            length,
+                            type_name: String::new(),
+                        })))
+                    }
+                    _ => Ok(TypeSpec::Named(name)),
+                };
+            }
+        }
+
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::LBracket,
+                ..
+            })) => self.next_token(),
+            Some(Ok(t)) => {
+                return Err(
+                    CompilerError::syntax(format!("Expected '[', found {:?}", t)).with_span(t.pos),
+                )
+            }
+            Some(Err(e)) => return Err(e.clone()),
+            None => return Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        }
+
+        let mut indexes = Vec::new();
+
+        loop {
+            indexes.push(self.parse_index_type()?);
+
+            match &self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::Comma,
+                    ..
+                })) => self.next_token(),
+                Some(Ok(Token {
+                    token: TokenType::RBracket,
+                    ..
+                })) => {
+                    self.next_token();
+                    break;
+                }
+                Some(Ok(t)) => {
+                    return Err(CompilerError::syntax(format!(
+                        "Expected ',' or ']', found {:?}",
+                        t
+                    ))
+                    .with_span(t.pos))
+                }
+                Some(Err(e)) => return Err(e.clone()),
+                None => {
+                    return Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos))
+                }
+            }
+        }
+
+        self.parse_of()?;
+        let mut spec = self.parse_type_spec()?;
+
+        // Last index is the innermost array
+        for index in indexes.into_iter().rev() {
+            spec = TypeSpec::Array(Box::new(ArrayType {
+                index,
+                element: spec,
+                type_name: String::new(),
+            }));
+        }
+
+        Ok(spec)
+    }
+
+    // Index of an array: a type name, an enumeration or `low..high`
+    fn parse_index_type(&mut self) -> Result<TypeSpec, CompilerError> {
+        let named = matches!(
+            self.current_token,
+            Some(Ok(Token {
+                token: TokenType::Identifier(_),
+                ..
+            }))
+        ) && matches!(
+            self.peek_token(),
+            Some(Ok(Token {
+                token: TokenType::Comma | TokenType::RBracket,
+                ..
+            }))
+        );
+
+        match &self.current_token {
+            _ if named => Ok(TypeSpec::Named(self.parse_identifier()?)),
+            Some(Ok(Token {
+                token: TokenType::LBrace,
+                ..
+            })) => self.parse_enum_type(),
+            _ => self.parse_subrange_type(),
+        }
+    }
+
+    fn parse_record_type(&mut self) -> Result<TypeSpec, CompilerError> {
+        // record field-list end
+        let pos = self.current_pos;
+        self.next_token();
+        self.open_types += 1;
+        let (fields, variant) = self.parse_field_list()?;
+
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::EndKeyword,
+                ..
+            })) => {
+                self.open_types -= 1;
+                self.next_token()
+            }
+            Some(Ok(t)) => {
+                return Err(
+                    CompilerError::syntax(format!("Expected 'end', found {:?}", t))
+                        .with_span(t.pos),
+                )
+            }
+            Some(Err(e)) => return Err(e.clone()),
+            None => return Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
+        }
+
+        Ok(TypeSpec::Record(RecordType {
+            fields,
+            variant,
+            type_name: String::new(),
+            pos,
+        }))
+    }
+
+    /// Fields of a record or a variant, up to its END or `)` (not consumed)
+    fn parse_field_list(
+        &mut self,
+    ) -> Result<(Vec<FieldDeclaration>, Option<VariantPart>), CompilerError> {
+        // [id {, id} : type {; id {, id} : type}] [; variant-part] [;]
+        let mut fields = Vec::new();
+
+        while let Some(Ok(Token {
+            token: TokenType::Identifier(_),
+            ..
+        })) = &self.current_token
+        {
+            let mut ids = vec![self.parse_identifier()?];
+
+            while let Some(Ok(Token {
+                token: TokenType::Comma,
+                ..
+            })) = &self.current_token
+            {
+                self.next_token();
+                ids.push(self.parse_identifier()?);
+            }
+
+            self.parse_colon()?;
+            let type_name = self.parse_type_spec()?;
+
+            for id in ids {
+                fields.push(FieldDeclaration {
+                    id,
+                    type_name: type_name.clone(),
+                });
+            }
+
+            match &self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::Semicolon,
+                    ..
+                })) => self.next_token(),
+                _ => return Ok((fields, None)),
+            }
+        }
+
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::CaseKeyword,
+                ..
+            })) => Ok((
+                fields,
+                Some(self.nested("Type", |p| p.parse_variant_part())?),
             )),
+            _ => Ok((fields, None)),
         }
     }
 
-    fn parse_additive_op(&mut self) -> Result<AdditiveOp, CompilerError> {
-        let op = match self.current_token.take() {
-            Some(Ok(t)) => match t {
-                Token {
-                    token: TokenType::PlusOp,
+    fn parse_variant_part(&mut self) -> Result<VariantPart, CompilerError> {
+        // case [id :] type of labels : ( field-list ) {; labels : ( field-list )} [;]
+        let pos = self.current_pos;
+        self.next_token();
+
+        let mut tag = None;
+        let mut tag_type = self.parse_identifier()?;
+        if let Some(Ok(Token {
+            token: TokenType::Colon,
+            ..
+        })) = &self.current_token
+        {
+            self.next_token();
+            tag = Some(mem::replace(&mut tag_type, self.parse_identifier()?));
+        }
+
+        // Labels are still parsed when the tag type is wrong
+        let tag_name = self
+            .analyzer
+            .check_variant_tag(&mut tag_type)
+            .unwrap_or_else(|e| {
+                self.report(e);
+                String::new()
+            });
+        self.parse_of()?;
+
+        let mut variants = Vec::new();
+        // Values of the labels so far, to catch duplicates
+        let mut seen = Vec::new();
+
+        loop {
+            let labels = self.parse_case_labels(&tag_name, &mut seen)?;
+            self.parse_colon()?;
+            self.parse_lbrace()?;
+            self.open_types += 1;
+            let (fields, variant) = self.parse_field_list()?;
+            self.parse_rbrace()?;
+            self.open_types -= 1;
+
+            variants.push(Variant {
+                labels,
+                fields,
+                variant: variant.map(Box::new),
+            });
+
+            match &self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::Semicolon,
                     ..
-                } => {
+                })) => self.next_token(),
+                _ => break,
+            }
+
+            // The last variant may be followed by `;`
+            if let Some(Ok(Token {
+                token: TokenType::EndKeyword | TokenType::RBrace,
+                ..
+            })) = &self.current_token
+            {
+                break;
+            }
+        }
+
+        Ok(VariantPart {
+            tag,
+            tag_type,
+            variants,
+            pos,
+        })
+    }
+
+    fn parse_enum_type(&mut self) -> Result<TypeSpec, CompilerError> {
+        // ( id {, id} )
+        let pos = self.current_pos;
+        self.next_token();
+        let mut values = Vec::new();
+
+        loop {
+            values.push(self.parse_identifier()?);
+
+            match &self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::Comma,
+                    ..
+                })) => self.next_token(),
+                Some(Ok(Token {
+                    token: TokenType::RBrace,
+                    ..
+                })) => {
                     self.next_token();
-                    Ok(AdditiveOp::Plus)
+                    break;
                 }
-                Token {
-                    token: TokenType::MinusOp,
+                Some(Ok(t)) => {
+                    return Err(CompilerError::syntax(format!(
+                        "Expected ',' or ')', found {:?}",
+                        t
+                    ))
+                    .with_span(t.pos))
+                }
+                Some(Err(e)) => return Err(e.clone()),
+                None => {
+                    return Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos))
+                }
+            }
+        }
+
+        Ok(TypeSpec::Enum(EnumType {
+            values,
+            type_name: String::new(),
+            pos,
+        }))
+    }
+
+    fn parse_selectors(&mut self) -> Result<Vec<Selector>, CompilerError> {
+        // { [ expr {, expr} ] | . id }
+        let mut selectors = Vec::new();
+
+        loop {
+            match &self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::Period,
                     ..
-                } => {
+                })) => {
                     self.next_token();
-                    Ok(AdditiveOp::Minus)
+                    selectors.push(Selector::Field(self.parse_identifier()?));
+                    continue;
                 }
-                Token {
-                    token: TokenType::OrOp,
-                    ..
-                } => {
+                Some(Ok(Token {
+                    token: TokenType::Caret,
+                    pos,
+                })) => {
+                    selectors.push(Selector::Deref(*pos));
                     self.next_token();
-                    Ok(AdditiveOp::Or)
+                    continue;
                 }
-                tok => Err(CompilerError::syntax(
-                    format!("Expected additive operator, found {:?}", tok),
-                    tok.pos,
-                )),
-            },
-            Some(Err(e)) => Err(e),
-            None => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
-            )),
-        };
+                Some(Ok(Token {
+                    token: TokenType::LBracket,
+                    ..
+                })) => self.next_token(),
+                _ => return Ok(selectors),
+            }
 
-        op
+            loop {
+                selectors.push(Selector::Index(self.parse_expr()?));
+
+                match &self.current_token {
+                    Some(Ok(Token {
+                        token: TokenType::Comma,
+                        ..
+                    })) => self.next_token(),
+                    Some(Ok(Token {
+                        token: TokenType::RBracket,
+                        ..
+                    })) => {
+                        self.next_token();
+                        break;
+                    }
+                    Some(Ok(t)) => {
+                        return Err(CompilerError::syntax(format!(
+                            "Expected ',' or ']', found {:?}",
+                            t
+                        ))
+                        .with_span(t.pos))
+                    }
+                    Some(Err(e)) => return Err(e.clone()),
+                    None => {
+                        return Err(
+                            CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)
+                        )
+                    }
+                }
+            }
+        }
     }
 
-    fn parse_sub_expr(&mut self) -> Result<Option<SubExpression>, CompilerError> {
-        match &self.current_token {
-            Some(Ok(t)) if t.is_expression_end() || t.is_rel_op() => Ok(None),
-            Some(Ok(t)) if t.is_add_op() => {
-                let op = Box::new(self.parse_additive_op()?);
-                let term = Box::new(self.parse_term()?);
-                let sub_expr_res = self.parse_sub_expr()?;
-                let sub_expr = sub_expr_res.map(Box::new);
+    // Current token is the opening bracket, the closing one is left for the caller
+    fn parse_set_constructor(&mut self) -> Result<SetConstructor, CompilerError> {
+        // [ [ expr [.. expr] {, expr [.. expr]} ] ]
+        let pos = self.current_pos;
+        self.next_token();
+        let mut elements = Vec::new();
 
-                let term_type = &term.term_type;
-                let sub_expr_type;
+        if let Some(Ok(Token {
+            token: TokenType::RBracket,
+            ..
+        })) = &self.current_token
+        {
+            return Ok(SetConstructor {
+                elements,
+                set_type: String::new(),
+                pos,
+            });
+        }
 
-                if sub_expr.is_some() {
-                    sub_expr_type = sub_expr.as_ref().unwrap().sub_expr_type.clone();
-                } else {
-                    sub_expr_type = String::new();
+        loop {
+            let low = self.parse_expr()?;
+            let high = match &self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::DotDot,
+                    ..
+                })) => {
+                    self.next_token();
+                    Some(self.parse_expr()?)
                 }
+                _ => None,
+            };
+            elements.push(SetElement { low, high });
 
-                let sub_expr_type = self.analyzer.merge_types(
-                    term_type,
-                    &sub_expr_type,
-                    self.current_pos,
-                    false,
-                )?;
-
-                Ok(Some(SubExpression {
-                    op,
-                    term,
-                    sub_expr,
-                    sub_expr_type,
-                }))
+            match &self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::Comma,
+                    ..
+                })) => self.next_token(),
+                Some(Ok(Token {
+                    token: TokenType::RBracket,
+                    ..
+                })) => break,
+                Some(Ok(t)) => {
+                    return Err(CompilerError::syntax(format!(
+                        "Expected ',', '..' or ']', found {:?}",
+                        t
+                    ))
+                    .with_span(t.pos))
+                }
+                Some(Err(e)) => return Err(e.clone()),
+                None => {
+                    return Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos))
+                }
             }
-            Some(Ok(t)) => Err(CompilerError::syntax(
-                format!("Expected +, - or statement end, found {:?}", t),
-                t.pos,
-            )),
-            Some(Err(e)) => Err(e.clone()),
-            None => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
-            )),
         }
+
+        Ok(SetConstructor {
+            elements,
+            set_type: String::new(),
+            pos,
+        })
     }
 
-    fn parse_statement(&mut self) -> Result<Statement, CompilerError> {
-        match &self.current_token {
-            Some(Ok(Token {
-                token: TokenType::Identifier(_),
-                ..
-            })) => Ok(Statement::Simple(self.parse_assignment()?)),
-            Some(Ok(Token {
-                token: TokenType::IfKeyword,
-                ..
-            })) => Ok(Statement::Cond(self.parse_conditional()?)),
+    fn parse_of(&mut self) -> Result<(), CompilerError> {
+        let tok = self.current_token.take();
+        self.next_token();
+
+        match tok {
             Some(Ok(Token {
-                token: TokenType::WhileKeyword,
+                token: TokenType::OfKeyword,
                 ..
-            })) => Ok(Statement::While(self.parse_while_loop()?)),
-            _ => Err(CompilerError::syntax(
-                "Illegal statement".into(),
-                self.current_pos,
-            )),
+            })) => Ok(()),
+            Some(Ok(t)) => {
+                Err(CompilerError::syntax(format!("Expected 'of', found {:?}", t)).with_span(t.pos))
+            }
+            Some(Err(e)) => Err(e),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
         }
     }
 
-    fn parse_expr(&mut self) -> Result<Expression, CompilerError> {
-        // Expr ::= <Simple Expr> | <Simple Expr> <Rel Op> <Simple Expr>
-        let first = Box::new(self.parse_simple_expr()?);
-
-        match &self.current_token {
-            Some(Ok(token)) if token.is_rel_op() => {
-                let op = Box::new(self.parse_relational_op()?);
-                let second = Box::new(self.parse_simple_expr()?);
-                self.analyzer.merge_types(
-                    &first.expr_type,
-                    &second.expr_type,
-                    self.current_pos,
-                    true,
-                )?;
+    fn parse_lbrace(&mut self) -> Result<(), CompilerError> {
+        let tok = self.current_token.take();
+        self.next_token();
 
-                Ok(Expression::Relational(Box::new(RelationalExpression {
-                    first,
-                    op,
-                    second,
-                })))
+        match tok {
+            Some(Ok(Token {
+                token: TokenType::LBrace,
+                ..
+            })) => Ok(()),
+            Some(Ok(t)) => {
+                Err(CompilerError::syntax(format!("Expected '(', found {:?}", t)).with_span(t.pos))
             }
-            _ => Ok(Expression::Simple(first)),
+            Some(Err(e)) => Err(e),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
         }
     }
 
-    fn parse_if(&mut self) -> Result<(), CompilerError> {
+    fn parse_rbrace(&mut self) -> Result<(), CompilerError> {
         let tok = self.current_token.take();
         self.next_token();
 
         match tok {
             Some(Ok(Token {
-                token: TokenType::IfKeyword,
+                token: TokenType::RBrace,
                 ..
             })) => Ok(()),
-            Some(Ok(t)) => Err(CompilerError::syntax(
-                format!("Expected 'if', found {:?}", t),
-                t.pos,
-            )),
-            _ => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
-            )),
+            Some(Ok(t)) => {
+                Err(CompilerError::syntax(format!("Expected ')', found {:?}", t)).with_span(t.pos))
+            }
+            Some(Err(e)) => Err(e),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
         }
     }
 
-    fn parse_then(&mut self) -> Result<(), CompilerError> {
+    fn parse_colon(&mut self) -> Result<(), CompilerError> {
         let tok = self.current_token.take();
         self.next_token();
 
         match tok {
             Some(Ok(Token {
-                token: TokenType::ThenKeyword,
+                token: TokenType::Colon,
                 ..
             })) => Ok(()),
-            Some(Ok(t)) => Err(CompilerError::syntax(
-                format!("Expected 'Then', found {:?}", t),
-                t.pos,
-            )),
+            Some(Ok(t)) => {
+                Err(CompilerError::syntax(format!("Expected ':', found {:?}", t)).with_span(t.pos))
+            }
             Some(Err(e)) => Err(e),
-            _ => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
-            )),
+            _ => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
         }
     }
 
-    fn parse_else(&mut self) -> Result<Option<()>, CompilerError> {
-        match &self.current_token {
-            Some(Ok(Token {
-                token: TokenType::ElseKeyword,
-                ..
-            })) => {
-                self.next_token();
-                Ok(Some(()))
+    fn parse_case(&mut self) -> Result<CaseStatement, CompilerError> {
+        // Consume CASE
+        self.next_token();
+        let selector = self.parse_expr()?;
+        // Labels are still parsed when the selector is wrong
+        let selector_type = self
+            .analyzer
+            .check_case_selector(&selector)
+            .unwrap_or_else(|e| {
+                self.report(e);
+                String::new()
+            });
+        self.parse_of()?;
+
+        self.open_lists
+            .push(mem::discriminant(&TokenType::EndKeyword));
+        let branches = self.parse_case_branches(&selector_type);
+        self.open_lists.pop();
+        let (branches, else_statements) = branches?;
+
+        // Consume END
+        self.next_token();
+
+        Ok(CaseStatement {
+            selector: Box::new(selector),
+            branches,
+            else_statements,
+        })
+    }
+
+    /// Branches up to the closing END (not consumed) and statements of the ELSE part
+    fn parse_case_branches(
+        &mut self,
+        selector_type: &str,
+    ) -> Result<(Vec<CaseBranch>, Option<Vec<Statement>>), CompilerError> {
+        let mut branches = Vec::new();
+        // Values of the labels so far, to catch duplicates
+        let mut seen = Vec::new();
+
+        loop {
+            match &self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::EndKeyword,
+                    ..
+                })) => return Ok((branches, None)),
+                Some(Ok(Token {
+                    token: TokenType::ElseKeyword,
+                    ..
+                })) => {
+                    self.next_token();
+                    let statements = self.parse_statement_list(TokenType::EndKeyword, "end")?;
+                    return Ok((branches, Some(statements)));
+                }
+                Some(Ok(Token {
+                    token: TokenType::Semicolon,
+                    ..
+                })) => self.next_token(),
+                Some(Ok(_)) => match self.parse_case_branch(selector_type, &mut seen) {
+                    Ok(branch) => branches.push(branch),
+                    Err(e) => {
+                        self.report(e);
+                        self.skip_case_branch();
+                    }
+                },
+                Some(Err(e)) => return Err(e.clone()),
+                None => {
+                    return Err(CompilerError::syntax("Unexpected EOF, expected 'end'")
+                        .with_span(self.current_pos))
+                }
             }
-            Some(Err(e)) => Err(e.clone()),
-            _ => Ok(None),
         }
     }
 
-    fn parse_conditional(&mut self) -> Result<IfStatement, CompilerError> {
-        self.parse_if()?;
-        let condition = Box::new(self.parse_expr()?);
-        self.analyzer
-            .check_expr(&condition, &String::from("boolean"), self.current_pos)?;
-        self.parse_then()?;
-        let statement = self.parse_statement()?;
-        let else_ = self.parse_else()?;
+    fn parse_case_branch(
+        &mut self,
+        selector_type: &str,
+        seen: &mut Vec<(i32, i32, (usize, usize))>,
+    ) -> Result<CaseBranch, CompilerError> {
+        let labels = self.parse_case_labels(selector_type, seen)?;
+        self.parse_colon()?;
+        let statement = self.parse_statement()?;
+
+        // The last branch may be followed by ELSE directly
+        if let Some(Ok(Token {
+            token: TokenType::ElseKeyword,
+            ..
+        })) = &self.current_token
+        {
+            return Ok(CaseBranch { labels, statement });
+        }
+
+        self.parse_statement_separator(&TokenType::EndKeyword, "end")?;
+        Ok(CaseBranch { labels, statement })
+    }
+
+    /// Skip the rest of a broken branch: up to the next `;`, or the ELSE/END of the case
+    fn skip_case_branch(&mut self) {
+        // BEGIN and CASE nested in the branch have their own END
+        let mut depth = 0;
 
-        let else_statement = if else_.is_some() {
-            Some(Box::new(self.parse_statement()?))
-        } else {
-            None
-        };
+        loop {
+            match &self.current_token {
+                Some(Ok(t)) => match t.token {
+                    TokenType::BeginKeyword | TokenType::CaseKeyword => depth += 1,
+                    TokenType::EndKeyword if depth == 0 => return,
+                    TokenType::EndKeyword => depth -= 1,
+                    TokenType::Semicolon if depth == 0 => {
+                        self.next_token();
+                        return;
+                    }
+                    TokenType::ElseKeyword if depth == 0 => return,
+                    _ => (),
+                },
+                Some(Err(e)) => self.report(e.clone()),
+                None => return,
+            }
 
-        Ok(IfStatement {
-            condition,
-            statement: Box::new(statement),
-            else_statement,
-        })
+            self.next_token();
+        }
     }
 
-    fn parse_while(&mut self) -> Result<(), CompilerError> {
-        let tok = self.current_token.take();
-        self.next_token();
+    fn parse_case_labels(
+        &mut self,
+        selector_type: &str,
+        seen: &mut Vec<(i32, i32, (usize, usize))>,
+    ) -> Result<Vec<CaseLabel>, CompilerError> {
+        // label {, label}, where label ::= const [.. const]
+        let mut labels = Vec::new();
 
-        match tok {
-            Some(Ok(Token {
-                token: TokenType::WhileKeyword,
-                ..
-            })) => Ok(()),
-            Some(Ok(t)) => Err(CompilerError::syntax(
-                format!("Expected 'While', found {:?}", t),
-                t.pos,
-            )),
-            Some(Err(e)) => Err(e),
-            _ => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
-            )),
+        loop {
+            let low = self.parse_simple_expr()?;
+            let high = match &self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::DotDot,
+                    ..
+                })) => {
+                    self.next_token();
+                    Some(self.parse_simple_expr()?)
+                }
+                _ => None,
+            };
+
+            let label = CaseLabel { low, high };
+
+            // Without a selector type labels are still checked for being ordinal
+            if let Err(e) = self.analyzer.check_case_label(&label, selector_type, seen) {
+                self.report(e);
+            }
+            labels.push(label);
+
+            match &self.current_token {
+                Some(Ok(Token {
+                    token: TokenType::Comma,
+                    ..
+                })) => self.next_token(),
+                _ => return Ok(labels),
+            }
         }
     }
 
-    fn parse_do(&mut self) -> Result<(), CompilerError> {
-        let tok = self.current_token.take();
+    fn parse_repeat_loop(&mut self) -> Result<RepeatStatement, CompilerError> {
+        // Consume REPEAT
         self.next_token();
 
-        match tok {
-            Some(Ok(Token {
-                token: TokenType::DoKeyword,
-                ..
-            })) => Ok(()),
-            Some(Ok(t)) => Err(CompilerError::syntax(
-                format!("Expected 'Do', found {:?}", t),
-                t.pos,
-            )),
-            Some(Err(e)) => Err(e),
-            _ => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
-            )),
-        }
-    }
+        // The body is a statement list of its own, no BEGIN..END needed
+        let statements = self.parse_statement_list(TokenType::UntilKeyword, "until")?;
+        // Consume UNTIL
+        self.next_token();
 
-    fn parse_while_loop(&mut self) -> Result<WhileLoop, CompilerError> {
-        self.parse_while()?;
-        let expr = self.parse_expr()?;
-        self.analyzer
-            .check_expr(&expr, &String::from("boolean"), self.current_pos)?;
-        self.parse_do()?;
-        let statement = self.parse_statement()?;
+        let condition = self.parse_condition(false)?;
+        self.analyzer.check_condition(&condition, "repeat")?;
 
-        Ok(WhileLoop {
-            condition: Box::new(expr),
-            statement: Box::new(statement),
+        Ok(RepeatStatement {
+            statements,
+            condition: Box::new(condition),
         })
     }
 
@@ -911,29 +2983,179 @@ impl Parser {
             self.next_token();
         };
 
+        let statements = self.parse_statement_list(TokenType::EndKeyword, "end")?;
+        // Consume END
+        self.next_token();
+
+        Ok(Compound { statements })
+    }
+
+    /// Statements separated by semicolons up to `closing` (END or UNTIL), which is not consumed
+    fn parse_statement_list(
+        &mut self,
+        closing: TokenType,
+        closing_name: &str,
+    ) -> Result<Vec<Statement>, CompilerError> {
+        self.open_lists.push(mem::discriminant(&closing));
+        let res = self.parse_statements_until(&closing, closing_name);
+        self.open_lists.pop();
+        res
+    }
+
+    fn parse_statements_until(
+        &mut self,
+        closing: &TokenType,
+        closing_name: &str,
+    ) -> Result<Vec<Statement>, CompilerError> {
         let mut statements = Vec::new();
 
         loop {
-            if let Some(Ok(Token {
-                token: TokenType::EndKeyword,
-                ..
-            })) = self.current_token
-            {
-                self.next_token();
-                break;
-            } else {
-                let statement = self.parse_statement();
-                match statement {
-                    Ok(st) => statements.push(st),
+            match &self.current_token {
+                None => {
+                    return Err(CompilerError::syntax(format!(
+                        "Unexpected EOF, expected '{}'",
+                        closing_name
+                    ))
+                    .with_span(self.current_pos))
+                }
+                Some(Ok(t)) if mem::discriminant(&t.token) == mem::discriminant(closing) => break,
+                // Empty statement, nothing to keep
+                Some(Ok(Token {
+                    token: TokenType::Semicolon,
+                    ..
+                })) => self.next_token(),
+                Some(Ok(Token {
+                    token: TokenType::ElseKeyword,
+                    pos,
+                })) => {
+                    // Common mistake: `if a then b := 1; else ...`, the else-branch is still parsed
+                    self.report(
+                        CompilerError::syntax("Unexpected 'else'")
+                            .with_span(*pos)
+                            .with_note("there must be no ';' before 'else'"),
+                    );
+                    self.next_token();
+                }
+                // Closes an enclosing list, e.g. END of BEGIN..END around an unclosed REPEAT.
+                // This list is abandoned and the enclosing one carries on.
+                Some(Ok(t)) if self.closes_open_list(t) => {
+                    return Err(CompilerError::syntax(format!(
+                        "Expected '{}' before {}",
+                        closing_name, t.token
+                    ))
+                    .with_span(t.pos))
+                }
+                // Routines are declared before the body, so its END is missing
+                Some(Ok(t))
+                    if matches!(
+                        t.token,
+                        TokenType::ProcedureKeyword | TokenType::FunctionKeyword
+                    ) =>
+                {
+                    return Err(CompilerError::syntax(format!(
+                        "Expected '{}' before {}",
+                        closing_name, t.token
+                    ))
+                    .with_span(t.pos))
+                }
+                Some(Ok(t))
+                    if matches!(t.token, TokenType::EndKeyword | TokenType::UntilKeyword) =>
+                {
+                    self.report(
+                        CompilerError::syntax(format!("Unexpected {}", t.token)).with_span(t.pos),
+                    );
+                    self.next_token();
+                }
+                _ => match self.parse_statement() {
+                    Ok(st) => {
+                        statements.push(st);
+
+                        if let Err(e) = self.parse_statement_separator(closing, closing_name) {
+                            self.report(e);
+                            self.skip_until_starters();
+                        }
+                    }
                     Err(e) => {
-                        self.errors.push(e);
+                        self.report(e);
                         self.skip_until_starters();
                     }
-                }
-            };
+                },
+            }
         }
 
-        Ok(Compound { statements })
+        Ok(statements)
+    }
+
+    // Statements are separated by semicolons, the last one may be directly followed by END/UNTIL
+    fn parse_statement_separator(
+        &mut self,
+        closing: &TokenType,
+        closing_name: &str,
+    ) -> Result<(), CompilerError> {
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::Semicolon,
+                ..
+            })) => {
+                self.next_token();
+                Ok(())
+            }
+            Some(Ok(t)) if mem::discriminant(&t.token) == mem::discriminant(closing) => Ok(()),
+            // Reported by the statement list
+            Some(Ok(t)) if self.closes_open_list(t) => Ok(()),
+            None => Ok(()),
+            Some(Ok(t)) => Err(CompilerError::syntax(format!(
+                "Expected ';' or '{}' after statement, found {:?}",
+                closing_name, t
+            ))
+            .with_span(t.pos)),
+            Some(Err(e)) => Err(e.clone()),
+        }
+    }
+
+    fn closes_open_list(&self, t: &Token) -> bool {
+        self.open_lists.contains(&mem::discriminant(&t.token))
+    }
+
+    // Declarations start with an identifier, while `id :=` already starts a statement
+    fn at_declaration(&mut self) -> bool {
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::Identifier(_),
+                ..
+            })) => !matches!(
+                self.peek_token(),
+                Some(Ok(Token {
+                    token: TokenType::AssignOp,
+                    ..
+                }))
+            ),
+            _ => false,
+        }
+    }
+
+    // `N := 10` where a constant can be declared: a name which isn't declared yet
+    // is taken for a constant, while assigning a variable starts a statement
+    fn at_mistyped_const(&mut self) -> bool {
+        match &self.current_token {
+            Some(Ok(
+                token @ Token {
+                    token: TokenType::Identifier(_),
+                    ..
+                },
+            )) => {
+                let id = Identifier::new(token.clone());
+                self.analyzer.find_identifier(&id).is_err()
+                    && matches!(
+                        self.peek_token(),
+                        Some(Ok(Token {
+                            token: TokenType::AssignOp,
+                            ..
+                        }))
+                    )
+            }
+            _ => false,
+        }
     }
 
     fn skip_until_starters(&mut self) {
@@ -955,13 +3177,35 @@ impl Parser {
                     | Token {
                         token: TokenType::WhileKeyword,
                         ..
+                    }
+                    | Token {
+                        token:
+                            TokenType::RepeatKeyword
+                            | TokenType::UntilKeyword
+                            | TokenType::ForKeyword
+                            | TokenType::CaseKeyword
+                            | TokenType::ProcedureKeyword
+                            | TokenType::FunctionKeyword,
+                        ..
                     } => {
                         return;
                     }
+                    // Procedure call starts a statement as well
+                    Token {
+                        token: TokenType::Identifier(_),
+                        ..
+                    } if matches!(
+                        self.analyzer
+                            .find_identifier(&Identifier::new(token.clone())),
+                        Ok(Usage::Procedure(_))
+                    ) =>
+                    {
+                        return
+                    }
                     Token {
                         token: TokenType::Identifier(_),
                         ..
-                    } => match self.lexer.peek() {
+                    } => match self.peek_token() {
                         Some(Ok(Token {
                             token: TokenType::AssignOp,
                             ..
@@ -974,7 +3218,7 @@ impl Parser {
                     _ => self.next_token(),
                 },
                 Some(Err(e)) => {
-                    self.errors.push(e.clone());
+                    self.report(e.clone());
                     self.next_token();
                 }
                 None => return,
@@ -1026,112 +3270,204 @@ impl Parser {
                 self.next_token();
                 Ok(RelationalOp::UnEq)
             }
+            Some(Ok(Token {
+                token: TokenType::InOp,
+                ..
+            })) => {
+                self.next_token();
+                Ok(RelationalOp::In)
+            }
             Some(Ok(Token {
                 token: TokenType::Identifier(s),
                 pos,
-            })) => Err(CompilerError::syntax(
-                format!("Expected ; but found identifier {:?}", s),
-                *pos,
-            )),
-            Some(Ok(t)) => Err(CompilerError::syntax(
-                format!("Expected *, div or mod, found {:?}", t),
-                t.pos,
-            )),
+            })) => Err(
+                CompilerError::syntax(format!("Expected ; but found identifier {:?}", s))
+                    .with_span(*pos),
+            ),
+            Some(Ok(t)) => Err(CompilerError::syntax(format!(
+                "Expected *, div or mod, found {:?}",
+                t
+            ))
+            .with_span(t.pos)),
             Some(Err(e)) => Err((*e).clone()),
-            None => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
-            )),
+            None => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
         };
 
         op
     }
 
     fn parse_simple_expr(&mut self) -> Result<SimpleExpression, CompilerError> {
-        let mut inside_braces = false;
+        self.nested("Expression", |p| p.parse_simple_expr_at_depth())
+    }
 
-        if let &Some(Ok(Token {
-            token: TokenType::LBrace,
-            ..
-        })) = &self.current_token
-        {
-            inside_braces = true;
-            self.next_token();
+    // Every expression, parenthesized or an argument, an index or a set element, goes
+    // through here, as does every statement. Each level costs several stack frames.
+    fn nested<T>(
+        &mut self,
+        what: &str,
+        parse: impl FnOnce(&mut Self) -> Result<T, CompilerError>,
+    ) -> Result<T, CompilerError> {
+        if self.expr_depth >= self.max_expr_depth {
+            self.too_deep = true;
+            return Err(CompilerError::syntax(format!(
+                "{} is too deeply nested (limit is {})",
+                what, self.max_expr_depth
+            ))
+            .with_span(self.current_pos));
+        }
+
+        self.expr_depth += 1;
+        let result = parse(self);
+        self.expr_depth -= 1;
+        result
+    }
+
+    fn parse_simple_expr_at_depth(&mut self) -> Result<SimpleExpression, CompilerError> {
+        // [+|-] term {add_op term}
+        let sign = match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::PlusOp,
+                ..
+            })) => Some(Sign::Plus),
+            Some(Ok(Token {
+                token: TokenType::MinusOp,
+                ..
+            })) => Some(Sign::Minus),
+            _ => None,
         };
+        if sign.is_some() {
+            self.next_token();
+        }
 
         let term = Box::new(self.parse_term()?);
-        let sub_expr = self.parse_sub_expr()?;
-        let sub_expr_type;
-
-        if sub_expr.is_some() {
-            sub_expr_type = sub_expr.as_ref().unwrap().sub_expr_type.clone();
-        } else {
-            sub_expr_type = String::new();
+        if let Some(sign) = &sign {
+            self.analyzer
+                .check_sign(sign, &term.term_type, term.factor.pos())?;
         }
 
+        let sub_expr = self.parse_sub_expr()?;
+        let sub_expr_type = match &sub_expr {
+            Some(s) => s.sub_expr_type.clone(),
+            None => String::new(),
+        };
+
         let pos = self.current_pos;
 
         let expr_type = self
             .analyzer
             .merge_types(&term.term_type, &sub_expr_type, pos, false)?;
 
-        let expr = SimpleExpression {
+        Ok(SimpleExpression {
+            sign,
             term,
             sub_expr,
             expr_type,
-        };
+        })
+    }
 
-        let expr = if inside_braces {
-            match self.current_token.clone() {
-                Some(Ok(Token {
-                    token: TokenType::RBrace,
-                    ..
-                })) => {
-                    // Do not consume RBrace => it is consumed inside parse_factor
-                    Ok(expr)
-                }
-                Some(Ok(t)) => Err(CompilerError::syntax(
-                    format!("Expected closing brace, got {:?}", t),
-                    t.pos,
-                )),
-                _ => Err(CompilerError::syntax(
-                    "Unexpected EOF (expected '}}'".into(),
-                    self.current_pos,
-                )),
-            }
-        } else {
-            Ok(expr)
-        };
+    // Inner part of `( <simple expr> )` factor, current token is the opening brace
+    fn parse_braced_expr(&mut self) -> Result<Expression, CompilerError> {
+        self.next_token();
 
-        expr
+        // A comparison in parentheses is a boolean factor, e.g. `not (a = b)`
+        let expr = self.parse_expr()?;
+
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::RBrace,
+                ..
+            })) => {
+                // Do not consume RBrace => it is consumed inside parse_factor
+                Ok(expr)
+            }
+            Some(Ok(t)) => Err(CompilerError::syntax(format!(
+                "Expected closing brace, got {:?}",
+                t
+            ))
+            .with_span(t.pos)),
+            Some(Err(e)) => Err(e.clone()),
+            None => {
+                Err(CompilerError::syntax("Unexpected EOF (expected ')')")
+                    .with_span(self.current_pos))
+            }
+        }
     }
+
     fn parse_assignment(&mut self) -> Result<VarAssignment, CompilerError> {
         let id = self.parse_identifier()?;
+        let selectors = self.parse_selectors()?;
 
         match &self.current_token {
             Some(Ok(Token {
-                token: TokenType::AssignOp,
-                ..
+                token: token @ (TokenType::AssignOp | TokenType::Eq),
+                pos,
             })) => {
+                // Common mistake: `x = 5`, parsed on as the assignment
+                if matches!(token, TokenType::Eq) {
+                    self.report(
+                        CompilerError::syntax("Expected ':=' to assign, found '='")
+                            .with_span(*pos)
+                            .with_note("'=' compares two values, ':=' assigns one"),
+                    );
+                }
                 self.next_token();
 
-                let assignment = VarAssignment {
+                let mut assignment = VarAssignment {
                     name: Box::new(id),
+                    selectors,
                     value: Box::new(self.parse_expr()?),
                 };
 
-                self.parse_semicolon()?;
-                Ok(self.analyzer.check_assignment(assignment)?)
+                // The statement is complete, parsing goes on from its end
+                match self.analyzer.check_assignment(&mut assignment) {
+                    Ok(()) if self.pedantic => {
+                        if let Some(w) = self.analyzer.check_conversion(&assignment) {
+                            self.report(w);
+                        }
+                    }
+                    Ok(()) => (),
+                    Err(e) => self.report(e),
+                }
+                Ok(assignment)
+            }
+            Some(Ok(t)) => {
+                Err(CompilerError::syntax(format!("Expected :=, found {:?}", t)).with_span(t.pos))
             }
-            Some(Ok(t)) => Err(CompilerError::syntax(
-                format!("Expected :=, found {:?}", t),
-                t.pos,
-            )),
             Some(Err(e)) => Err(e.clone()),
-            _ => Err(CompilerError::syntax(
-                "Expected :=, found EOF".into(),
-                self.current_pos,
-            )),
+            _ => Err(CompilerError::syntax("Expected :=, found EOF").with_span(self.current_pos)),
+        }
+    }
+
+    /// Names used by a fragment resolve in `scope` of an analyzed program's tree
+    pub fn resume_scope(&mut self, tree: ScopeTree, scope: ScopeId) {
+        self.analyzer.resume_scope(tree, scope);
+    }
+
+    /// Parses input which is nothing but one expression
+    pub fn parse_expression_only(&mut self) -> Result<Expression, CompilerError> {
+        self.fragment = true;
+        let e = self.parse_expr()?;
+        self.expect_input_end()?;
+        Ok(e)
+    }
+
+    /// Parses input which is nothing but one statement
+    pub fn parse_statement_only(&mut self) -> Result<Statement, CompilerError> {
+        self.fragment = true;
+        let s = self.parse_statement()?;
+        self.expect_input_end()?;
+        Ok(s)
+    }
+
+    fn expect_input_end(&mut self) -> Result<(), CompilerError> {
+        match &self.current_token {
+            None => Ok(()),
+            Some(Ok(t)) => Err(CompilerError::syntax(format!(
+                "Expected end of input, found {:?}",
+                t
+            ))
+            .with_span(t.pos)),
+            Some(Err(e)) => Err(e.clone()),
         }
     }
 
@@ -1143,6 +3479,17 @@ impl Parser {
         self.parse_program()
     }
 
+    /// Program or unit, whichever the source starts with
+    pub fn parse_module(&mut self) -> Result<Module, CompilerError> {
+        match &self.current_token {
+            Some(Ok(Token {
+                token: TokenType::UnitKeyword,
+                ..
+            })) => self.parse_unit().map(Module::Unit),
+            _ => self.parse_program().map(Module::Program),
+        }
+    }
+
     fn parse_multiplicative_op(&mut self) -> Result<MultiplicativeOp, CompilerError> {
         let op = match &self.current_token {
             Some(Ok(Token {
@@ -1176,19 +3523,17 @@ impl Parser {
             Some(Ok(Token {
                 token: TokenType::Identifier(s),
                 pos,
-            })) => Err(CompilerError::syntax(
-                format!("Expected ; but found identifier {:?}", s),
-                *pos,
-            )),
-            Some(Ok(t)) => Err(CompilerError::syntax(
-                format!("Expected *, div or mod, found {:?}", t),
-                t.pos,
-            )),
+            })) => Err(
+                CompilerError::syntax(format!("Expected ; but found identifier {:?}", s))
+                    .with_span(*pos),
+            ),
+            Some(Ok(t)) => Err(CompilerError::syntax(format!(
+                "Expected *, div or mod, found {:?}",
+                t
+            ))
+            .with_span(t.pos)),
             Some(Err(e)) => Err((*e).clone()),
-            None => Err(CompilerError::syntax(
-                "Unexpected EOF".into(),
-                self.current_pos,
-            )),
+            None => Err(CompilerError::syntax("Unexpected EOF").with_span(self.current_pos)),
         };
 
         op
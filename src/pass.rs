@@ -0,0 +1,13 @@
+use crate::error::Diagnostic;
+use crate::syntax::Program;
+
+/// Extra analysis over the AST, run after semantic analysis for every
+/// program that could be parsed (see `Program::has_errors` for partial trees).
+/// Passes usually implement `Visitor` and report problems by pushing
+/// diagnostics, which end up next to the compiler's own.
+pub trait Pass: Send {
+    /// Shown in internal errors if the pass panics
+    fn name(&self) -> &str;
+
+    fn run(&mut self, program: &Program, diagnostics: &mut Vec<Diagnostic>);
+}
@@ -0,0 +1,540 @@
+use crate::options::{KeywordCase, Newline, PrintOptions};
+use crate::scope::SymbolId;
+use crate::syntax::*;
+use crate::token::TokenType;
+use std::collections::HashMap;
+
+const INDENT: &str = "    ";
+
+/// Prints the AST back as Pascal source which parses to the same tree.
+/// Comments and the original layout are not kept.
+pub fn print_program(p: &Program) -> String {
+    print_program_with(p, &PrintOptions::default())
+}
+
+/// Same as `print_program` with the given newlines and keyword case
+pub fn print_program_with(p: &Program, options: &PrintOptions) -> String {
+    print_program_renamed(p, &HashMap::new(), options)
+}
+
+/// Same as `print_program_with`, but identifiers bound to symbols from `names` are replaced
+pub fn print_program_renamed(
+    p: &Program,
+    names: &HashMap<SymbolId, String>,
+    options: &PrintOptions,
+) -> String {
+    let mut printer = Printer {
+        out: String::new(),
+        indent: 0,
+        names,
+        keyword_case: options.keyword_case,
+    };
+
+    printer.program(p);
+
+    // Lines are built with '\n' only, the printer pops it to append separators
+    match options.newline {
+        Newline::Lf => printer.out,
+        Newline::CrLf => printer.out.replace('\n', "\r\n"),
+    }
+}
+
+struct Printer<'a> {
+    out: String,
+    indent: usize,
+    names: &'a HashMap<SymbolId, String>,
+    keyword_case: KeywordCase,
+}
+
+impl Printer<'_> {
+    fn kw(&self, keyword: &str) -> String {
+        match self.keyword_case {
+            KeywordCase::Lower => keyword.to_string(),
+            KeywordCase::Upper => keyword.to_uppercase(),
+            KeywordCase::Capitalized => {
+                let mut chars = keyword.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => String::new(),
+                }
+            }
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn name(&self, id: &Identifier) -> String {
+        match id.symbol.and_then(|s| self.names.get(&s)) {
+            Some(name) => name.clone(),
+            None => id.get_id(),
+        }
+    }
+
+    fn program(&mut self, p: &Program) {
+        let mut header = format!("{} {}", self.kw("program"), self.name(&p.identifier));
+        if !p.parameters.is_empty() {
+            let params: Vec<String> = p.parameters.iter().map(|id| self.name(id)).collect();
+            header = format!("{}({})", header, params.join(", "));
+        }
+        header.push(';');
+        self.line(&header);
+
+        if !p.units.is_empty() {
+            let units: Vec<String> = p.units.iter().map(|id| self.name(id)).collect();
+            let uses = format!("{} {};", self.kw("uses"), units.join(", "));
+            self.line(&uses);
+        }
+        self.sections(&p.const_section, &p.type_section, &p.var_section);
+
+        for d in p.procedures.iter() {
+            self.procedure(d);
+        }
+
+        self.compound(&p.compound);
+        // Program ends with a period instead of a semicolon
+        self.out.pop();
+        self.out.push_str(".\n");
+    }
+
+    fn sections(
+        &mut self,
+        consts: &Option<Box<ConstSection>>,
+        types: &Option<Box<TypeSection>>,
+        vars: &Option<Box<VarSection>>,
+    ) {
+        if let Some(section) = consts {
+            self.line(&self.kw("const"));
+            self.indent += 1;
+            for d in section.constants.iter() {
+                let decl = format!("{} = {};", self.name(&d.id), self.expression(&d.value));
+                self.line(&decl);
+            }
+            self.indent -= 1;
+        }
+
+        if let Some(section) = types {
+            self.line(&self.kw("type"));
+            self.indent += 1;
+            for d in section.types.iter() {
+                let decl = format!("{} = {};", self.name(&d.id), self.type_spec(&d.parent));
+                self.line(&decl);
+            }
+            self.indent -= 1;
+        }
+
+        if let Some(section) = vars {
+            self.line(&self.kw("var"));
+            self.indent += 1;
+            for d in section.declarations.iter() {
+                let decl = format!("{} : {};", self.name(&d.id), self.type_spec(&d.type_name));
+                self.line(&decl);
+            }
+            self.indent -= 1;
+        }
+    }
+
+    fn type_spec(&self, t: &TypeSpec) -> String {
+        match t {
+            TypeSpec::Named(id) => self.name(id),
+            TypeSpec::Array(a) => format!(
+                "{}[{}] {} {}",
+                self.kw("array"),
+                self.type_spec(&a.index),
+                self.kw("of"),
+                self.type_spec(&a.element)
+            ),
+            TypeSpec::Record(r) => {
+                let fields = self.field_list(&r.fields, r.variant.as_ref());
+
+                let (record, end) = (self.kw("record"), self.kw("end"));
+                match fields.is_empty() {
+                    true => format!("{} {}", record, end),
+                    false => format!("{} {} {}", record, fields.join("; "), end),
+                }
+            }
+            TypeSpec::Set(s) => format!(
+                "{} {} {}",
+                self.kw("set"),
+                self.kw("of"),
+                self.type_spec(&s.element)
+            ),
+            TypeSpec::Pointer(p) => format!("^{}", self.name(&p.target)),
+            TypeSpec::String(s) => {
+                format!(
+                    "{}[{}]",
+                    self.name(&s.name),
+                    self.simple_expression(&s.length)
+                )
+            }
+            TypeSpec::Enum(e) => {
+                let values: Vec<String> = e.values.iter().map(|v| self.name(v)).collect();
+                format!("({})", values.join(", "))
+            }
+            TypeSpec::Subrange(s) => format!(
+                "{}..{}",
+                self.simple_expression(&s.low),
+                self.simple_expression(&s.high)
+            ),
+        }
+    }
+
+    fn procedure(&mut self, d: &ProcedureDeclaration) {
+        let params: Vec<String> = d
+            .params
+            .iter()
+            .map(|p| {
+                let var = if p.by_reference {
+                    format!("{} ", self.kw("var"))
+                } else {
+                    String::new()
+                };
+                format!("{}{} : {}", var, self.name(&p.id), self.name(&p.type_name))
+            })
+            .collect();
+
+        let mut header = match &d.result_type {
+            Some(_) => format!("{} {}", self.kw("function"), self.name(&d.id)),
+            None => format!("{} {}", self.kw("procedure"), self.name(&d.id)),
+        };
+        if !params.is_empty() && !d.short_heading {
+            header = format!("{}({})", header, params.join("; "));
+        }
+        if let (Some(result_type), false) = (&d.result_type, d.short_heading) {
+            header = format!("{} : {}", header, self.name(result_type));
+        }
+        header.push(';');
+
+        if d.forward {
+            let line = format!("{} {};", header, self.kw("forward"));
+            self.line(&line);
+            return;
+        }
+        self.line(&header);
+        self.sections(&d.const_section, &d.type_section, &d.var_section);
+
+        // Nested procedures are indented to show where they belong
+        self.indent += 1;
+        for nested in d.procedures.iter() {
+            self.procedure(nested);
+        }
+        self.indent -= 1;
+
+        self.compound(&d.compound);
+        self.out.pop();
+        self.out.push_str(";\n");
+    }
+
+    fn compound(&mut self, c: &Compound) {
+        self.line(&self.kw("begin"));
+        self.indent += 1;
+        self.statement_list(&c.statements);
+        self.indent -= 1;
+        self.line(&self.kw("end"));
+    }
+
+    fn statement_list(&mut self, statements: &[Statement]) {
+        for s in statements.iter() {
+            self.statement(s);
+            // Separator goes after the statement's last line
+            self.out.pop();
+            self.out.push_str(";\n");
+        }
+    }
+
+    fn statement(&mut self, s: &Statement) {
+        match s {
+            Statement::Simple(a) => {
+                let text = format!(
+                    "{}{} := {}",
+                    self.name(&a.name),
+                    self.selectors(&a.selectors),
+                    self.expression(&a.value)
+                );
+                self.line(&text);
+            }
+            Statement::Cond(c) => {
+                let header = format!(
+                    "{} {} {}",
+                    self.kw("if"),
+                    self.expression(&c.condition),
+                    self.kw("then")
+                );
+                self.line(&header);
+                self.nested(&c.statement);
+
+                if let Some(e) = &c.else_statement {
+                    self.line(&self.kw("else"));
+                    self.nested(e);
+                }
+            }
+            Statement::While(w) => {
+                let header = format!(
+                    "{} {} {}",
+                    self.kw("while"),
+                    self.expression(&w.condition),
+                    self.kw("do")
+                );
+                self.line(&header);
+                self.nested(&w.statement);
+            }
+            Statement::Repeat(r) => {
+                self.line(&self.kw("repeat"));
+                self.indent += 1;
+                self.statement_list(&r.statements);
+                self.indent -= 1;
+
+                let footer = format!("{} {}", self.kw("until"), self.expression(&r.condition));
+                self.line(&footer);
+            }
+            Statement::For(l) => {
+                let header = format!(
+                    "{} {} := {} {} {} {}",
+                    self.kw("for"),
+                    self.name(&l.variable),
+                    self.expression(&l.start),
+                    self.kw(if l.downto { "downto" } else { "to" }),
+                    self.expression(&l.end),
+                    self.kw("do")
+                );
+                self.line(&header);
+                self.nested(&l.statement);
+            }
+            Statement::Case(c) => {
+                let header = format!(
+                    "{} {} {}",
+                    self.kw("case"),
+                    self.expression(&c.selector),
+                    self.kw("of")
+                );
+                self.line(&header);
+                self.indent += 1;
+
+                for b in c.branches.iter() {
+                    let labels: Vec<String> = b.labels.iter().map(|l| self.case_label(l)).collect();
+                    let label_line = format!("{}:", labels.join(", "));
+                    self.line(&label_line);
+                    self.nested(&b.statement);
+                    self.out.pop();
+                    self.out.push_str(";\n");
+                }
+
+                self.indent -= 1;
+
+                if let Some(statements) = &c.else_statements {
+                    self.line(&self.kw("else"));
+                    self.indent += 1;
+                    self.statement_list(statements);
+                    self.indent -= 1;
+                }
+
+                self.line(&self.kw("end"));
+            }
+            Statement::Call(c) => {
+                let text = self.call(c);
+                self.line(&text);
+            }
+            Statement::Compound(c) => self.compound(c),
+            // Prints nothing, a following separator or ELSE ends it
+            Statement::Empty => (),
+        }
+    }
+
+    fn nested(&mut self, s: &Statement) {
+        // BEGIN..END lines up with the statement that owns it
+        if let Statement::Compound(c) = s {
+            return self.compound(c);
+        }
+
+        self.indent += 1;
+        self.statement(s);
+        self.indent -= 1;
+    }
+
+    // Fields of a record or a variant, the variant part last
+    fn field_list(
+        &self,
+        fields: &[FieldDeclaration],
+        variant: Option<&VariantPart>,
+    ) -> Vec<String> {
+        let mut list: Vec<String> = fields
+            .iter()
+            .map(|f| format!("{} : {}", f.id.get_id(), self.type_spec(&f.type_name)))
+            .collect();
+
+        if let Some(part) = variant {
+            let head = match &part.tag {
+                Some(tag) => format!("{} : {}", tag.get_id(), self.name(&part.tag_type)),
+                None => self.name(&part.tag_type),
+            };
+            let variants: Vec<String> = part
+                .variants
+                .iter()
+                .map(|v| {
+                    let labels: Vec<String> = v.labels.iter().map(|l| self.case_label(l)).collect();
+                    let fields = self.field_list(&v.fields, v.variant.as_deref());
+                    format!("{}: ({})", labels.join(", "), fields.join("; "))
+                })
+                .collect();
+
+            list.push(format!(
+                "{} {} {} {}",
+                self.kw("case"),
+                head,
+                self.kw("of"),
+                variants.join("; ")
+            ));
+        }
+
+        list
+    }
+
+    fn case_label(&self, l: &CaseLabel) -> String {
+        match &l.high {
+            Some(high) => format!(
+                "{}..{}",
+                self.simple_expression(&l.low),
+                self.simple_expression(high)
+            ),
+            None => self.simple_expression(&l.low),
+        }
+    }
+
+    fn call(&self, c: &Call) -> String {
+        if c.arguments.is_empty() {
+            return self.name(&c.name);
+        }
+
+        let mut arguments: Vec<String> = c.arguments.iter().map(|a| self.expression(a)).collect();
+        for (i, format) in c.formats.iter() {
+            arguments[*i].push_str(&format!(":{}", self.expression(&format.width)));
+            if let Some(decimals) = &format.decimals {
+                arguments[*i].push_str(&format!(":{}", self.expression(decimals)));
+            }
+        }
+
+        format!("{}({})", self.name(&c.name), arguments.join(", "))
+    }
+
+    fn selectors(&self, selectors: &[Selector]) -> String {
+        let mut text = String::new();
+
+        // Indexes in a row share the brackets, `a[i, j]`
+        for (i, s) in selectors.iter().enumerate() {
+            let previous_index = i > 0 && matches!(selectors[i - 1], Selector::Index(_));
+
+            match s {
+                Selector::Index(e) if previous_index => {
+                    text.pop();
+                    text = format!("{}, {}]", text, self.expression(e));
+                }
+                Selector::Index(e) => text = format!("{}[{}]", text, self.expression(e)),
+                Selector::Field(id) => text = format!("{}.{}", text, id.get_id()),
+                Selector::Deref(_) => text.push('^'),
+            }
+        }
+
+        text
+    }
+
+    fn expression(&self, e: &Expression) -> String {
+        match e {
+            Expression::Simple(s) => self.simple_expression(s),
+            Expression::Relational(r) => format!(
+                "{} {} {}",
+                self.simple_expression(&r.first),
+                match *r.op {
+                    RelationalOp::In => self.kw("in"),
+                    ref op => format!("{:?}", op),
+                },
+                self.simple_expression(&r.second)
+            ),
+        }
+    }
+
+    fn simple_expression(&self, e: &SimpleExpression) -> String {
+        let mut text = self.term(&e.term);
+        match e.sign {
+            Some(Sign::Plus) => text.insert(0, '+'),
+            Some(Sign::Minus) => text.insert(0, '-'),
+            None => (),
+        }
+
+        let mut sub_expr = e.sub_expr.as_ref();
+        while let Some(s) = sub_expr {
+            let op = match *s.op {
+                AdditiveOp::Plus => "+",
+                AdditiveOp::Minus => "-",
+                AdditiveOp::Or => &self.kw("or"),
+            };
+            text = format!("{} {} {}", text, op, self.term(&s.term));
+            sub_expr = s.sub_expr.as_deref();
+        }
+
+        text
+    }
+
+    fn term(&self, t: &Term) -> String {
+        let mut text = self.factor(&t.factor);
+
+        let mut sub_term = t.sub_term.as_deref();
+        while let Some(s) = sub_term {
+            let op = match s.op {
+                MultiplicativeOp::Mul => "*",
+                MultiplicativeOp::Div => &self.kw("div"),
+                MultiplicativeOp::Mod => &self.kw("mod"),
+                MultiplicativeOp::And => &self.kw("and"),
+            };
+            text = format!("{} {} {}", text, op, self.factor(&s.factor));
+            sub_term = s.sub_term.as_deref();
+        }
+
+        text
+    }
+
+    fn factor(&self, f: &Factor) -> String {
+        match f {
+            Factor::Integer(t) | Factor::Real(t) | Factor::Char(t) | Factor::String(t) => {
+                match &t.token {
+                    TokenType::Integer(i) => i.to_string(),
+                    TokenType::HexInteger(i) => format!("${:X}", i),
+                    // Debug formatting always keeps the decimal point
+                    TokenType::Real(r) => format!("{:?}", r),
+                    TokenType::StringLiteral(s) => format!("'{}'", s.replace('\'', "''")),
+                    TokenType::CharCode(c) => format!("#{}", *c as u32),
+                    _ => String::new(),
+                }
+            }
+            Factor::Identifier(id) => self.name(id),
+            Factor::Call(c) => self.call(c),
+            Factor::Selected(v) => {
+                format!("{}{}", self.name(&v.name), self.selectors(&v.selectors))
+            }
+            Factor::Address(v) => {
+                format!("@{}{}", self.name(&v.name), self.selectors(&v.selectors))
+            }
+            Factor::Nil(_) => self.kw("nil"),
+            Factor::Not(operand) => format!("{} {}", self.kw("not"), self.factor(operand)),
+            Factor::Set(s) => {
+                let elements: Vec<String> = s
+                    .elements
+                    .iter()
+                    .map(|e| match &e.high {
+                        Some(high) => {
+                            format!("{}..{}", self.expression(&e.low), self.expression(high))
+                        }
+                        None => self.expression(&e.low),
+                    })
+                    .collect();
+
+                format!("[{}]", elements.join(", "))
+            }
+            Factor::Expression(e) => format!("({})", self.expression(e)),
+        }
+    }
+}
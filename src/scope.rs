@@ -1,15 +1,67 @@
-use crate::syntax::Identifier;
-use crate::token::{Token, TokenType};
-use std::collections::HashMap;
+use crate::const_eval::ConstValue;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
+use std::ops::Range;
 
 #[derive(PartialEq, Clone)]
 pub enum Usage {
     Constant(String),
     Type(Option<String>),
     Program,
+    Unit,
     Variable(String),
-    // Procedure, function…
+    Procedure(Signature),
+    Function(Signature),
+}
+
+/// Formal parameters of a procedure or function, what a call has to match
+#[derive(PartialEq, Clone)]
+pub struct Signature {
+    pub(crate) params: Vec<Param>,
+    // Result type, functions only
+    pub(crate) result: Option<String>,
+    // Trailing parameters a call may leave out, builtins only
+    pub(crate) optional: usize,
+    // The last parameter repeats, so any number of arguments may follow (write, read)
+    pub(crate) rest: bool,
+}
+
+#[derive(PartialEq, Clone)]
+pub struct Param {
+    pub(crate) type_name: String,
+    // VAR parameter, the argument must be a variable
+    pub(crate) by_reference: bool,
+}
+
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|p| match p.by_reference {
+                true => format!("var {}", p.type_name),
+                false => p.type_name.clone(),
+            })
+            .collect();
+        let required = params.len() - self.optional;
+
+        write!(f, "({}", params[..required].join(", "))?;
+        for (i, param) in params[required..].iter().enumerate() {
+            match i + required {
+                0 => write!(f, "[{}]", param)?,
+                _ => write!(f, "[, {}]", param)?,
+            }
+        }
+        if self.rest {
+            write!(f, "...")?;
+        }
+        write!(f, ")")?;
+
+        match &self.result {
+            Some(result) => write!(f, ": {}", result),
+            None => Ok(()),
+        }
+    }
 }
 
 impl fmt::Debug for Usage {
@@ -18,49 +70,349 @@ impl fmt::Debug for Usage {
             Usage::Constant(s) => write!(f, "constant of type \"{:?}\"", s),
             Usage::Type(s) => write!(f, "type alias of \"{:?}\"", s),
             Usage::Program => write!(f, "program"),
+            Usage::Unit => write!(f, "unit"),
             Usage::Variable(s) => write!(f, "variable of type \"{:?}\"", s),
+            Usage::Procedure(s) => write!(f, "procedure {:?}", s),
+            Usage::Function(s) => write!(f, "function {:?}", s),
+        }
+    }
+}
+
+/// Structure behind the name of an array or record type. Such names are
+/// spelled out from the structure (`array[1..10] of integer`), so types
+/// written the same way are the same type.
+#[derive(Clone)]
+pub enum TypeDef {
+    Array {
+        index_type: String,
+        low: ConstValue,
+        high: ConstValue,
+        element: String,
+    },
+    // Field names and types in declaration order, those of the variants included
+    Record {
+        fields: Vec<(String, String)>,
+        variant: Option<VariantLayout>,
+    },
+    // Element type is resolved, so aliases of one type give the same set
+    Set {
+        element: String,
+    },
+    // Enumerator names, their ordinals are the positions
+    Enum {
+        values: Vec<String>,
+    },
+    // Target is named as written, it may be declared after the pointer
+    Pointer {
+        target: String,
+    },
+    // Values are of the host type, the bounds only limit constants assigned
+    Subrange {
+        host: String,
+        low: ConstValue,
+        high: ConstValue,
+    },
+}
+
+/// Where the variant part of a record keeps its fields: every variant starts at
+/// the same offset, right after the fields before the part. Fields are indexes
+/// into the record's `fields`.
+#[derive(Clone, Debug)]
+pub struct VariantLayout {
+    // None when the tag has no name and so no storage
+    pub tag: Option<usize>,
+    pub tag_type: String,
+    pub variants: Vec<VariantFields>,
+}
+
+#[derive(Clone, Debug)]
+pub struct VariantFields {
+    // Label ranges, `low..high` or a single constant as `low == high`
+    pub labels: Vec<(ConstValue, ConstValue)>,
+    // Fields of the variant, those of a nested part included
+    pub fields: Range<usize>,
+    pub nested: Option<VariantLayout>,
+}
+
+impl fmt::Debug for TypeDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeDef::Array {
+                index_type,
+                low,
+                high,
+                element,
+            } => f
+                .debug_struct("Array")
+                .field("index_type", index_type)
+                .field("low", low)
+                .field("high", high)
+                .field("element", element)
+                .finish(),
+            TypeDef::Record { fields, variant } => f
+                .debug_struct("Record")
+                .field("fields", fields)
+                .field("variant", variant)
+                .finish(),
+            TypeDef::Set { element } => f.debug_struct("Set").field("element", element).finish(),
+            TypeDef::Enum { values } => f.debug_struct("Enum").field("values", values).finish(),
+            TypeDef::Pointer { target } => {
+                f.debug_struct("Pointer").field("target", target).finish()
+            }
+            TypeDef::Subrange { host, low, high } => f
+                .debug_struct("Subrange")
+                .field("host", host)
+                .field("low", low)
+                .field("high", high)
+                .finish(),
         }
     }
 }
 
+/// Index of a declared identifier inside the analyzer's symbol table.
+/// Resolved identifiers in the AST carry it, so later phases can follow
+/// def-use links without looking names up again.
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub struct SymbolId(pub(crate) usize);
+
+impl fmt::Debug for SymbolId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Clone)]
+pub struct Symbol {
+    pub(crate) name: String,
+    pub(crate) usage: Usage,
+    // Known value for constants
+    pub(crate) value: Option<ConstValue>,
+    // Builtins are declared at (0, 0)
+    pub(crate) pos: (usize, usize),
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Symbol")
+            .field("name", &self.name)
+            .field("usage", &self.usage)
+            .field("value", &self.value)
+            .field("position", &self.pos)
+            .finish()
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ScopeId(pub(crate) usize);
+
+impl fmt::Debug for ScopeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "scope#{}", self.0)
+    }
+}
+
+#[derive(Clone)]
 pub struct Scope {
-    identifiers: HashMap<String, Usage>,
+    // Ordered, so dumps and listings are the same on every run
+    identifiers: BTreeMap<String, SymbolId>,
+    pub(crate) parent: Option<ScopeId>,
+    // Source region covered by the scope, end is None while it is still open
+    pub(crate) start: (usize, usize),
+    pub(crate) end: Option<(usize, usize)>,
 }
 
 impl Scope {
+    pub fn new(parent: Option<ScopeId>, start: (usize, usize)) -> Self {
+        Self {
+            identifiers: BTreeMap::new(),
+            parent,
+            start,
+            end: None,
+        }
+    }
+
+    pub fn get(&self, id: String) -> Option<SymbolId> {
+        self.identifiers.get(id.as_str()).copied()
+    }
+
+    pub fn insert(&mut self, id: String, symbol: SymbolId) {
+        self.identifiers.insert(id, symbol);
+    }
+
+    /// Names bound in the scope and their symbols, in name order
+    pub fn identifiers(&self) -> impl Iterator<Item = (&str, SymbolId)> {
+        self.identifiers
+            .iter()
+            .map(|(id, symbol)| (id.as_str(), *symbol))
+    }
+
+    pub fn contains(&self, pos: (usize, usize)) -> bool {
+        pos >= self.start && self.end.is_none_or(|end| pos <= end)
+    }
+}
+
+impl fmt::Debug for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope")
+            .field("identifiers", &self.identifiers)
+            .field("parent", &self.parent)
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .finish()
+    }
+}
+
+/// Every scope and symbol seen during analysis. Scopes are kept after
+/// they are closed, so consumers can ask what was visible at a position
+/// once the whole program is analyzed.
+#[derive(Clone)]
+pub struct ScopeTree {
+    pub(crate) scopes: Vec<Scope>,
+    pub(crate) symbols: Vec<Symbol>,
+    // Array and record types by name, they don't belong to a scope
+    pub(crate) types: BTreeMap<String, TypeDef>,
+}
+
+impl Default for ScopeTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScopeTree {
     pub fn new() -> Self {
         Self {
-            identifiers: HashMap::new(),
+            scopes: Vec::from([Scope::new(None, (0, 0))]),
+            symbols: Vec::new(),
+            types: BTreeMap::new(),
         }
     }
-    pub fn default() -> Self {
-        let identifiers = HashMap::from([
-            ("integer".into(), Usage::Type(None)),
-            ("real".into(), Usage::Type(None)),
-            ("char".into(), Usage::Type(None)),
-            ("boolean".into(), Usage::Type(None)),
-            ("true".into(), Usage::Constant("boolean".into())),
-            ("false".into(), Usage::Constant("boolean".into())),
-        ]);
-
-        Self { identifiers }
-    }
-
-    pub fn get(&self, id: String) -> Option<&Usage> {
-        self.identifiers.get(id.as_str())
-    }
-
-    pub fn insert(&mut self, id: &Identifier, usage: Usage) {
-        if let Identifier {
-            id:
-                Token {
-                    token: TokenType::Identifier(s),
-                    ..
-                },
-            ..
-        } = id
-        {
-            self.identifiers.insert(s.clone(), usage);
+
+    pub fn scope(&self, scope: ScopeId) -> &Scope {
+        &self.scopes[scope.0]
+    }
+
+    pub fn symbol(&self, symbol: SymbolId) -> &Symbol {
+        &self.symbols[symbol.0]
+    }
+
+    pub fn type_def(&self, type_name: &str) -> Option<&TypeDef> {
+        self.types.get(type_name)
+    }
+
+    /// Innermost scope whose region contains the position
+    pub fn scope_at(&self, pos: (usize, usize)) -> ScopeId {
+        // Nested scopes are always created after their parents,
+        // so the last matching one is the innermost
+        let index = self
+            .scopes
+            .iter()
+            .rposition(|scope| scope.contains(pos))
+            .unwrap_or(0);
+
+        ScopeId(index)
+    }
+
+    pub fn stats(&self) -> ScopeStats {
+        let mut stats = ScopeStats {
+            scopes: self.scopes.len(),
+            symbols: self.symbols.len(),
+            max_depth: 0,
+            largest_scope: 0,
+        };
+
+        for scope in self.scopes.iter() {
+            let mut depth = 0;
+            let mut parent = scope.parent;
+
+            while let Some(p) = parent {
+                depth += 1;
+                parent = self.scope(p).parent;
+            }
+
+            stats.max_depth = stats.max_depth.max(depth);
+            stats.largest_scope = stats.largest_scope.max(scope.identifiers.len());
         }
+
+        stats
+    }
+
+    /// Symbol the name refers to inside `scope`
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<SymbolId> {
+        let mut current = Some(scope);
+
+        while let Some(scope_id) = current {
+            let scope = self.scope(scope_id);
+
+            if let Some(symbol) = scope.identifiers.get(name) {
+                return Some(*symbol);
+            }
+            current = scope.parent;
+        }
+
+        None
+    }
+
+    /// Follows type aliases visible inside `scope` down to the builtin or spelled out type
+    pub fn resolve_type(&self, scope: ScopeId, type_name: &str) -> String {
+        let mut name = type_name.to_string();
+        let mut seen = BTreeSet::new();
+
+        while let Some(symbol) = self.lookup(scope, &name) {
+            match &self.symbol(symbol).usage {
+                Usage::Type(Some(parent)) if seen.insert(name.clone()) => name = parent.clone(),
+                _ => break,
+            }
+        }
+
+        name
+    }
+
+    /// Symbols declared before the position and visible there, sorted by name. Inner
+    /// declarations shadow outer ones, once they have been made.
+    pub fn visible_at(&self, pos: (usize, usize)) -> Vec<(String, SymbolId)> {
+        let mut visible: BTreeMap<String, SymbolId> = BTreeMap::new();
+        let mut current = Some(self.scope_at(pos));
+
+        while let Some(scope_id) = current {
+            let scope = self.scope(scope_id);
+
+            for (name, symbol) in scope.identifiers.iter() {
+                if self.symbol(*symbol).pos < pos {
+                    visible.entry(name.clone()).or_insert(*symbol);
+                }
+            }
+
+            current = scope.parent;
+        }
+
+        visible.into_iter().collect()
+    }
+}
+
+impl fmt::Debug for ScopeTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScopeTree")
+            .field("scopes", &self.scopes)
+            .field("symbols", &self.symbols)
+            .field("types", &self.types)
+            .finish()
+    }
+}
+
+pub struct ScopeStats {
+    pub scopes: usize,
+    pub symbols: usize,
+    // Scope of builtins has depth 0
+    pub max_depth: usize,
+    pub largest_scope: usize,
+}
+
+impl fmt::Display for ScopeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Scopes: {}", self.scopes)?;
+        writeln!(f, "Symbols: {}", self.symbols)?;
+        writeln!(f, "Max scope depth: {}", self.max_depth)?;
+        write!(f, "Largest scope: {} identifiers", self.largest_scope)
     }
 }
@@ -0,0 +1,126 @@
+use crate::error::Diagnostic;
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+// Place in the source and what is written under it, `^` for the diagnostic
+// itself and `-` for its labels
+struct Mark<'a> {
+    pos: (usize, usize),
+    underline: char,
+    text: &'a str,
+    color: &'a str,
+}
+
+/// Source line of `pos` with its line number and a caret under the column,
+/// followed by `label` if it isn't empty:
+///
+/// ```text
+///   |
+/// 3 |   x := 'a';
+///   |        ^ a char
+/// ```
+///
+/// Returns an empty string when `pos` is outside of `source`. With `color`
+/// the output has ANSI escape codes, the same ones the CLI prints.
+pub fn render_span(source: &str, pos: (usize, usize), label: &str, color: bool) -> String {
+    let mark = Mark {
+        pos,
+        underline: '^',
+        text: label,
+        color: RED,
+    };
+    render_marks(source, &[mark], color)
+}
+
+/// Diagnostic as the CLI prints it with `--snippets`: the headline, the source
+/// lines of its position and labels, then the notes
+pub fn render_diagnostic(source: &str, diagnostic: &Diagnostic, color: bool) -> String {
+    let severity = if diagnostic.is_error() { RED } else { YELLOW };
+    let mut text = match color {
+        true => format!("{}{}{}", severity, diagnostic.headline(), RESET),
+        false => diagnostic.headline(),
+    };
+
+    // The lines of included files aren't in `source`
+    if diagnostic.has_position() && diagnostic.file().is_none() {
+        let mut marks = vec![Mark {
+            pos: diagnostic.pos(),
+            underline: '^',
+            text: "",
+            color: severity,
+        }];
+        marks.extend(diagnostic.labels().iter().map(|(pos, label)| Mark {
+            pos: *pos,
+            underline: '-',
+            text: label,
+            color: BLUE,
+        }));
+
+        let snippet = render_marks(source, &marks, color);
+        if !snippet.is_empty() {
+            text.push('\n');
+            text.push_str(snippet.trim_end_matches('\n'));
+        }
+    }
+
+    for note in diagnostic.notes() {
+        text.push_str(&format!("\n    note: {}", note));
+    }
+
+    text
+}
+
+// Marks in line order, marks on the same line share its copy
+fn render_marks(source: &str, marks: &[Mark], color: bool) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut marks: Vec<&Mark> = marks
+        .iter()
+        .filter(|m| m.pos.0 >= 1 && m.pos.0 <= lines.len())
+        .collect();
+    marks.sort_by_key(|m| m.pos);
+
+    let width = match marks.last() {
+        Some(m) => m.pos.0.to_string().len(),
+        None => return String::new(),
+    };
+    let paint = |code: &str, text: &str| match color {
+        true => format!("{}{}{}", code, text, RESET),
+        false => text.to_string(),
+    };
+    let gutter = |number: &str| paint(BLUE, &format!("{:>width$} |", number, width = width));
+
+    let mut text = format!("{}\n", gutter(""));
+    let mut shown = None;
+
+    for mark in marks {
+        let line = lines[mark.pos.0 - 1];
+        if shown != Some(mark.pos.0) {
+            text.push_str(&format!("{} {}\n", gutter(&mark.pos.0.to_string()), line));
+            shown = Some(mark.pos.0);
+        }
+
+        // Tabs are copied, so the caret lines up however wide they are shown
+        let indent: String = line
+            .chars()
+            .chain(std::iter::repeat(' '))
+            .take(mark.pos.1.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underline = match mark.text.is_empty() {
+            true => mark.underline.to_string(),
+            false => format!("{} {}", mark.underline, mark.text),
+        };
+
+        text.push_str(&format!(
+            "{} {}{}\n",
+            gutter(""),
+            indent,
+            paint(mark.color, &underline)
+        ));
+    }
+
+    text
+}
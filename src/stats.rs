@@ -0,0 +1,177 @@
+use crate::syntax::*;
+use crate::visitor::*;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Size of a compilation: tokens, AST nodes by kind, symbols and nesting
+pub struct Stats {
+    pub tokens: usize,
+    pub nodes: BTreeMap<&'static str, usize>,
+    pub symbols: usize,
+    // Deepest chain of nested statements and parenthesized expressions
+    pub max_statement_depth: usize,
+    pub max_expression_depth: usize,
+}
+
+impl Stats {
+    pub fn collect(program: &Program, tokens: usize) -> Self {
+        let mut collector = StatsCollector {
+            stats: Stats {
+                tokens,
+                nodes: BTreeMap::new(),
+                // Builtins don't count
+                symbols: program
+                    .scope_tree()
+                    .symbols
+                    .iter()
+                    .filter(|s| s.pos != (0, 0))
+                    .count(),
+                max_statement_depth: 0,
+                max_expression_depth: 0,
+            },
+            statement_depth: 0,
+            expression_depth: 0,
+        };
+
+        collector.visit_program(program);
+        collector.stats
+    }
+}
+
+struct StatsCollector {
+    stats: Stats,
+    statement_depth: usize,
+    expression_depth: usize,
+}
+
+impl StatsCollector {
+    fn count(&mut self, kind: &'static str) {
+        *self.stats.nodes.entry(kind).or_insert(0) += 1;
+    }
+}
+
+impl Visitor for StatsCollector {
+    fn visit_program(&mut self, p: &Program) {
+        self.count("program");
+        walk_program(self, p);
+    }
+
+    fn visit_const_declaration(&mut self, d: &ConstDeclaration) {
+        self.count("const declaration");
+        self.visit_expression(&d.value);
+    }
+
+    fn visit_type_declaration(&mut self, d: &TypeDeclaration) {
+        self.count("type declaration");
+        self.visit_type_spec(&d.parent);
+    }
+
+    fn visit_var_declaration(&mut self, d: &VarDeclaration) {
+        self.count("var declaration");
+        self.visit_type_spec(&d.type_name);
+    }
+
+    fn visit_type_spec(&mut self, t: &TypeSpec) {
+        match t {
+            TypeSpec::Array(_) => self.count("array type"),
+            TypeSpec::Record(_) => self.count("record type"),
+            TypeSpec::Set(_) => self.count("set type"),
+            TypeSpec::Enum(_) => self.count("enumerated type"),
+            TypeSpec::Pointer(_) => self.count("pointer type"),
+            TypeSpec::String(_) => self.count("bounded string type"),
+            TypeSpec::Subrange(_) => self.count("subrange type"),
+            TypeSpec::Named(_) => (),
+        }
+        walk_type_spec(self, t);
+    }
+
+    fn visit_field_declaration(&mut self, f: &FieldDeclaration) {
+        self.count("field declaration");
+        self.visit_type_spec(&f.type_name);
+    }
+
+    fn visit_variant_part(&mut self, p: &VariantPart) {
+        self.count("variant part");
+        walk_variant_part(self, p);
+    }
+
+    fn visit_procedure_declaration(&mut self, d: &ProcedureDeclaration) {
+        self.count(match (d.forward, &d.result_type) {
+            (true, _) => "forward declaration",
+            (false, Some(_)) => "function declaration",
+            (false, None) => "procedure declaration",
+        });
+        walk_procedure_declaration(self, d);
+    }
+
+    fn visit_formal_parameter(&mut self, _p: &FormalParameter) {
+        self.count("formal parameter");
+    }
+
+    fn visit_compound(&mut self, c: &Compound) {
+        self.count("compound");
+        walk_compound(self, c);
+    }
+
+    fn visit_statement(&mut self, s: &Statement) {
+        self.count(match s {
+            Statement::Simple(_) => "assignment",
+            Statement::Cond(_) => "if statement",
+            Statement::While(_) => "while loop",
+            Statement::Repeat(_) => "repeat loop",
+            Statement::For(_) => "for loop",
+            Statement::Case(_) => "case statement",
+            Statement::Call(_) => "procedure call",
+            Statement::Compound(_) => "compound statement",
+            Statement::Empty => "empty statement",
+        });
+
+        self.statement_depth += 1;
+        self.stats.max_statement_depth = self.stats.max_statement_depth.max(self.statement_depth);
+        walk_statement(self, s);
+        self.statement_depth -= 1;
+    }
+
+    fn visit_expression(&mut self, e: &Expression) {
+        if let Expression::Relational(_) = e {
+            self.count("relational expression");
+        }
+        walk_expression(self, e);
+    }
+
+    fn visit_simple_expression(&mut self, e: &SimpleExpression) {
+        self.count("simple expression");
+
+        self.expression_depth += 1;
+        self.stats.max_expression_depth =
+            self.stats.max_expression_depth.max(self.expression_depth);
+        walk_simple_expression(self, e);
+        self.expression_depth -= 1;
+    }
+
+    fn visit_term(&mut self, t: &Term) {
+        self.count("term");
+        walk_term(self, t);
+    }
+
+    fn visit_factor(&mut self, f: &Factor) {
+        self.count("factor");
+        walk_factor(self, f);
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Tokens: {}", self.tokens)?;
+        writeln!(f, "Symbols: {}", self.symbols)?;
+        writeln!(f, "Max statement nesting: {}", self.max_statement_depth)?;
+        writeln!(f, "Max expression nesting: {}", self.max_expression_depth)?;
+        write!(f, "AST nodes:")?;
+
+        for (kind, count) in self.nodes.iter() {
+            write!(f, "\n    {}: {}", kind, count)?;
+        }
+
+        Ok(())
+    }
+}
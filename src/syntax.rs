@@ -1,19 +1,46 @@
+use crate::scope::{ScopeTree, SymbolId};
 use crate::token::{Token, TokenType};
 use std::fmt;
 
+#[derive(Clone)]
 pub enum Factor {
     Integer(Token),
     Real(Token),
+    // One character string literal or `#code`
+    Char(Token),
+    // Any other string literal
+    String(Token),
     Identifier(Identifier),
-    Expression(Box<SimpleExpression>),
+    // Function call
+    Call(Call),
+    // Array element, record field or pointer target
+    Selected(SelectedVariable),
+    // `[1, 3..5]`
+    Set(SetConstructor),
+    // `@x`, pointer to a variable
+    Address(SelectedVariable),
+    Nil(Token),
+    // `not x`, boolean (or with `bitwise_not` integer) negation
+    Not(Box<Factor>),
+    // `(a + b)`, or `(a = b)` which is boolean
+    Expression(Box<Expression>),
 }
 
+#[derive(Clone)]
 pub enum AdditiveOp {
     Plus,
     Minus,
     Or,
 }
 
+// Sign in front of the first term of a simple expression
+#[derive(Clone)]
+pub enum Sign {
+    Plus,
+    Minus,
+}
+
+#[derive(Clone)]
 pub enum MultiplicativeOp {
     Mul,
     Div,
@@ -21,6 +48,7 @@ pub enum MultiplicativeOp {
     And,
 }
 
+#[derive(Clone)]
 pub enum RelationalOp {
     Less,
     Bigger,
@@ -28,14 +56,22 @@ pub enum RelationalOp {
     BiggerEq,
     Eq,
     UnEq,
+    // Membership of the left operand in the set on the right
+    In,
 }
 
 #[derive(Clone)]
 pub struct Identifier {
     pub(crate) id: Token,
+    // Filled in by the analyzer once the name is declared or resolved
+    pub(crate) symbol: Option<SymbolId>,
 }
 
 impl Identifier {
+    pub fn new(id: Token) -> Self {
+        Self { id, symbol: None }
+    }
+
     pub fn get_id(&self) -> String {
         match &self.id.token {
             TokenType::Identifier(s) => s.clone(),
@@ -44,17 +80,38 @@ impl Identifier {
     }
 }
 
+impl Factor {
+    /// Position of the literal or identifier the factor starts with
+    pub fn pos(&self) -> (usize, usize) {
+        match self {
+            Factor::Integer(t) | Factor::Real(t) | Factor::Char(t) | Factor::String(t) => t.pos,
+            Factor::Identifier(id) => id.id.pos,
+            Factor::Call(c) => c.name.id.pos,
+            Factor::Selected(v) => v.name.id.pos,
+            Factor::Set(s) => s.pos,
+            Factor::Address(v) => v.name.id.pos,
+            Factor::Nil(t) => t.pos,
+            Factor::Not(f) => f.pos(),
+            Factor::Expression(e) => e.pos(),
+        }
+    }
+}
+
 pub struct VarAssignment {
     pub(crate) name: Box<Identifier>,
+    // Assigns to an array element or record field when not empty
+    pub(crate) selectors: Vec<Selector>,
     pub(crate) value: Box<Expression>,
 }
 
+#[derive(Clone)]
 pub struct Term {
     pub(crate) factor: Box<Factor>,
     pub(crate) sub_term: Option<Box<SubTerm>>,
     pub(crate) term_type: String,
 }
 
+#[derive(Clone)]
 pub struct SubTerm {
     pub(crate) op: MultiplicativeOp,
     pub(crate) factor: Box<Factor>,
@@ -62,6 +119,7 @@ pub struct SubTerm {
     pub(crate) sub_term: Option<Box<SubTerm>>,
 }
 
+#[derive(Clone)]
 pub struct SubExpression {
     pub(crate) op: Box<AdditiveOp>,
     pub(crate) term: Box<Term>,
@@ -69,23 +127,105 @@ pub struct SubExpression {
     pub(crate) sub_expr: Option<Box<SubExpression>>,
 }
 
+// A long chain like `1 + 1 + ... + 1` is dropped link by link, not by one nested
+// call per operator
+impl Drop for SubTerm {
+    fn drop(&mut self) {
+        let mut next = self.sub_term.take();
+        while let Some(mut link) = next {
+            next = link.sub_term.take();
+        }
+    }
+}
+
+impl Drop for SubExpression {
+    fn drop(&mut self) {
+        let mut next = self.sub_expr.take();
+        while let Some(mut link) = next {
+            next = link.sub_expr.take();
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct SimpleExpression {
+    // `-a * b + c` negates `a * b` only
+    pub(crate) sign: Option<Sign>,
     pub(crate) term: Box<Term>,
     pub(crate) sub_expr: Option<SubExpression>,
     pub(crate) expr_type: String,
 }
 
+impl SimpleExpression {
+    /// Identifier if the expression is nothing but a variable or constant
+    pub fn as_identifier(&self) -> Option<&Identifier> {
+        if self.sign.is_some() {
+            return None;
+        }
+
+        match (&*self.term.factor, &self.term.sub_term, &self.sub_expr) {
+            (Factor::Identifier(id), None, None) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Variable the expression is nothing but, array elements and record fields included
+    pub fn as_variable(&self) -> Option<&Identifier> {
+        if self.sign.is_some() {
+            return None;
+        }
+
+        match (&*self.term.factor, &self.term.sub_term, &self.sub_expr) {
+            (Factor::Identifier(id), None, None) => Some(id),
+            (Factor::Selected(v), None, None) => Some(&v.name),
+            _ => None,
+        }
+    }
+
+    /// Factor if the expression is nothing but one, e.g. a call or `(a + b)`
+    pub fn as_factor(&self) -> Option<&Factor> {
+        match (&self.sign, &self.term.sub_term, &self.sub_expr) {
+            (None, None, None) => Some(&self.term.factor),
+            _ => None,
+        }
+    }
+
+    pub fn pos(&self) -> (usize, usize) {
+        self.term.factor.pos()
+    }
+}
+
+#[derive(Clone)]
 pub struct RelationalExpression {
     pub(crate) first: Box<SimpleExpression>,
     pub(crate) op: Box<RelationalOp>,
     pub(crate) second: Box<SimpleExpression>,
 }
 
+#[derive(Clone)]
 pub enum Expression {
     Simple(Box<SimpleExpression>),
     Relational(Box<RelationalExpression>),
 }
 
+impl Expression {
+    pub fn pos(&self) -> (usize, usize) {
+        match self {
+            Expression::Simple(s) => s.pos(),
+            Expression::Relational(r) => r.first.pos(),
+        }
+    }
+}
+
+pub struct ConstSection {
+    pub(crate) constants: Vec<ConstDeclaration>,
+}
+
+pub struct ConstDeclaration {
+    pub(crate) id: Identifier,
+    pub(crate) value: Box<Expression>,
+}
+
 pub struct TypeSection {
     pub(crate) types: Vec<TypeDeclaration>,
 }
@@ -98,27 +238,301 @@ pub enum Statement {
     Simple(VarAssignment),
     Cond(IfStatement),
     While(WhileLoop),
+    Repeat(RepeatStatement),
+    For(ForStatement),
+    Case(CaseStatement),
+    Call(Call),
+    Compound(Compound),
+    Empty,
 }
 
 pub struct TypeDeclaration {
     pub(crate) id: Identifier,
-    pub(crate) parent: Identifier,
+    pub(crate) parent: TypeSpec,
 }
 
 pub struct VarDeclaration {
     pub(crate) id: Identifier,
-    pub(crate) type_name: Identifier,
+    pub(crate) type_name: TypeSpec,
+}
+
+/// Type as written in a declaration
+#[derive(Clone)]
+pub enum TypeSpec {
+    Named(Identifier),
+    Array(Box<ArrayType>),
+    Record(RecordType),
+    Set(Box<SetType>),
+    Enum(EnumType),
+    Pointer(PointerType),
+    String(Box<StringType>),
+    Subrange(Box<SubrangeType>),
+}
+
+impl TypeSpec {
+    /// Name the analyzer knows the type by
+    pub fn type_name(&self) -> String {
+        match self {
+            TypeSpec::Named(id) => id.get_id(),
+            TypeSpec::Array(a) => a.type_name.clone(),
+            TypeSpec::Record(r) => r.type_name.clone(),
+            TypeSpec::Set(s) => s.type_name.clone(),
+            TypeSpec::Enum(e) => e.type_name.clone(),
+            TypeSpec::Pointer(p) => p.type_name.clone(),
+            TypeSpec::String(s) => s.type_name.clone(),
+            TypeSpec::Subrange(s) => s.type_name.clone(),
+        }
+    }
+
+    pub fn pos(&self) -> (usize, usize) {
+        match self {
+            TypeSpec::Named(id) => id.id.pos,
+            TypeSpec::Array(a) => a.index.pos(),
+            TypeSpec::Record(r) => r.pos,
+            TypeSpec::Set(s) => s.pos,
+            TypeSpec::Enum(e) => e.pos,
+            TypeSpec::Pointer(p) => p.target.id.pos,
+            TypeSpec::String(s) => s.name.id.pos,
+            TypeSpec::Subrange(s) => s.low.pos(),
+        }
+    }
+}
+
+/// `low..high` of an ordinal type, e.g. `1..10`, `'a'..'z'` or `false..true`
+#[derive(Clone)]
+pub struct SubrangeType {
+    pub(crate) low: SimpleExpression,
+    pub(crate) high: SimpleExpression,
+    // Filled in by the analyzer once the bounds are evaluated
+    pub(crate) type_name: String,
+}
+
+/// `array[index] of element`, the index is an ordinal type: `1..10`, `boolean`, a
+/// type name or an enumeration. `array[a..b, c..d]` is read as an array of arrays.
+#[derive(Clone)]
+pub struct ArrayType {
+    pub(crate) index: TypeSpec,
+    pub(crate) element: TypeSpec,
+    // Filled in by the analyzer once the bounds are evaluated
+    pub(crate) type_name: String,
+}
+
+/// `set of element`, the element type must be ordinal
+#[derive(Clone)]
+pub struct SetType {
+    pub(crate) element: TypeSpec,
+    // Filled in by the analyzer once the element type is resolved
+    pub(crate) type_name: String,
+    // SET keyword
+    pub(crate) pos: (usize, usize),
+}
+
+/// `string[length]`, a string of at most `length` characters (Turbo Pascal)
+#[derive(Clone)]
+pub struct StringType {
+    pub(crate) name: Identifier,
+    pub(crate) length: SimpleExpression,
+    // Filled in by the analyzer once the length is known
+    pub(crate) type_name: String,
+}
+
+/// `^T`, the target may be declared later in the same type section
+#[derive(Clone)]
+pub struct PointerType {
+    pub(crate) target: Identifier,
+    pub(crate) type_name: String,
+}
+
+/// `(id, id, ...)`, each identifier is a constant of the new type
+#[derive(Clone)]
+pub struct EnumType {
+    pub(crate) values: Vec<Identifier>,
+    // Filled in by the analyzer once the enumerators are declared
+    pub(crate) type_name: String,
+    // Opening brace
+    pub(crate) pos: (usize, usize),
+}
+
+/// `record id : type; ... end`, the fields may end with a variant part
+#[derive(Clone)]
+pub struct RecordType {
+    pub(crate) fields: Vec<FieldDeclaration>,
+    pub(crate) variant: Option<VariantPart>,
+    // Filled in by the analyzer once the field types are known
+    pub(crate) type_name: String,
+    // RECORD keyword
+    pub(crate) pos: (usize, usize),
+}
+
+/// Fields aren't symbols, only the record's type knows them
+#[derive(Clone)]
+pub struct FieldDeclaration {
+    pub(crate) id: Identifier,
+    pub(crate) type_name: TypeSpec,
+}
+
+/// `case tag : type of labels : (fields); ...`, with only the type when the tag
+/// has no name. The variants share storage, only one of them is in use.
+#[derive(Clone)]
+pub struct VariantPart {
+    pub(crate) tag: Option<Identifier>,
+    // An ordinal type identifier, the labels are constants of it
+    pub(crate) tag_type: Identifier,
+    pub(crate) variants: Vec<Variant>,
+    // CASE keyword
+    pub(crate) pos: (usize, usize),
+}
+
+/// `labels : (fields)`, the fields may end with a nested variant part
+#[derive(Clone)]
+pub struct Variant {
+    pub(crate) labels: Vec<CaseLabel>,
+    pub(crate) fields: Vec<FieldDeclaration>,
+    pub(crate) variant: Option<Box<VariantPart>>,
+}
+
+/// Array element, record field or pointer target, `a[i].x^`. `a[i, j]` and
+/// `a[i][j]` both have two index selectors.
+#[derive(Clone)]
+pub struct SelectedVariable {
+    pub(crate) name: Identifier,
+    pub(crate) selectors: Vec<Selector>,
+}
+
+/// `[a, b..c]`, all elements are of the same ordinal type. `[]` is the empty set.
+#[derive(Clone)]
+pub struct SetConstructor {
+    pub(crate) elements: Vec<SetElement>,
+    // Filled in by the analyzer, `EMPTY_SET` for `[]`
+    pub(crate) set_type: String,
+    // Opening bracket
+    pub(crate) pos: (usize, usize),
+}
+
+/// Single value, or every value from `low` to `high`
+#[derive(Clone)]
+pub struct SetElement {
+    pub(crate) low: Expression,
+    pub(crate) high: Option<Expression>,
+}
+
+#[derive(Clone)]
+pub enum Selector {
+    Index(Expression),
+    Field(Identifier),
+    // `^` at the position
+    Deref((usize, usize)),
 }
 
 pub struct VarSection {
     pub(crate) declarations: Vec<VarDeclaration>,
 }
 
+/// Procedure, or function when it has a result type
+pub struct ProcedureDeclaration {
+    pub(crate) id: Identifier,
+    pub(crate) params: Vec<FormalParameter>,
+    pub(crate) result_type: Option<Identifier>,
+    pub(crate) const_section: Option<Box<ConstSection>>,
+    pub(crate) type_section: Option<Box<TypeSection>>,
+    pub(crate) var_section: Option<Box<VarSection>>,
+    // Nested procedures, visible only inside this one
+    pub(crate) procedures: Vec<ProcedureDeclaration>,
+    pub(crate) compound: Box<Compound>,
+    // `forward;` instead of a block, sections and compound are then empty
+    pub(crate) forward: bool,
+    // Body of a forward declared routine written without the heading, `params`
+    // and `result_type` are those of the forward declaration
+    pub(crate) short_heading: bool,
+}
+
+#[derive(Clone)]
+pub struct FormalParameter {
+    pub(crate) id: Identifier,
+    pub(crate) type_name: Identifier,
+    // VAR parameter, passed by reference
+    pub(crate) by_reference: bool,
+}
+
+#[derive(Clone)]
+pub struct Call {
+    pub(crate) name: Identifier,
+    pub(crate) arguments: Vec<Expression>,
+    // Field widths of write and writeln arguments, by argument index
+    pub(crate) formats: Vec<(usize, WriteFormat)>,
+}
+
+/// `x:8` writes `x` right-aligned in 8 characters, `x:8:2` a real with 2 decimals
+#[derive(Clone)]
+pub struct WriteFormat {
+    pub(crate) width: Expression,
+    pub(crate) decimals: Option<Expression>,
+}
+
 pub struct Program {
     pub(crate) identifier: Identifier,
+    // `program p(input, output)`, files the program works with
+    pub(crate) parameters: Vec<Identifier>,
+    // `uses Crt, Dos;`
+    pub(crate) units: Vec<Identifier>,
+    pub(crate) const_section: Option<Box<ConstSection>>,
     pub(crate) var_section: Option<Box<VarSection>>,
     pub(crate) type_section: Option<Box<TypeSection>>,
+    pub(crate) procedures: Vec<ProcedureDeclaration>,
     pub(crate) compound: Box<Compound>,
+    pub(crate) scope_tree: ScopeTree,
+    // Parser recovered from errors, so parts of the tree are missing
+    pub(crate) has_errors: bool,
+}
+
+impl Program {
+    pub fn has_errors(&self) -> bool {
+        self.has_errors
+    }
+
+    pub fn scope_tree(&self) -> &ScopeTree {
+        &self.scope_tree
+    }
+}
+
+/// Separately compiled unit: the interface declares what programs using it see,
+/// the implementation holds the routine bodies and private declarations
+pub struct Unit {
+    pub(crate) identifier: Identifier,
+    pub(crate) interface: UnitPart,
+    pub(crate) implementation: UnitPart,
+    // `begin ... end.` run before the program using the unit
+    pub(crate) initialization: Option<Box<Compound>>,
+    // Names the interface declares, what a program using the unit sees
+    pub(crate) exports: Vec<(String, SymbolId)>,
+    pub(crate) scope_tree: ScopeTree,
+    pub(crate) has_errors: bool,
+}
+
+impl Unit {
+    pub fn has_errors(&self) -> bool {
+        self.has_errors
+    }
+
+    pub fn scope_tree(&self) -> &ScopeTree {
+        &self.scope_tree
+    }
+}
+
+/// Interface or implementation section of a unit. Routines of the interface
+/// are headings only.
+pub struct UnitPart {
+    pub(crate) units: Vec<Identifier>,
+    pub(crate) const_section: Option<Box<ConstSection>>,
+    pub(crate) type_section: Option<Box<TypeSection>>,
+    pub(crate) var_section: Option<Box<VarSection>>,
+    pub(crate) procedures: Vec<ProcedureDeclaration>,
+}
+
+/// What a source file compiles to
+pub enum Module {
+    Program(Program),
+    Unit(Unit),
 }
 
 pub struct IfStatement {
@@ -132,6 +546,158 @@ pub struct WhileLoop {
     pub(crate) statement: Box<Statement>,
 }
 
+pub struct RepeatStatement {
+    pub(crate) statements: Vec<Statement>,
+    pub(crate) condition: Box<Expression>,
+}
+
+pub struct ForStatement {
+    pub(crate) variable: Identifier,
+    pub(crate) start: Box<Expression>,
+    pub(crate) end: Box<Expression>,
+    // Counts down instead of up
+    pub(crate) downto: bool,
+    pub(crate) statement: Box<Statement>,
+}
+
+pub struct CaseStatement {
+    pub(crate) selector: Box<Expression>,
+    pub(crate) branches: Vec<CaseBranch>,
+    // Statements after ELSE, run when no label matches
+    pub(crate) else_statements: Option<Vec<Statement>>,
+}
+
+pub struct CaseBranch {
+    pub(crate) labels: Vec<CaseLabel>,
+    pub(crate) statement: Statement,
+}
+
+/// Single constant or a `low..high` range
+#[derive(Clone)]
+pub struct CaseLabel {
+    pub(crate) low: SimpleExpression,
+    pub(crate) high: Option<SimpleExpression>,
+}
+
+impl ProcedureDeclaration {
+    /// Heading followed by `forward;`, the block comes with a later declaration
+    pub(crate) fn forward(
+        id: Identifier,
+        params: Vec<FormalParameter>,
+        result_type: Option<Identifier>,
+    ) -> Self {
+        Self {
+            id,
+            params,
+            result_type,
+            const_section: None,
+            type_section: None,
+            var_section: None,
+            procedures: Vec::new(),
+            compound: Box::new(Compound {
+                statements: Vec::new(),
+            }),
+            forward: true,
+            short_heading: false,
+        }
+    }
+}
+
+impl fmt::Debug for ProcedureDeclaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProcedureDeclaration")
+            .field("id", &self.id)
+            .field("params", &self.params)
+            .field("result_type", &self.result_type)
+            .field("const_section", &self.const_section)
+            .field("type_section", &self.type_section)
+            .field("var_section", &self.var_section)
+            .field("procedures", &self.procedures)
+            .field("compound", &self.compound)
+            .field("forward", &self.forward)
+            .field("short_heading", &self.short_heading)
+            .finish()
+    }
+}
+
+impl fmt::Debug for FormalParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormalParameter")
+            .field("id", &self.id)
+            .field("type_name", &self.type_name)
+            .field("by_reference", &self.by_reference)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Call {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Call")
+            .field("name", &self.name)
+            .field("arguments", &self.arguments)
+            .field("formats", &self.formats)
+            .finish()
+    }
+}
+
+impl fmt::Debug for WriteFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteFormat")
+            .field("width", &self.width)
+            .field("decimals", &self.decimals)
+            .finish()
+    }
+}
+
+impl fmt::Debug for CaseStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CaseStatement")
+            .field("selector", &self.selector)
+            .field("branches", &self.branches)
+            .field("else_statements", &self.else_statements)
+            .finish()
+    }
+}
+
+impl fmt::Debug for CaseBranch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CaseBranch")
+            .field("labels", &self.labels)
+            .field("statement", &self.statement)
+            .finish()
+    }
+}
+
+impl fmt::Debug for CaseLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CaseLabel")
+            .field("low", &self.low)
+            .field("high", &self.high)
+            .finish()
+    }
+}
+
+impl fmt::Debug for ForStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ForStatement")
+            .field("variable", &self.variable)
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("downto", &self.downto)
+            .field("statement", &self.statement)
+            .finish()
+    }
+}
+
+impl fmt::Debug for RepeatStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RepeatStatement")
+            .field("statements", &self.statements)
+            .field("condition", &self.condition)
+            .finish()
+    }
+}
+
 impl fmt::Debug for WhileLoop {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("WhileLoop")
@@ -145,9 +711,41 @@ impl fmt::Debug for Program {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Program")
             .field("identifier", &self.identifier)
+            .field("parameters", &self.parameters)
+            .field("units", &self.units)
+            .field("const_section", &self.const_section)
             .field("var_section", &self.var_section)
             .field("type_section", &self.type_section)
+            .field("procedures", &self.procedures)
             .field("compound", &self.compound)
+            .field("scope_tree", &self.scope_tree)
+            .field("has_errors", &self.has_errors)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Unit")
+            .field("identifier", &self.identifier)
+            .field("interface", &self.interface)
+            .field("implementation", &self.implementation)
+            .field("initialization", &self.initialization)
+            .field("exports", &self.exports)
+            .field("scope_tree", &self.scope_tree)
+            .field("has_errors", &self.has_errors)
+            .finish()
+    }
+}
+
+impl fmt::Debug for UnitPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnitPart")
+            .field("units", &self.units)
+            .field("const_section", &self.const_section)
+            .field("type_section", &self.type_section)
+            .field("var_section", &self.var_section)
+            .field("procedures", &self.procedures)
             .finish()
     }
 }
@@ -160,6 +758,153 @@ impl fmt::Debug for VarSection {
     }
 }
 
+impl fmt::Debug for TypeSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeSpec::Named(id) => write!(f, "Named({:?})", id),
+            TypeSpec::Array(a) => write!(f, "Array({:?})", a),
+            TypeSpec::Record(r) => write!(f, "Record({:?})", r),
+            TypeSpec::Set(s) => write!(f, "Set({:?})", s),
+            TypeSpec::Enum(e) => write!(f, "Enum({:?})", e),
+            TypeSpec::Pointer(p) => write!(f, "Pointer({:?})", p),
+            TypeSpec::String(s) => write!(f, "String({:?})", s),
+            TypeSpec::Subrange(s) => write!(f, "Subrange({:?})", s),
+        }
+    }
+}
+
+impl fmt::Debug for PointerType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PointerType")
+            .field("target", &self.target)
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+impl fmt::Debug for EnumType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnumType")
+            .field("values", &self.values)
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+impl fmt::Debug for StringType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StringType")
+            .field("length", &self.length)
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+impl fmt::Debug for SetType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SetType")
+            .field("element", &self.element)
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+impl fmt::Debug for ArrayType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArrayType")
+            .field("index", &self.index)
+            .field("element", &self.element)
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+impl fmt::Debug for SubrangeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubrangeType")
+            .field("low", &self.low)
+            .field("high", &self.high)
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+impl fmt::Debug for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordType")
+            .field("fields", &self.fields)
+            .field("variant", &self.variant)
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+impl fmt::Debug for VariantPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VariantPart")
+            .field("tag", &self.tag)
+            .field("tag_type", &self.tag_type)
+            .field("variants", &self.variants)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Variant")
+            .field("labels", &self.labels)
+            .field("fields", &self.fields)
+            .field("variant", &self.variant)
+            .finish()
+    }
+}
+
+impl fmt::Debug for FieldDeclaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FieldDeclaration")
+            .field("id", &self.id)
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+impl fmt::Debug for SelectedVariable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SelectedVariable")
+            .field("name", &self.name)
+            .field("selectors", &self.selectors)
+            .finish()
+    }
+}
+
+impl fmt::Debug for SetConstructor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SetConstructor")
+            .field("elements", &self.elements)
+            .field("set_type", &self.set_type)
+            .finish()
+    }
+}
+
+impl fmt::Debug for SetElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SetElement")
+            .field("low", &self.low)
+            .field("high", &self.high)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Index(e) => write!(f, "Index({:?})", e),
+            Selector::Field(id) => write!(f, "Field({:?})", id),
+            Selector::Deref(_) => write!(f, "Deref"),
+        }
+    }
+}
+
 impl fmt::Debug for VarDeclaration {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("VarDeclaration")
@@ -181,6 +926,18 @@ impl fmt::Debug for Statement {
                 .field("value", &c)
                 .finish(),
             Statement::While(w) => f.debug_struct("WhileLoop").field("value", &w).finish(),
+            Statement::Repeat(r) => f
+                .debug_struct("RepeatStatement")
+                .field("value", &r)
+                .finish(),
+            Statement::For(l) => f.debug_struct("ForStatement").field("value", &l).finish(),
+            Statement::Case(c) => f.debug_struct("CaseStatement").field("value", &c).finish(),
+            Statement::Call(c) => f.debug_struct("Call").field("value", &c).finish(),
+            Statement::Compound(c) => f
+                .debug_struct("Compound statement")
+                .field("value", &c)
+                .finish(),
+            Statement::Empty => write!(f, "Empty statement"),
         }
     }
 }
@@ -198,7 +955,15 @@ impl fmt::Debug for Factor {
         match self {
             Factor::Integer(i) => write!(f, "Factor<Int>({:?})", i),
             Factor::Real(real) => write!(f, "Factor<Real>({:?})", real),
+            Factor::Char(c) => write!(f, "Factor<Char>({:?})", c),
+            Factor::String(s) => write!(f, "Factor<String>({:?})", s),
             Factor::Identifier(i) => write!(f, "Factor<Variable>({:?})", i),
+            Factor::Call(c) => write!(f, "Factor<Call>({:?})", c),
+            Factor::Selected(v) => write!(f, "Factor<Selected>({:?})", v),
+            Factor::Set(c) => write!(f, "Factor<Set>({:?})", c),
+            Factor::Address(v) => write!(f, "Factor<Address>({:?})", v),
+            Factor::Nil(t) => write!(f, "Factor<Nil>({:?})", t),
+            Factor::Not(operand) => write!(f, "Factor<Not>({:?})", operand),
             Factor::Expression(inner) => f
                 .debug_struct("Factor")
                 .field("expression", &inner)
@@ -210,6 +975,7 @@ impl fmt::Debug for Factor {
 impl fmt::Debug for SimpleExpression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("SimpleExpression")
+            .field("sign", &self.sign)
             .field("term", &self.term)
             .field("sub_expr", &self.sub_expr)
             .field("expr_type", &self.expr_type)
@@ -248,6 +1014,15 @@ impl fmt::Debug for AdditiveOp {
     }
 }
 
+impl fmt::Debug for Sign {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sign::Plus => write!(f, "Plus <+>"),
+            Sign::Minus => write!(f, "Minus <->"),
+        }
+    }
+}
+
 impl fmt::Debug for MultiplicativeOp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -274,6 +1049,7 @@ impl fmt::Debug for VarAssignment {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("VarAssignment")
             .field("identifier", &self.name)
+            .field("selectors", &self.selectors)
             .field("value", &self.value)
             .finish()
     }
@@ -283,6 +1059,7 @@ impl fmt::Debug for Identifier {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Identifier")
             .field("name", &self.id)
+            .field("symbol", &self.symbol)
             .finish()
     }
 }
@@ -296,6 +1073,7 @@ impl fmt::Debug for RelationalOp {
             RelationalOp::LessEq => write!(f, "<="),
             RelationalOp::Eq => write!(f, "="),
             RelationalOp::UnEq => write!(f, "<>"),
+            RelationalOp::In => write!(f, "in"),
         }
     }
 }
@@ -329,6 +1107,23 @@ impl fmt::Debug for IfStatement {
     }
 }
 
+impl fmt::Debug for ConstSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConstSection")
+            .field("constants", &self.constants)
+            .finish()
+    }
+}
+
+impl fmt::Debug for ConstDeclaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConstDeclaration")
+            .field("id", &self.id)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
 impl fmt::Debug for TypeSection {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("TypeSection")
@@ -19,6 +19,7 @@ impl Token {
                 | TokenType::LessEq
                 | TokenType::Eq
                 | TokenType::UnEq
+                | TokenType::InOp
         )
     }
     pub fn is_mul_op(&self) -> bool {
@@ -42,6 +43,28 @@ impl Token {
                 | TokenType::EndKeyword
                 | TokenType::ThenKeyword
                 | TokenType::DoKeyword
+                | TokenType::ElseKeyword
+                | TokenType::UntilKeyword
+                | TokenType::ToKeyword
+                | TokenType::DowntoKeyword
+                | TokenType::OfKeyword
+                | TokenType::Colon
+                | TokenType::Comma
+                | TokenType::DotDot
+                | TokenType::RBracket
+        )
+    }
+
+    pub fn starts_statement(&self) -> bool {
+        matches!(
+            self.token,
+            TokenType::Identifier(_)
+                | TokenType::BeginKeyword
+                | TokenType::IfKeyword
+                | TokenType::WhileKeyword
+                | TokenType::RepeatKeyword
+                | TokenType::ForKeyword
+                | TokenType::CaseKeyword
         )
     }
 }
@@ -51,6 +74,10 @@ pub enum TokenType {
     Integer(i32),
     Identifier(String),
     StringLiteral(String),
+    // `#65`, a char given by its code
+    CharCode(char),
+    // `$FF`, Turbo Pascal hexadecimal integer
+    HexInteger(i32),
     Real(f32),
     ProgramKeyword,
     VarKeyword,
@@ -61,7 +88,28 @@ pub enum TokenType {
     ThenKeyword,
     WhileKeyword,
     DoKeyword,
+    RepeatKeyword,
+    UntilKeyword,
+    ForKeyword,
+    ToKeyword,
+    DowntoKeyword,
+    CaseKeyword,
+    OfKeyword,
+    ProcedureKeyword,
+    FunctionKeyword,
     TypeKeyword,
+    ConstKeyword,
+    ArrayKeyword,
+    RecordKeyword,
+    SetKeyword,
+    NilKeyword,
+    NotOp,
+    // Set membership, `c in ['a'..'z']`
+    InOp,
+    UsesKeyword,
+    UnitKeyword,
+    InterfaceKeyword,
+    ImplementationKeyword,
     PlusOp,
     MinusOp,
     AndOp,
@@ -78,8 +126,13 @@ pub enum TokenType {
     AssignOp,
     Colon,
     Period,
+    DotDot,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
+    Caret,
+    At,
     Comma,
     Semicolon,
     Eof,
@@ -120,17 +173,46 @@ impl fmt::Display for TokenType {
             TokenType::ThenKeyword => write!(f, "'THEN' keyword"),
             TokenType::AssignOp => write!(f, "'Assign (:=)' operator"),
             TokenType::AndOp => write!(f, "AND logical operator"),
+            TokenType::InOp => write!(f, "IN set operator"),
             TokenType::OrOp => write!(f, "OR logical operator"),
             TokenType::Colon => write!(f, "Colon"),
             TokenType::Comma => write!(f, ","),
             TokenType::Semicolon => write!(f, "Semicolon"),
             TokenType::Period => write!(f, "Period sign"),
+            TokenType::DotDot => write!(f, "Range sign"),
             TokenType::TypeKeyword => write!(f, "TYPE keyword"),
+            TokenType::ConstKeyword => write!(f, "CONST keyword"),
+            TokenType::ArrayKeyword => write!(f, "ARRAY keyword"),
+            TokenType::RecordKeyword => write!(f, "RECORD keyword"),
+            TokenType::SetKeyword => write!(f, "SET keyword"),
+            TokenType::NilKeyword => write!(f, "NIL keyword"),
+            TokenType::NotOp => write!(f, "NOT operator"),
+            TokenType::UsesKeyword => write!(f, "USES keyword"),
+            TokenType::UnitKeyword => write!(f, "UNIT keyword"),
+            TokenType::InterfaceKeyword => write!(f, "INTERFACE keyword"),
+            TokenType::ImplementationKeyword => write!(f, "IMPLEMENTATION keyword"),
             TokenType::LBrace => write!(f, "("),
             TokenType::RBrace => write!(f, ")"),
+            TokenType::LBracket => write!(f, "["),
+            TokenType::RBracket => write!(f, "]"),
+            TokenType::Caret => write!(f, "^"),
+            TokenType::At => write!(f, "@"),
             TokenType::WhileKeyword => write!(f, "WHILE keyword"),
             TokenType::DoKeyword => write!(f, "DO keyword"),
-            TokenType::StringLiteral(s) => write!(f, "String literal '{}'", s),
+            TokenType::RepeatKeyword => write!(f, "REPEAT keyword"),
+            TokenType::UntilKeyword => write!(f, "UNTIL keyword"),
+            TokenType::ForKeyword => write!(f, "FOR keyword"),
+            TokenType::ToKeyword => write!(f, "TO keyword"),
+            TokenType::DowntoKeyword => write!(f, "DOWNTO keyword"),
+            TokenType::CaseKeyword => write!(f, "CASE keyword"),
+            TokenType::OfKeyword => write!(f, "OF keyword"),
+            TokenType::ProcedureKeyword => write!(f, "PROCEDURE keyword"),
+            TokenType::FunctionKeyword => write!(f, "FUNCTION keyword"),
+            TokenType::StringLiteral(s) => {
+                write!(f, "String literal '{}'", s.replace('\'', "''"))
+            }
+            TokenType::CharCode(c) => write!(f, "Character code #{}", *c as u32),
+            TokenType::HexInteger(i) => write!(f, "Hex integer ${:X}", i),
             TokenType::Real(r) => write!(f, "Real literal '{}'", r),
         }
     }
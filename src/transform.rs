@@ -0,0 +1,216 @@
+use crate::options::PrintOptions;
+use crate::printer::print_program_renamed;
+use crate::scope::{SymbolId, Usage};
+use crate::syntax::*;
+use crate::visitor::Visitor;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// Source-to-source transforms built on the printer and the symbol table
+
+/// Renames every user identifier to a meaningless one (`v1` for variables,
+/// `t2` for types…), numbered in declaration order. Builtins keep their names.
+pub fn obfuscate(p: &Program, options: &PrintOptions) -> String {
+    let mut names = HashMap::new();
+
+    for (index, symbol) in p.scope_tree().symbols.iter().enumerate() {
+        if symbol.pos == (0, 0) {
+            continue;
+        }
+
+        let prefix = match symbol.usage {
+            Usage::Constant(_) => "c",
+            Usage::Type(_) => "t",
+            Usage::Program => "p",
+            Usage::Unit => "u",
+            Usage::Variable(_) => "v",
+            Usage::Procedure(_) => "r",
+            Usage::Function(_) => "f",
+        };
+
+        names.insert(SymbolId(index), format!("{}{}", prefix, names.len() + 1));
+    }
+
+    print_program_renamed(p, &names, options)
+}
+
+/// Declaration removed by `strip_unused`
+pub struct Removed {
+    pub name: String,
+    pub kind: &'static str,
+    pub pos: (usize, usize),
+}
+
+impl fmt::Display for Removed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Removed unused {} {:?} [{}:{}]",
+            self.kind, self.name, self.pos.0, self.pos.1
+        )
+    }
+}
+
+/// Removes declarations which are never referenced. Repeats until nothing
+/// changes, since removing a variable may leave its type or a constant unused.
+pub fn strip_unused(p: &mut Program) -> Vec<Removed> {
+    let mut removed = Vec::new();
+
+    loop {
+        let mut references = References::default();
+        references.visit_program(p);
+        let before = removed.len();
+
+        strip_block(
+            &mut p.const_section,
+            &mut p.type_section,
+            &mut p.var_section,
+            &mut p.procedures,
+            &references,
+            &mut removed,
+        );
+
+        if removed.len() == before {
+            break;
+        }
+    }
+
+    removed
+}
+
+/// Declarations of the program or of one procedure, nested procedures included
+fn strip_block(
+    consts: &mut Option<Box<ConstSection>>,
+    types: &mut Option<Box<TypeSection>>,
+    vars: &mut Option<Box<VarSection>>,
+    procedures: &mut Vec<ProcedureDeclaration>,
+    references: &References,
+    removed: &mut Vec<Removed>,
+) {
+    let mut keep = |id: &Identifier, kind| {
+        let used = references.is_used(id);
+        if !used {
+            removed.push(Removed {
+                name: id.get_id(),
+                kind,
+                pos: id.id.pos,
+            });
+        }
+        used
+    };
+
+    if let Some(section) = vars {
+        section.declarations.retain(|d| keep(&d.id, "variable"));
+    }
+    if let Some(section) = types {
+        section.types.retain(|d| keep(&d.id, "type"));
+    }
+    if let Some(section) = consts {
+        section.constants.retain(|d| keep(&d.id, "constant"));
+    }
+    procedures.retain(|d| {
+        // Goes with its body, which reports the removal
+        if d.forward {
+            return references.is_used(&d.id);
+        }
+
+        let kind = match d.result_type {
+            Some(_) => "function",
+            None => "procedure",
+        };
+        keep(&d.id, kind)
+    });
+
+    for d in procedures.iter_mut() {
+        strip_block(
+            &mut d.const_section,
+            &mut d.type_section,
+            &mut d.var_section,
+            &mut d.procedures,
+            references,
+            removed,
+        );
+    }
+
+    // Empty sections are not valid Pascal
+    if vars.as_ref().is_some_and(|s| s.declarations.is_empty()) {
+        *vars = None;
+    }
+    if types.as_ref().is_some_and(|s| s.types.is_empty()) {
+        *types = None;
+    }
+    if consts.as_ref().is_some_and(|s| s.constants.is_empty()) {
+        *consts = None;
+    }
+}
+
+/// Symbols referenced anywhere except their own declaration
+#[derive(Default)]
+struct References(HashSet<SymbolId>);
+
+impl References {
+    fn is_used(&self, id: &Identifier) -> bool {
+        // Unresolved declarations are kept, there is nothing to go by
+        id.symbol.is_none_or(|s| self.0.contains(&s))
+    }
+}
+
+impl Visitor for References {
+    fn visit_const_declaration(&mut self, d: &ConstDeclaration) {
+        self.visit_expression(&d.value);
+    }
+
+    // Enumerations are kept, the code may use their values without naming the type
+    fn visit_type_declaration(&mut self, d: &TypeDeclaration) {
+        if let TypeSpec::Enum(_) = d.parent {
+            self.visit_identifier(&d.id);
+        }
+        self.visit_type_spec(&d.parent);
+    }
+
+    fn visit_var_declaration(&mut self, d: &VarDeclaration) {
+        self.visit_type_spec(&d.type_name);
+    }
+
+    // Same as the default walk without the procedure's own name.
+    // A procedure which only calls itself still counts as used.
+    fn visit_procedure_declaration(&mut self, d: &ProcedureDeclaration) {
+        for p in d.params.iter() {
+            self.visit_identifier(&p.type_name);
+        }
+
+        if let Some(result_type) = &d.result_type {
+            self.visit_identifier(result_type);
+        }
+
+        if let Some(section) = &d.const_section {
+            for c in section.constants.iter() {
+                self.visit_const_declaration(c);
+            }
+        }
+
+        if let Some(section) = &d.type_section {
+            for t in section.types.iter() {
+                self.visit_type_declaration(t);
+            }
+        }
+
+        if let Some(section) = &d.var_section {
+            for v in section.declarations.iter() {
+                self.visit_var_declaration(v);
+            }
+        }
+
+        for nested in d.procedures.iter() {
+            self.visit_procedure_declaration(nested);
+        }
+
+        self.visit_compound(&d.compound);
+    }
+
+    fn visit_identifier(&mut self, id: &Identifier) {
+        if let Some(symbol) = id.symbol {
+            self.0.insert(symbol);
+        }
+    }
+}
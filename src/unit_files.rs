@@ -0,0 +1,108 @@
+use crate::error::CompilerError;
+use crate::io::CharReader;
+use crate::lexer::Lexer;
+use crate::options::Options;
+use crate::parser::Parser;
+use crate::scope::{ScopeTree, SymbolId};
+use crate::syntax::Module;
+use std::path::{Path, PathBuf};
+
+// Units compiled from their source, for a `uses` of anything which isn't a
+// builtin unit. Only the interface is kept.
+
+/// What a program using the unit sees
+pub(crate) struct UnitInterface {
+    // Tree of the unit, it has the types of the exported symbols
+    pub(crate) tree: ScopeTree,
+    pub(crate) exports: Vec<(String, SymbolId)>,
+}
+
+/// `<name>.pas` (as written or lowercase) in the first directory of the unit path which has it
+pub(crate) fn find(name: &str, options: &Options) -> Option<PathBuf> {
+    let files = [
+        format!("{}.pas", name),
+        format!("{}.pas", name.to_ascii_lowercase()),
+    ];
+
+    options
+        .unit_path
+        .iter()
+        .flat_map(|dir| files.iter().map(move |file| dir.join(file)))
+        .find(|path| path.is_file())
+}
+
+/// Compiles unit `name` from `path`, `loading` are the units whose compilation led here
+pub(crate) fn load(
+    name: &str,
+    path: &Path,
+    options: &Options,
+    loading: &[String],
+) -> Result<UnitInterface, CompilerError> {
+    let reader = CharReader::new(path.display().to_string()).map_err(|e| {
+        CompilerError::semantic(format!(
+            "Can't read unit {:?} from {}",
+            name,
+            path.display()
+        ))
+        .with_note(e.to_string())
+    })?;
+
+    let mut parser = Parser::new(Lexer::new(reader), options).with_loading(loading.to_vec());
+    let module = parser.parse_module();
+    let mut errors: Vec<CompilerError> =
+        parser.errors.into_iter().filter(|e| e.is_error()).collect();
+
+    let unit = match module {
+        Ok(Module::Unit(unit)) => unit,
+        Ok(Module::Program(_)) => {
+            return Err(CompilerError::semantic(format!(
+                "{} is a program, not unit {:?}",
+                path.display(),
+                name
+            )))
+        }
+        Err(e) => {
+            errors.push(e);
+            return Err(unit_errors(name, path, &errors));
+        }
+    };
+
+    if !unit.identifier.get_id().eq_ignore_ascii_case(name) {
+        return Err(CompilerError::semantic(format!(
+            "{} holds unit {:?}, not {:?}",
+            path.display(),
+            unit.identifier.get_id(),
+            name
+        )));
+    }
+
+    if !errors.is_empty() {
+        return Err(unit_errors(name, path, &errors));
+    }
+
+    Ok(UnitInterface {
+        tree: unit.scope_tree,
+        exports: unit.exports,
+    })
+}
+
+// The first error tells where to look, the unit is compiled on its own for the rest.
+// Its notes come along, so errors of units used by units are traced to the source.
+fn unit_errors(name: &str, path: &Path, errors: &[CompilerError]) -> CompilerError {
+    let error = CompilerError::semantic(format!(
+        "Unit {:?} can't be used, {} has {} error(s)",
+        name,
+        path.display(),
+        errors.len()
+    ));
+
+    match errors.first() {
+        Some(first) => first
+            .notes()
+            .iter()
+            .fold(error.with_note(first.headline()), |e, note| {
+                e.with_note(note.as_str())
+            }),
+        None => error,
+    }
+}
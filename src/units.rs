@@ -0,0 +1,121 @@
+use crate::const_eval::ConstValue;
+use crate::scope::{Param, Signature, Usage};
+
+// Stand-ins for the units Turbo Pascal programs commonly use. Only the
+// declarations are here, so programs written against them type check.
+
+/// Identifier the unit declares, with the value of constants
+pub(crate) struct UnitItem {
+    pub(crate) name: &'static str,
+    pub(crate) usage: Usage,
+    pub(crate) value: Option<ConstValue>,
+}
+
+/// Items of the unit called `name` (any case), None if there is no such unit
+pub(crate) fn unit(name: &str) -> Option<Vec<UnitItem>> {
+    match name.to_ascii_lowercase().as_str() {
+        "crt" => Some(crt()),
+        "dos" => Some(dos()),
+        _ => None,
+    }
+}
+
+pub(crate) const UNIT_NAMES: &str = "Crt and Dos";
+
+fn params(types: &[&str], by_reference: bool) -> Vec<Param> {
+    types
+        .iter()
+        .map(|t| Param {
+            type_name: t.to_string(),
+            by_reference,
+        })
+        .collect()
+}
+
+fn procedure(name: &'static str, params: Vec<Param>) -> UnitItem {
+    UnitItem {
+        name,
+        usage: Usage::Procedure(Signature {
+            params,
+            result: None,
+            optional: 0,
+            rest: false,
+        }),
+        value: None,
+    }
+}
+
+fn function(name: &'static str, params: Vec<Param>, result: &str) -> UnitItem {
+    UnitItem {
+        name,
+        usage: Usage::Function(Signature {
+            params,
+            result: Some(result.into()),
+            optional: 0,
+            rest: false,
+        }),
+        value: None,
+    }
+}
+
+fn crt() -> Vec<UnitItem> {
+    const COLOURS: [&str; 16] = [
+        "black",
+        "blue",
+        "green",
+        "cyan",
+        "red",
+        "magenta",
+        "brown",
+        "lightgray",
+        "darkgray",
+        "lightblue",
+        "lightgreen",
+        "lightcyan",
+        "lightred",
+        "lightmagenta",
+        "yellow",
+        "white",
+    ];
+
+    let mut items = vec![
+        procedure("clrscr", Vec::new()),
+        procedure("clreol", Vec::new()),
+        procedure("gotoxy", params(&["integer", "integer"], false)),
+        procedure("delay", params(&["integer"], false)),
+        procedure("sound", params(&["integer"], false)),
+        procedure("nosound", Vec::new()),
+        procedure("textcolor", params(&["integer"], false)),
+        procedure("textbackground", params(&["integer"], false)),
+        function("keypressed", Vec::new(), "boolean"),
+        function("readkey", Vec::new(), "char"),
+        function("wherex", Vec::new(), "integer"),
+        function("wherey", Vec::new(), "integer"),
+    ];
+
+    for (i, name) in COLOURS.into_iter().enumerate() {
+        items.push(UnitItem {
+            name,
+            usage: Usage::Constant("integer".into()),
+            value: Some(ConstValue::Integer(i as i32)),
+        });
+    }
+
+    items
+}
+
+fn dos() -> Vec<UnitItem> {
+    vec![
+        // Year, month, day and day of week
+        procedure(
+            "getdate",
+            params(&["integer", "integer", "integer", "integer"], true),
+        ),
+        // Hour, minute, second and hundredths
+        procedure(
+            "gettime",
+            params(&["integer", "integer", "integer", "integer"], true),
+        ),
+        function("getenv", params(&["string"], false), "string"),
+    ]
+}
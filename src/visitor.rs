@@ -0,0 +1,346 @@
+use crate::syntax::*;
+
+/// Read-only traversal of the AST. Every method walks into children by
+/// default, so implementors override only the nodes they care about and
+/// call the matching `walk_*` function to keep descending.
+pub trait Visitor {
+    fn visit_program(&mut self, p: &Program) {
+        walk_program(self, p);
+    }
+
+    fn visit_const_declaration(&mut self, d: &ConstDeclaration) {
+        self.visit_identifier(&d.id);
+        self.visit_expression(&d.value);
+    }
+
+    fn visit_type_declaration(&mut self, d: &TypeDeclaration) {
+        self.visit_identifier(&d.id);
+        self.visit_type_spec(&d.parent);
+    }
+
+    fn visit_var_declaration(&mut self, d: &VarDeclaration) {
+        self.visit_identifier(&d.id);
+        self.visit_type_spec(&d.type_name);
+    }
+
+    fn visit_type_spec(&mut self, t: &TypeSpec) {
+        walk_type_spec(self, t);
+    }
+
+    fn visit_field_declaration(&mut self, f: &FieldDeclaration) {
+        self.visit_identifier(&f.id);
+        self.visit_type_spec(&f.type_name);
+    }
+
+    fn visit_variant_part(&mut self, p: &VariantPart) {
+        walk_variant_part(self, p);
+    }
+
+    fn visit_procedure_declaration(&mut self, d: &ProcedureDeclaration) {
+        walk_procedure_declaration(self, d);
+    }
+
+    fn visit_formal_parameter(&mut self, p: &FormalParameter) {
+        self.visit_identifier(&p.id);
+        self.visit_identifier(&p.type_name);
+    }
+
+    fn visit_compound(&mut self, c: &Compound) {
+        walk_compound(self, c);
+    }
+
+    fn visit_statement(&mut self, s: &Statement) {
+        walk_statement(self, s);
+    }
+
+    fn visit_case_label(&mut self, l: &CaseLabel) {
+        self.visit_simple_expression(&l.low);
+
+        if let Some(high) = &l.high {
+            self.visit_simple_expression(high);
+        }
+    }
+
+    fn visit_expression(&mut self, e: &Expression) {
+        walk_expression(self, e);
+    }
+
+    fn visit_write_format(&mut self, f: &WriteFormat) {
+        self.visit_expression(&f.width);
+
+        if let Some(decimals) = &f.decimals {
+            self.visit_expression(decimals);
+        }
+    }
+
+    fn visit_simple_expression(&mut self, e: &SimpleExpression) {
+        walk_simple_expression(self, e);
+    }
+
+    fn visit_term(&mut self, t: &Term) {
+        walk_term(self, t);
+    }
+
+    fn visit_factor(&mut self, f: &Factor) {
+        walk_factor(self, f);
+    }
+
+    fn visit_identifier(&mut self, _id: &Identifier) {}
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(v: &mut V, p: &Program) {
+    v.visit_identifier(&p.identifier);
+    for param in p.parameters.iter() {
+        v.visit_identifier(param);
+    }
+    for unit in p.units.iter() {
+        v.visit_identifier(unit);
+    }
+
+    if let Some(section) = &p.const_section {
+        for d in section.constants.iter() {
+            v.visit_const_declaration(d);
+        }
+    }
+
+    if let Some(section) = &p.type_section {
+        for d in section.types.iter() {
+            v.visit_type_declaration(d);
+        }
+    }
+
+    if let Some(section) = &p.var_section {
+        for d in section.declarations.iter() {
+            v.visit_var_declaration(d);
+        }
+    }
+
+    for d in p.procedures.iter() {
+        v.visit_procedure_declaration(d);
+    }
+
+    v.visit_compound(&p.compound);
+}
+
+pub fn walk_procedure_declaration<V: Visitor + ?Sized>(v: &mut V, d: &ProcedureDeclaration) {
+    v.visit_identifier(&d.id);
+
+    for p in d.params.iter() {
+        v.visit_formal_parameter(p);
+    }
+
+    if let Some(result_type) = &d.result_type {
+        v.visit_identifier(result_type);
+    }
+
+    if let Some(section) = &d.const_section {
+        for c in section.constants.iter() {
+            v.visit_const_declaration(c);
+        }
+    }
+
+    if let Some(section) = &d.type_section {
+        for t in section.types.iter() {
+            v.visit_type_declaration(t);
+        }
+    }
+
+    if let Some(section) = &d.var_section {
+        for var in section.declarations.iter() {
+            v.visit_var_declaration(var);
+        }
+    }
+
+    for nested in d.procedures.iter() {
+        v.visit_procedure_declaration(nested);
+    }
+
+    v.visit_compound(&d.compound);
+}
+
+pub fn walk_type_spec<V: Visitor + ?Sized>(v: &mut V, t: &TypeSpec) {
+    match t {
+        TypeSpec::Named(id) => v.visit_identifier(id),
+        TypeSpec::Array(a) => {
+            v.visit_type_spec(&a.index);
+            v.visit_type_spec(&a.element);
+        }
+        TypeSpec::Record(r) => {
+            for f in r.fields.iter() {
+                v.visit_field_declaration(f);
+            }
+            if let Some(part) = &r.variant {
+                v.visit_variant_part(part);
+            }
+        }
+        TypeSpec::Set(s) => v.visit_type_spec(&s.element),
+        TypeSpec::Pointer(p) => v.visit_identifier(&p.target),
+        TypeSpec::String(s) => {
+            v.visit_identifier(&s.name);
+            v.visit_simple_expression(&s.length);
+        }
+        TypeSpec::Enum(e) => {
+            for id in e.values.iter() {
+                v.visit_identifier(id);
+            }
+        }
+        TypeSpec::Subrange(s) => {
+            v.visit_simple_expression(&s.low);
+            v.visit_simple_expression(&s.high);
+        }
+    }
+}
+
+pub fn walk_variant_part<V: Visitor + ?Sized>(v: &mut V, p: &VariantPart) {
+    if let Some(tag) = &p.tag {
+        v.visit_identifier(tag);
+    }
+    v.visit_identifier(&p.tag_type);
+
+    for variant in p.variants.iter() {
+        for l in variant.labels.iter() {
+            v.visit_case_label(l);
+        }
+        for f in variant.fields.iter() {
+            v.visit_field_declaration(f);
+        }
+        if let Some(nested) = &variant.variant {
+            v.visit_variant_part(nested);
+        }
+    }
+}
+
+pub fn walk_compound<V: Visitor + ?Sized>(v: &mut V, c: &Compound) {
+    for s in c.statements.iter() {
+        v.visit_statement(s);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(v: &mut V, s: &Statement) {
+    match s {
+        Statement::Simple(a) => {
+            v.visit_identifier(&a.name);
+            walk_selectors(v, &a.selectors);
+            v.visit_expression(&a.value);
+        }
+        Statement::Cond(c) => {
+            v.visit_expression(&c.condition);
+            v.visit_statement(&c.statement);
+
+            if let Some(e) = &c.else_statement {
+                v.visit_statement(e);
+            }
+        }
+        Statement::While(w) => {
+            v.visit_expression(&w.condition);
+            v.visit_statement(&w.statement);
+        }
+        Statement::Repeat(r) => {
+            for s in r.statements.iter() {
+                v.visit_statement(s);
+            }
+            v.visit_expression(&r.condition);
+        }
+        Statement::For(l) => {
+            v.visit_identifier(&l.variable);
+            v.visit_expression(&l.start);
+            v.visit_expression(&l.end);
+            v.visit_statement(&l.statement);
+        }
+        Statement::Case(c) => {
+            v.visit_expression(&c.selector);
+
+            for b in c.branches.iter() {
+                for l in b.labels.iter() {
+                    v.visit_case_label(l);
+                }
+                v.visit_statement(&b.statement);
+            }
+
+            for s in c.else_statements.iter().flatten() {
+                v.visit_statement(s);
+            }
+        }
+        Statement::Call(c) => walk_call(v, c),
+        Statement::Compound(c) => v.visit_compound(c),
+        Statement::Empty => (),
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(v: &mut V, e: &Expression) {
+    match e {
+        Expression::Simple(s) => v.visit_simple_expression(s),
+        Expression::Relational(r) => {
+            v.visit_simple_expression(&r.first);
+            v.visit_simple_expression(&r.second);
+        }
+    }
+}
+
+pub fn walk_simple_expression<V: Visitor + ?Sized>(v: &mut V, e: &SimpleExpression) {
+    v.visit_term(&e.term);
+
+    let mut sub_expr = e.sub_expr.as_ref();
+    while let Some(s) = sub_expr {
+        v.visit_term(&s.term);
+        sub_expr = s.sub_expr.as_deref();
+    }
+}
+
+pub fn walk_term<V: Visitor + ?Sized>(v: &mut V, t: &Term) {
+    v.visit_factor(&t.factor);
+
+    let mut sub_term = t.sub_term.as_deref();
+    while let Some(s) = sub_term {
+        v.visit_factor(&s.factor);
+        sub_term = s.sub_term.as_deref();
+    }
+}
+
+pub fn walk_factor<V: Visitor + ?Sized>(v: &mut V, f: &Factor) {
+    match f {
+        Factor::Identifier(id) => v.visit_identifier(id),
+        Factor::Call(c) => walk_call(v, c),
+        Factor::Selected(s) | Factor::Address(s) => {
+            v.visit_identifier(&s.name);
+            walk_selectors(v, &s.selectors);
+        }
+        Factor::Set(s) => {
+            for e in s.elements.iter() {
+                v.visit_expression(&e.low);
+
+                if let Some(high) = &e.high {
+                    v.visit_expression(high);
+                }
+            }
+        }
+        Factor::Not(operand) => v.visit_factor(operand),
+        Factor::Expression(e) => v.visit_expression(e),
+        Factor::Integer(_)
+        | Factor::Real(_)
+        | Factor::Char(_)
+        | Factor::String(_)
+        | Factor::Nil(_) => (),
+    }
+}
+
+pub fn walk_call<V: Visitor + ?Sized>(v: &mut V, c: &Call) {
+    v.visit_identifier(&c.name);
+
+    for arg in c.arguments.iter() {
+        v.visit_expression(arg);
+    }
+    for (_, format) in c.formats.iter() {
+        v.visit_write_format(format);
+    }
+}
+
+pub fn walk_selectors<V: Visitor + ?Sized>(v: &mut V, selectors: &[Selector]) {
+    for s in selectors.iter() {
+        match s {
+            Selector::Index(e) => v.visit_expression(e),
+            Selector::Field(id) => v.visit_identifier(id),
+            Selector::Deref(_) => (),
+        }
+    }
+}
@@ -0,0 +1,481 @@
+// ISO 7185 conformance suite. Each program under tests/conformance uses one
+// feature of the standard and is compiled with `--conformance=iso7185`; the
+// tables record which features the compiler supports so far. When a feature
+// lands, its program starts compiling and its entry must be flipped.
+
+use pascal_compiler::completion::completions;
+use pascal_compiler::driver::{parse_expression, Compiler, ScopeContext};
+use pascal_compiler::hover::type_at;
+use pascal_compiler::io::CharReader;
+use pascal_compiler::lexer::Lexer;
+use pascal_compiler::options::{Conformance, Options};
+use pascal_compiler::snippet::render_diagnostic;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Status {
+    Supported,
+    Missing,
+}
+
+use Status::*;
+
+const FEATURES: &[(&str, Status)] = &[
+    ("program_heading", Supported),
+    ("comments", Supported),
+    ("constants", Supported),
+    ("enumerated_types", Supported),
+    ("arrays", Supported),
+    ("records", Supported),
+    ("sets", Supported),
+    ("pointers", Supported),
+    ("procedures", Supported),
+    ("forward_declarations", Supported),
+    ("statements", Supported),
+    ("boolean_operators", Supported),
+    ("integer_arithmetic", Supported),
+    ("unary_minus", Supported),
+    ("real_division", Missing),
+    ("subrange_types", Supported),
+    ("with_statement", Missing),
+    ("goto_labels", Missing),
+    ("text_output", Supported),
+    ("standard_functions", Supported),
+    ("packed_arrays", Missing),
+    ("file_types", Missing),
+    ("variant_records", Supported),
+    ("procedural_parameters", Missing),
+];
+
+// Accepted by default, but not by ISO 7185, so they must be rejected in that mode
+const EXTENSIONS: &[&str] = &[
+    "string_type",
+    "char_codes",
+    "address_of",
+    "missing_program_parameters",
+    "reordered_sections",
+    "text_after_program",
+    "hex_literals",
+    "uses_clause",
+    "inc_dec",
+    "bounded_strings",
+    "line_comments",
+    "counter",
+    "uses_unit_file",
+    "include_file",
+];
+
+fn compiles(path: &Path, conformance: Conformance) -> bool {
+    // Units are found next to the programs using them, included files are read
+    // from the program's directory
+    let options = Options {
+        conformance,
+        unit_path: path.parent().into_iter().map(Path::to_path_buf).collect(),
+        ..Options::default()
+    };
+
+    let result = Compiler::new(options)
+        .compile_file(&path.display().to_string())
+        .expect("failed to read conformance program");
+    !result.has_errors()
+}
+
+// Every program has an entry, so none is silently left out
+fn listed_programs(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .expect("failed to list conformance programs")
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            (path.extension()? == "pas").then(|| path.file_stem()?.to_str().map(String::from))?
+        })
+        .collect();
+
+    names.sort();
+    names
+}
+
+#[test]
+fn iso7185_features() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance");
+
+    let mut expected: Vec<String> = FEATURES.iter().map(|(name, _)| name.to_string()).collect();
+    expected.sort();
+    assert_eq!(listed_programs(&dir), expected);
+
+    let mismatches: Vec<String> = FEATURES
+        .iter()
+        .filter_map(|&(name, status)| {
+            let actual = match compiles(&dir.join(format!("{}.pas", name)), Conformance::Iso7185) {
+                true => Supported,
+                false => Missing,
+            };
+
+            (actual != status)
+                .then(|| format!("{}: recorded {:?}, found {:?}", name, status, actual))
+        })
+        .collect();
+
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+}
+
+#[test]
+fn iso7185_rejects_extensions() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance/extensions");
+
+    let mut expected: Vec<String> = EXTENSIONS.iter().map(|name| name.to_string()).collect();
+    expected.sort();
+    assert_eq!(listed_programs(&dir), expected);
+
+    for name in EXTENSIONS {
+        let path = dir.join(format!("{}.pas", name));
+
+        assert!(
+            compiles(&path, Conformance::Extended),
+            "{} fails by default",
+            name
+        );
+        assert!(
+            !compiles(&path, Conformance::Iso7185),
+            "{} passes in ISO mode",
+            name
+        );
+    }
+}
+
+// Source text from memory, e.g. of the playground, reads no files
+#[test]
+fn in_memory_source_includes_nothing() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance/extensions");
+    let include = dir.join("include_file.inc").display().to_string();
+    let source = format!("program sum(output);\n{{$I {}}}\nbegin\nend.\n", include);
+
+    let result = Compiler::new(Options::default()).compile_source(&source);
+    assert!(result
+        .diagnostics
+        .iter()
+        .any(|d| d.message().contains("include files need a source file")));
+}
+
+#[test]
+fn records_declared_apart_are_different_types() {
+    let source = "program copy(output);
+type r1 = record a: integer end;
+     r2 = record a: integer end;
+     same = r1;
+var x: r1; y: r2; z: same;
+begin
+  x := z;
+  x := y
+end.
+";
+
+    let result = Compiler::new(Options::default()).compile_source(source);
+    let errors: Vec<_> = result
+        .diagnostics
+        .iter()
+        .filter(|d| d.message().contains("Cannot assign"))
+        .collect();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message().contains("r2"));
+}
+
+#[test]
+fn for_loop_body_cannot_change_its_variable() {
+    let source = "program loops(output);
+var i, j: integer;
+procedure bump(var x: integer); begin x := x + 1 end;
+begin
+  for i := 1 to 2 do i := 5;
+  for i := 1 to 2 do bump(i);
+  for i := 1 to 2 do begin j := i; bump(j) end;
+  i := 7
+end.
+";
+
+    let result = Compiler::new(Options::default()).compile_source(source);
+    let lines: Vec<_> = result
+        .diagnostics
+        .iter()
+        .filter(|d| d.message().contains("control variable \"i\""))
+        .map(|d| d.pos().0)
+        .collect();
+    assert_eq!(lines, [5, 6]);
+}
+
+#[test]
+fn fragments_follow_the_options() {
+    let options = Options {
+        conformance: Conformance::Iso7185,
+        ..Options::default()
+    };
+    let context = ScopeContext::builtins(&options);
+
+    assert!(parse_expression("$ff + 1", &context, &Options::default()).is_ok());
+    let errors = parse_expression("$ff + 1", &context, &options).unwrap_err();
+    assert!(errors[0].message().contains("not part of ISO 7185"));
+}
+
+#[test]
+fn alias_of_itself_is_unknown() {
+    for types in ["A = A;", "A = B; B = A;"] {
+        let source = format!(
+            "program loop;\ntype {}\nvar x: A;\nbegin\n  x := 1\nend.\n",
+            types
+        );
+
+        let result = Compiler::new(Options::default()).compile_source(&source);
+        assert!(result
+            .diagnostics
+            .iter()
+            .any(|d| d.message() == "Unknown identifier \"A\"" && d.pos().0 == 3));
+    }
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    let n = 10_000;
+    let sources = [
+        format!(
+            "var x: integer;\nbegin\n  x := {}1{}\nend.",
+            "(".repeat(n),
+            ")".repeat(n)
+        ),
+        format!(
+            "type t = {}integer{};\nbegin\nend.",
+            "record a: ".repeat(n),
+            " end".repeat(n)
+        ),
+        format!(
+            "type t = {}integer;\nbegin\nend.",
+            "array[1..2] of ".repeat(n)
+        ),
+        format!("type t = {}char;\nbegin\nend.", "set of ".repeat(n)),
+        format!(
+            "type t = record {}a: integer{} end;\nbegin\nend.",
+            "case integer of 1: (".repeat(n),
+            ")".repeat(n)
+        ),
+        format!(
+            "var t: {}integer;\nbegin\nend.",
+            "array[1..2] of ".repeat(n)
+        ),
+    ];
+
+    for source in sources {
+        let source = format!("program deep;\n{}\n", source);
+        // The default limit needs more stack than a test thread has in debug builds
+        let options = Options {
+            max_expression_depth: 64,
+            ..Options::default()
+        };
+        let result = Compiler::new(options).compile_source(&source);
+        let messages: Vec<_> = result.diagnostics.iter().map(|d| d.message()).collect();
+
+        assert!(messages[0].contains("too deeply nested"), "{:?}", messages);
+    }
+}
+
+#[test]
+fn enumerations_spelled_alike_are_different_types() {
+    let source = "program colors;
+type color = (red, green, blue);
+var c: color;
+procedure paint;
+type hue = (red, green, blue);
+var h: hue;
+begin
+  h := red;
+  c := h
+end;
+begin
+  c := red
+end.
+";
+
+    let result = Compiler::new(Options::default()).compile_source(source);
+    let lines: Vec<_> = result.diagnostics.iter().map(|d| d.pos().0).collect();
+    assert_eq!(lines, [9]);
+}
+
+#[test]
+fn constant_comparisons_are_exact() {
+    // A subrange from true is empty exactly when its upper bound is false
+    let source = "program compare;
+const same = 16777217 = 16777216;
+      larger = 16777217 > 16777216.0;
+type never = true..same;
+     always = true..larger;
+begin
+end.
+";
+
+    let result = Compiler::new(Options::default()).compile_source(source);
+    let lines: Vec<_> = result.diagnostics.iter().map(|d| d.pos().0).collect();
+    assert_eq!(lines, [4]);
+}
+
+#[test]
+fn array_index_must_match_its_type() {
+    let source = "program index(output);
+type color = (red, green, blue);
+var shades: array[color] of integer;
+    flags: array[boolean] of char;
+    row: array[1..3] of real;
+    wrong: array[real] of integer;
+begin
+  shades[1] := 2;
+  flags[red] := 'n';
+  row[4] := 1.0
+end.
+";
+
+    let result = Compiler::new(Options::default()).compile_source(source);
+    let lines: Vec<_> = result.diagnostics.iter().map(|d| d.pos().0).collect();
+    assert_eq!(lines, [6, 8, 9, 10]);
+}
+
+#[test]
+fn caret_points_at_the_token() {
+    let source = "program caret;\nvar x: integer;\nbegin\n  x := zz\nend.\n";
+
+    let result = Compiler::new(Options::default()).compile_source(source);
+    let snippet = render_diagnostic(source, &result.diagnostics[0], false);
+    let line = snippet.lines().find(|l| l.contains("x := zz")).unwrap();
+    let caret = snippet.lines().find(|l| l.ends_with('^')).unwrap();
+    assert_eq!(caret.find('^'), line.find("zz"));
+}
+
+#[test]
+fn doubled_quote_is_one_apostrophe() {
+    for (source, literal) in [("''''", r#""'""#), ("'it''s'", r#""it's""#)] {
+        let tokens: Vec<_> = Lexer::new(CharReader::from_source(source))
+            .map(|t| format!("{:?}", t.unwrap()))
+            .collect();
+
+        assert_eq!(tokens.len(), 1, "{:?}", tokens);
+        assert!(
+            tokens[0].contains(&format!("StringLiteral({})", literal)),
+            "{:?}",
+            tokens
+        );
+    }
+}
+
+#[test]
+fn unreadable_line_is_an_error() {
+    let path = std::env::temp_dir().join("pascal_compiler_invalid_utf8.pas");
+    fs::write(
+        &path,
+        b"program bytes;\nvar x: integer;\nbegin\n  x := \xff\xfe;\n  x := 2\nend.\n",
+    )
+    .expect("failed to write the test program");
+
+    let result = Compiler::new(Options::default())
+        .compile_file(&path.display().to_string())
+        .expect("failed to open the test program");
+    fs::remove_file(&path).ok();
+
+    assert!(result
+        .diagnostics
+        .iter()
+        .any(|d| d.message().starts_with("Can't read line 4")));
+}
+
+#[test]
+fn membership_needs_a_set_of_the_element_type() {
+    let source = "program member;
+var s: set of char; c: char; n: integer; b: boolean;
+begin
+  b := c in s;
+  b := n in s;
+  b := c in n
+end.
+";
+
+    let result = Compiler::new(Options::default()).compile_source(source);
+    let lines: Vec<_> = result.diagnostics.iter().map(|d| d.pos().0).collect();
+    assert_eq!(lines, [5, 6]);
+}
+
+#[test]
+fn range_directive_applies_from_where_it_is() {
+    let source = "program look;\nvar k: 1..10;\nbegin\n  k := 30 {$R-};\n  k := 40;\n  k := {$R+} 50\nend.\n";
+
+    let result = Compiler::new(Options::default()).compile_source(source);
+    let errors: Vec<_> = result
+        .diagnostics
+        .iter()
+        .filter(|d| d.is_error())
+        .map(|d| (d.message(), d.pos()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            ("Value 30 is out of range 1..10", (4, 8)),
+            ("Value 50 is out of range 1..10", (6, 14)),
+        ]
+    );
+}
+
+#[test]
+fn unterminated_comment_is_reported_once() {
+    let cases = [
+        (
+            "program c;\nbegin\nend. { open\n",
+            vec![("Unterminated comment, expected '}'", (3, 6))],
+        ),
+        (
+            "program c;\nbegin\nend. (* open\n",
+            vec![("Unterminated comment, expected '*)'", (3, 6))],
+        ),
+        // The source ends in the comment, as if it were cut off there
+        (
+            "program c;\nvar x: integer;\nbegin\n  x := 1 { open\n",
+            vec![
+                ("Unterminated comment, expected '}'", (4, 10)),
+                ("Unexpected EOF", (4, 8)),
+                ("Unexpected EOF, expected 'end'", (4, 8)),
+            ],
+        ),
+    ];
+
+    for (source, expected) in cases {
+        let result = Compiler::new(Options::default()).compile_source(source);
+        let diagnostics: Vec<_> = result
+            .diagnostics
+            .iter()
+            .map(|d| (d.message(), d.pos()))
+            .collect();
+        assert_eq!(diagnostics, expected);
+    }
+}
+
+#[test]
+fn hover_follows_the_options() {
+    let source = "program h;\nvar Count: integer;\nbegin\n  count := 1\nend.\n";
+    let turbo = Options {
+        conformance: Conformance::TurboPascal7,
+        ..Options::default()
+    };
+
+    let found = type_at(source, (4, 3), &turbo).map(|t| t.to_string());
+    assert_eq!(found.as_deref(), Some("variable count : integer"));
+    assert!(type_at(source, (4, 3), &Options::default()).is_none());
+}
+
+#[test]
+fn completions_are_declared_before_the_cursor() {
+    let source = "program c;\nvar total: integer;\nprocedure first;\nbegin\n  T\nend;\nprocedure third;\nbegin\nend;\nbegin\n  Wri\nend.\n";
+    let names = |pos| -> Vec<String> {
+        completions(source, pos, &Options::default())
+            .into_iter()
+            .map(|c| c.name)
+            .collect()
+    };
+
+    let in_first = names((5, 4));
+    assert!(in_first.contains(&"total".to_string()), "{:?}", in_first);
+    assert!(!in_first.contains(&"third".to_string()), "{:?}", in_first);
+    assert_eq!(names((11, 6)), ["write", "writeln"]);
+}
@@ -0,0 +1,23 @@
+program arrays(output);
+type
+  row = array[1..3] of integer;
+  color = (red, green, blue);
+  index = 1..3;
+var
+  grid : array[1..3] of row;
+  shades : array[color] of integer;
+  flags : array[boolean] of char;
+  counts : array[char] of integer;
+  cells : array[index, color] of boolean;
+  sizes : array[(small, large)] of integer;
+  i, j : integer;
+begin
+  for i := 1 to 3 do
+    for j := 1 to 3 do
+      grid[i][j] := i * j;
+  shades[green] := 2;
+  flags[true] := 'y';
+  counts['a'] := 0;
+  cells[2, blue] := false;
+  sizes[large] := 10
+end.
@@ -0,0 +1,12 @@
+program booleans(output);
+var
+  a, b : boolean;
+begin
+  a := true;
+  b := not a or a and false;
+  if not b then
+    a := b <> a;
+  b := not (a = b);
+  if (a = b) or (a <> false) then
+    b := (a < b) and (b <= a)
+end.
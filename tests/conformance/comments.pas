@@ -0,0 +1,9 @@
+program comments(output);
+{ a brace comment
+  spanning lines }
+var
+  x : integer; (* a starred comment *)
+begin
+  x := { inline } 1 (* spanning
+  lines *)
+end.
@@ -0,0 +1,12 @@
+program constants(output);
+const
+  size = 10;
+  twice = size * 2;
+  letter = 'a';
+  ready = true;
+  ratio = 0.5;
+var
+  n : integer;
+begin
+  n := twice
+end.
@@ -0,0 +1,10 @@
+program enumerated(output);
+type
+  colour = (red, green, blue);
+var
+  c : colour;
+begin
+  c := green;
+  if c = blue then
+    c := red
+end.
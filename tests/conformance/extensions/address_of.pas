@@ -0,0 +1,7 @@
+program address(output);
+var
+  x : integer;
+  p : ^integer;
+begin
+  p := @x
+end.
@@ -0,0 +1,6 @@
+program names(output);
+var
+  s : string[20];
+begin
+  s := 'abc'
+end.
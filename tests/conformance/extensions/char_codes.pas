@@ -0,0 +1,6 @@
+program codes(output);
+var
+  c : char;
+begin
+  c := #65
+end.
@@ -0,0 +1,26 @@
+unit Counter;
+
+interface
+
+var
+  count : integer;
+
+procedure Reset;
+function Next(step : integer) : integer;
+
+implementation
+
+procedure Reset;
+begin
+  count := 0
+end;
+
+function Next;
+begin
+  count := count + step;
+  Next := count
+end;
+
+begin
+  Reset
+end.
@@ -0,0 +1,6 @@
+program hex(output);
+var
+  i : integer;
+begin
+  i := $FF
+end.
@@ -0,0 +1,8 @@
+program step(output);
+var
+  i : integer;
+begin
+  i := 0;
+  inc(i);
+  dec(i, 2)
+end.
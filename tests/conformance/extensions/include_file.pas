@@ -0,0 +1,5 @@
+program sum(output);
+{$I include_file.inc}
+begin
+  total := size * 2
+end.
@@ -0,0 +1,6 @@
+program comments(output);
+var
+  i : integer; // a line comment
+begin
+  i := 1 // up to the end of the line
+end.
@@ -0,0 +1,6 @@
+program bare;
+var
+  x : integer;
+begin
+  x := 1
+end.
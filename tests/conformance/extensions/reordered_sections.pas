@@ -0,0 +1,8 @@
+program reordered(output);
+var
+  x : integer;
+const
+  k = 1;
+begin
+  x := k
+end.
@@ -0,0 +1,6 @@
+program strings(output);
+var
+  s : string;
+begin
+  s := 'abc'
+end.
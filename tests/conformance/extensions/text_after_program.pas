@@ -0,0 +1,7 @@
+program trailing(output);
+var
+  x : integer;
+begin
+  x := 1
+end.
+x := 2
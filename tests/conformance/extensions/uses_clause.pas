@@ -0,0 +1,5 @@
+program screen(output);
+uses crt;
+begin
+  clrscr
+end.
@@ -0,0 +1,8 @@
+program tally(output);
+uses Counter;
+var total : integer;
+begin
+  Reset;
+  total := Next(2) + Next(3);
+  count := total
+end.
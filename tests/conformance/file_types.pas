@@ -0,0 +1,6 @@
+program files(output, data);
+var
+  data : file of integer;
+begin
+  rewrite(data)
+end.
@@ -0,0 +1,27 @@
+program parity(output);
+var
+  n : integer;
+  even : boolean;
+
+function isodd(k : integer) : boolean; forward;
+
+function iseven(k : integer) : boolean;
+begin
+  if k = 0 then
+    iseven := true
+  else
+    iseven := isodd(k - 1)
+end;
+
+function isodd;
+begin
+  if k = 0 then
+    isodd := false
+  else
+    isodd := iseven(k - 1)
+end;
+
+begin
+  n := 10;
+  even := iseven(n)
+end.
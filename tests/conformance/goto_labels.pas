@@ -0,0 +1,11 @@
+program labels(output);
+label 1;
+var
+  x : integer;
+begin
+  x := 0;
+  goto 1;
+  x := 1;
+1:
+  x := 2
+end.
@@ -0,0 +1,7 @@
+program arithmetic(output);
+var
+  a, b : integer;
+begin
+  a := 17;
+  b := a div 5 + a mod 5 * 2
+end.
@@ -0,0 +1,6 @@
+program packing(output);
+var
+  name : packed array[1..5] of char;
+begin
+  name[1] := 'a'
+end.
@@ -0,0 +1,15 @@
+program pointers(output);
+type
+  link = ^node;
+  node = record
+    value : integer;
+    next : link
+  end;
+var
+  head : link;
+begin
+  new(head);
+  head^.value := 1;
+  head^.next := nil;
+  dispose(head)
+end.
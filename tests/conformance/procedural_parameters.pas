@@ -0,0 +1,12 @@
+program procedural(output);
+var
+  x : integer;
+
+function apply(function f(n : integer) : integer; n : integer) : integer;
+begin
+  apply := f(n)
+end;
+
+begin
+  x := 0
+end.
@@ -0,0 +1,22 @@
+program procedures(output);
+var
+  total : integer;
+
+procedure add(var sum : integer; n : integer);
+begin
+  sum := sum + n
+end;
+
+function square(n : integer) : integer;
+  function id(k : integer) : integer;
+  begin
+    id := k
+  end;
+begin
+  square := id(n) * n
+end;
+
+begin
+  total := 0;
+  add(total, square(3))
+end.
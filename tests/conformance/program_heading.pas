@@ -0,0 +1,6 @@
+program heading(input, output, data);
+var
+  data : integer;
+begin
+  data := 0
+end.
@@ -0,0 +1,6 @@
+program division(output);
+var
+  x : real;
+begin
+  x := 1 / 3
+end.
@@ -0,0 +1,11 @@
+program records(output);
+type
+  point = record
+    x, y : real
+  end;
+var
+  p : point;
+begin
+  p.x := 1.0;
+  p.y := p.x + 2
+end.
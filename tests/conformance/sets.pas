@@ -0,0 +1,19 @@
+program sets(output);
+type
+  digits = set of char;
+  color = (red, green, blue);
+var
+  s : digits;
+  hues : set of color;
+  found : boolean;
+  c : char;
+begin
+  s := ['0'..'9'];
+  s := s + ['a'] - [];
+  found := s = [];
+  c := '5';
+  found := c in s;
+  hues := [red, blue];
+  if (green in hues) or not (c in ['a'..'z']) then
+    found := red in [red]
+end.
@@ -0,0 +1,7 @@
+program functions(output);
+var
+  i : integer;
+begin
+  i := ord('a') + abs(i) + sqr(2);
+  i := trunc(1.5)
+end.
@@ -0,0 +1,24 @@
+program statements(output);
+var
+  i, n : integer;
+  c : char;
+begin
+  n := 0;
+  for i := 10 downto 1 do
+    n := n + i;
+  while n > 0 do
+    n := n - 7;
+  repeat
+    n := n + 1
+  until n >= 3;
+  c := 'b';
+  case c of
+    'a': n := 1;
+    'b', 'c': n := 2
+  end;
+  if n = 2 then
+    begin
+    end
+  else
+    n := 0
+end.
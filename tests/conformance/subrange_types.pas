@@ -0,0 +1,14 @@
+program subrange(output);
+type
+  small = 1..10;
+  flag = false..true;
+  letter = 'a'..'z';
+var
+  x : small;
+  f : flag;
+  c : letter;
+begin
+  x := 1;
+  for f := false to true do
+    c := 'q'
+end.
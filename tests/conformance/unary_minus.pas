@@ -0,0 +1,8 @@
+program unary(output);
+const
+  low = -5;
+var
+  x : integer;
+begin
+  x := -low
+end.
@@ -0,0 +1,12 @@
+program variants(output);
+type
+  shape = record
+    case round : boolean of
+      true: (radius : real);
+      false: (width, height : real)
+  end;
+var
+  s : shape;
+begin
+  s.round := true
+end.
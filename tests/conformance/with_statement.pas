@@ -0,0 +1,9 @@
+program withs(output);
+var
+  p : record
+    x, y : integer
+  end;
+begin
+  with p do
+    x := y
+end.